@@ -0,0 +1,16 @@
+// grpc featureが有効な場合は、proto/skyway_caller.protoからgRPCのコードを生成する
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/skyway_caller.proto");
+        // 古いprotocでもproto3のoptionalを利用できるようにする
+        // clientは他の言語で生成するので、serverのみを生成する
+        // (clientを生成すると、Connect RPCのメソッドが接続用のconnect関数と衝突する)
+        tonic_build::configure()
+            .build_client(false)
+            .protoc_arg("--experimental_allow_proto3_optional")
+            .compile(&["proto/skyway_caller.proto"], &["proto"])
+            .expect("failed to compile proto/skyway_caller.proto");
+    }
+}
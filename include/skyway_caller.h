@@ -0,0 +1,84 @@
+/*
+ * C interface of skyway-webrtc-gateway-caller.
+ * Build the shared library with `cargo build --release --lib --features ffi`.
+ *
+ * Memory ownership:
+ * - Strings given as arguments are owned by the caller.
+ *   They are not referenced after the function returns.
+ * - The string given to a callback is owned by the library.
+ *   It is valid only while the callback runs. Copy it to keep it.
+ * - The buffer given to skyway_caller_poll_event is owned by the caller.
+ *   The library writes at most `len` bytes to it.
+ * - A handle is allocated by skyway_caller_start and released by skyway_caller_stop.
+ * - user_data is owned by the caller. The library only passes it to the callback.
+ *
+ * A panic inside the library is not propagated to C. It is reported as an error return value.
+ */
+
+#ifndef SKYWAY_CALLER_H
+#define SKYWAY_CALLER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Returned from skyway_caller_poll_event when the handle is invalid, no more events will arrive,
+ * or the library failed internally.
+ * It is INT64_MIN, so that it is never confused with the negated buffer size returned when the buffer is too small.
+ */
+#define SKYWAY_CALLER_CLOSED INT64_MIN
+
+/* Returned from skyway_caller_send when the library failed internally */
+#define SKYWAY_CALLER_INTERNAL_ERROR (-2)
+
+/* Opaque handle of a running instance. It owns its own tokio runtime */
+typedef struct SkywayCaller SkywayCaller;
+
+/*
+ * Receives the result of skyway_caller_send as a JSON string, and the user_data given to it.
+ * It is invoked on a worker thread of the library.
+ */
+typedef void (*SkywayCallerCallback)(const char *response, void *user_data);
+
+/*
+ * Start WebRTC Gateway operation.
+ * Returns NULL when base_url is NULL or not UTF-8, or the runtime cannot be created.
+ */
+SkywayCaller *skyway_caller_start(const char *base_url);
+
+/*
+ * Give a JSON message for operation.
+ * Returns 0 immediately, -1 when the arguments are invalid,
+ * or SKYWAY_CALLER_INTERNAL_ERROR when the library failed internally.
+ * The result is passed to callback later together with user_data. callback may be NULL to ignore the result.
+ * user_data must be valid until callback is invoked.
+ */
+int32_t skyway_caller_send(const SkywayCaller *handle, const char *json, SkywayCallerCallback callback,
+                           void *user_data);
+
+/*
+ * Take an event without blocking and copy it into buf as a NUL-terminated JSON string.
+ * Returns the length of the event excluding NUL, or 0 when there is no event.
+ * When buf is too small, returns the negated buffer size required including NUL,
+ * and the event is kept for the next call.
+ * Returns SKYWAY_CALLER_CLOSED when the handle is NULL, no more events will arrive,
+ * or the library failed internally. Compare with SKYWAY_CALLER_CLOSED before treating a negative value as a size.
+ */
+int64_t skyway_caller_poll_event(const SkywayCaller *handle, char *buf, size_t len);
+
+/*
+ * Stop the operation and release the handle.
+ * Callbacks of unfinished skyway_caller_send may not be invoked.
+ * Must not be called inside a callback.
+ */
+void skyway_caller_stop(SkywayCaller *handle);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SKYWAY_CALLER_H */
@@ -1,6 +1,7 @@
 pub mod request_message {
     use serde::de::DeserializeOwned;
     use serde::{Deserialize, Serialize};
+    use serde_json::Value;
     use skyway_webrtc_gateway_api::error;
 
     // ユーザから与えられたJSONをDTOとしてラップする
@@ -24,6 +25,8 @@ pub mod request_message {
         Status { params: Parameter },
         #[serde(rename = "DELETE")]
         Delete { params: Parameter },
+        #[serde(rename = "SUBSCRIBE")]
+        Subscribe { params: Parameter },
     }
 
     // EventはこのEnumを利用しないので不要
@@ -43,6 +46,8 @@ pub mod request_message {
         Disconnect { params: Parameter },
         #[serde(rename = "STATUS")]
         Status { params: Parameter },
+        #[serde(rename = "SUBSCRIBE")]
+        Subscribe { params: Parameter },
     }
 
     // EventはこのEnumを利用しないので不要
@@ -66,6 +71,28 @@ pub mod request_message {
         Disconnect { params: Parameter },
         #[serde(rename = "STATUS")]
         Status { params: Parameter },
+        #[serde(rename = "FANOUT_CREATE")]
+        FanoutCreate { params: Parameter },
+        #[serde(rename = "FANOUT_CALL")]
+        FanoutCall { params: Parameter },
+        #[serde(rename = "FANOUT_DELETE")]
+        FanoutDelete { params: Parameter },
+        #[serde(rename = "SUBSCRIBE")]
+        Subscribe { params: Parameter },
+    }
+
+    // event監視ループの操作
+    // Peer, Data, Mediaのいずれのリソースも対象とするため、独立したtypeとして扱う
+    #[allow(non_camel_case_types)]
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(tag = "command")]
+    pub enum ListenerServiceParams {
+        #[serde(rename = "SUBSCRIBE")]
+        Subscribe { params: Parameter },
+        #[serde(rename = "UNSUBSCRIBE")]
+        Unsubscribe { params: Parameter },
+        #[serde(rename = "LIST")]
+        List,
     }
 
     // JSONでクライアントから受け取るメッセージ
@@ -80,6 +107,27 @@ pub mod request_message {
         Data(DataServiceParams),
         #[serde(rename = "MEDIA")]
         Media(MediaServiceParams),
+        #[serde(rename = "LISTENER")]
+        Listener(ListenerServiceParams),
+    }
+
+    impl ServiceParams {
+        // 結果を返す前にeventを待つコマンドかどうか
+        // 待機中も他のメッセージを処理できるよう、これらは別タスクで実行される
+        pub(crate) fn waits_for_event(&self) -> bool {
+            let wait =
+                |params: &Parameter, key: &str| params.0.get(key) == Some(&Value::Bool(true));
+            match self {
+                ServiceParams::Data(DataServiceParams::Connect { params }) => {
+                    wait(params, "wait_open")
+                }
+                ServiceParams::Media(MediaServiceParams::Call { params })
+                | ServiceParams::Media(MediaServiceParams::Answer { params }) => {
+                    wait(params, "wait_ready")
+                }
+                _ => false,
+            }
+        }
     }
 
     #[cfg(test)]
@@ -130,25 +178,77 @@ pub mod request_message {
                 assert!(false);
             }
         }
+
+        #[test]
+        fn listener_message() {
+            use crate::application::dto::request_message::ListenerServiceParams;
+
+            let message = r#"{
+            "type": "LISTENER",
+            "command": "LIST"
+        }"#;
+
+            let list_message = serde_json::from_str::<ServiceParams>(message);
+            assert_eq!(
+                list_message.unwrap(),
+                ServiceParams::Listener(ListenerServiceParams::List)
+            );
+        }
+
+        #[test]
+        fn waits_for_event() {
+            let message = |params: &str| {
+                let message = format!(
+                    r#"{{"type": "DATA", "command": "CONNECT", "params": {}}}"#,
+                    params
+                );
+                serde_json::from_str::<ServiceParams>(&message).unwrap()
+            };
+
+            // wait_openを指定したCONNECTのみ、eventを待つ
+            assert!(message(r#"{"peer_id": "peer_id", "wait_open": true}"#).waits_for_event());
+            assert!(!message(r#"{"peer_id": "peer_id", "wait_open": false}"#).waits_for_event());
+            assert!(!message(r#"{"peer_id": "peer_id"}"#).waits_for_event());
+
+            // wait_readyを指定したCALL, ANSWERもeventを待つ
+            let message = r#"{"type": "MEDIA", "command": "CALL", "params": {"wait_ready": true}}"#;
+            let message = serde_json::from_str::<ServiceParams>(message).unwrap();
+            assert!(message.waits_for_event());
+            let message =
+                r#"{"type": "MEDIA", "command": "ANSWER", "params": {"wait_ready": true}}"#;
+            let message = serde_json::from_str::<ServiceParams>(message).unwrap();
+            assert!(message.waits_for_event());
+        }
     }
 }
 
 pub mod response_message {
+    use std::str::FromStr;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
     use serde::ser::SerializeStruct;
     use serde::{Deserialize, Serialize, Serializer};
     use serde_json::Value;
 
+    use crate::domain::fanout::entity::{FanoutCallResult, FanoutIdWrapper, FanoutInfo};
+    use crate::domain::listener::entity::{
+        ListenerInfo, ListenerList, ListenerStopped, ListenerTarget,
+    };
     use crate::domain::webrtc::common::value_object::{PeerInfo, SocketInfo};
     use crate::domain::webrtc::data::entity::{
-        DataConnectionEventEnum, DataConnectionIdWrapper, DataConnectionStatus, DataIdWrapper,
+        DataConnectionEventEnum, DataConnectionIdWrapper, DataConnectionResult,
+        DataConnectionStatus, DataIdWrapper, DataSubscribeResult,
     };
-    use crate::domain::webrtc::data::value_object::DataId;
+    use crate::domain::webrtc::data::value_object::{DataConnectionId, DataId};
     use crate::domain::webrtc::media::entity::{
-        AnswerResult, MediaConnectionEventEnum, MediaConnectionIdWrapper, MediaConnectionStatus,
-        MediaIdWrapper, RtcpIdWrapper,
+        AnswerResult, CallResult, MediaConnectionEventEnum, MediaConnectionIdWrapper,
+        MediaConnectionStatus, MediaIdWrapper, MediaSubscribeResult, RtcpIdWrapper,
+    };
+    use crate::domain::webrtc::media::value_object::{MediaConnectionId, MediaId, RtcpId};
+    use crate::domain::webrtc::peer::entity::{
+        PeerCallEvent, PeerCloseEvent, PeerConnectionEvent, PeerErrorEvent, PeerEventEnum,
+        PeerOpenEvent, PeerStatusMessage,
     };
-    use crate::domain::webrtc::media::value_object::{MediaId, RtcpId};
-    use crate::domain::webrtc::peer::entity::{PeerEventEnum, PeerStatusMessage};
     use crate::error;
 
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -162,6 +262,8 @@ pub mod response_message {
         Delete(PeerInfo),
         #[serde(rename = "EVENT")]
         Event(PeerEventEnum),
+        #[serde(rename = "SUBSCRIBE")]
+        Subscribe(PeerInfo),
     }
 
     impl PeerResponse {
@@ -190,15 +292,17 @@ pub mod response_message {
         #[serde(rename = "DELETE")]
         Delete(DataIdWrapper),
         #[serde(rename = "CONNECT")]
-        Connect(DataConnectionIdWrapper),
+        Connect(DataConnectionResult),
         #[serde(rename = "REDIRECT")]
-        Redirect(DataConnectionIdWrapper),
+        Redirect(DataConnectionResult),
         #[serde(rename = "DISCONNECT")]
         Disconnect(DataConnectionIdWrapper),
         #[serde(rename = "EVENT")]
         Event(DataConnectionEventEnum),
         #[serde(rename = "STATUS")]
         Status(DataConnectionStatus),
+        #[serde(rename = "SUBSCRIBE")]
+        Subscribe(DataSubscribeResult),
     }
 
     impl DataResponse {
@@ -235,7 +339,7 @@ pub mod response_message {
         #[serde(rename = "RTCP_DELETE")]
         RtcpDelete(RtcpIdWrapper),
         #[serde(rename = "CALL")]
-        Call(MediaConnectionIdWrapper),
+        Call(CallResult),
         #[serde(rename = "ANSWER")]
         Answer(AnswerResult),
         #[serde(rename = "DISCONNECT")]
@@ -244,6 +348,14 @@ pub mod response_message {
         Event(MediaConnectionEventEnum),
         #[serde(rename = "STATUS")]
         Status(MediaConnectionStatus),
+        #[serde(rename = "FANOUT_CREATE")]
+        FanoutCreate(FanoutInfo),
+        #[serde(rename = "FANOUT_CALL")]
+        FanoutCall(FanoutCallResult),
+        #[serde(rename = "FANOUT_DELETE")]
+        FanoutDelete(FanoutIdWrapper),
+        #[serde(rename = "SUBSCRIBE")]
+        Subscribe(MediaSubscribeResult),
     }
 
     impl MediaResponse {
@@ -268,6 +380,25 @@ pub mod response_message {
         }
     }
 
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(tag = "command")]
+    pub enum ListenerResponse {
+        #[serde(rename = "SUBSCRIBE")]
+        Subscribe(ListenerTarget),
+        #[serde(rename = "UNSUBSCRIBE")]
+        Unsubscribe(ListenerInfo),
+        #[serde(rename = "LIST")]
+        List(ListenerList),
+        #[serde(rename = "LISTENER_STOPPED")]
+        Stopped(ListenerStopped),
+    }
+
+    impl ListenerResponse {
+        pub fn create_response_message(self) -> ResponseResult {
+            ResponseResult::Success(ResponseMessage::Listener(self))
+        }
+    }
+
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
     #[serde(tag = "request_type")]
     pub enum ResponseMessage {
@@ -277,6 +408,8 @@ pub mod response_message {
         Data(DataResponse),
         #[serde(rename = "MEDIA")]
         Media(MediaResponse),
+        #[serde(rename = "LISTENER")]
+        Listener(ListenerResponse),
     }
 
     // JSONでクライアントから受け取るメッセージ
@@ -334,6 +467,163 @@ pub mod response_message {
         }
     }
 
+    // 監視ループから通知されたeventと、その送信元
+    // 通知順に番号を振るため、EventEnvelopeへの変換はPresentation層の手前の1箇所でのみ行う
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct SourcedEvent {
+        pub(crate) source: ListenerInfo,
+        pub(crate) peer_id: Option<String>,
+        pub(crate) event: ResponseResult,
+    }
+
+    // is_success, resultは展開されるので、ResponseResult::from_strでもparseできる
+    // EventEnvelopeとしては、str::parseで復元する
+    /// Event with metadata to reconstruct the timeline of a session
+    #[derive(Serialize, Debug, Clone, PartialEq)]
+    pub struct EventEnvelope {
+        /// Sequence number which starts from 1 and increases by 1 for each event
+        pub seq: u64,
+        /// Milliseconds since the UNIX epoch when the event was delivered
+        pub timestamp: u64,
+        /// Listener which observed the event
+        pub source: ListenerInfo,
+        /// Peer which owns the resource, if known
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub peer_id: Option<String>,
+        /// Number of events the receiver missed since the previous event it received, if any
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub dropped: Option<u64>,
+        #[serde(flatten)]
+        pub event: ResponseResult,
+    }
+
+    impl EventEnvelope {
+        pub(crate) fn new(seq: u64, event: SourcedEvent) -> Self {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or(0);
+            EventEnvelope {
+                seq,
+                timestamp,
+                source: event.source,
+                peer_id: event.peer_id,
+                dropped: None,
+                event: event.event,
+            }
+        }
+    }
+
+    impl FromStr for EventEnvelope {
+        type Err = serde_json::Error;
+
+        fn from_str(json: &str) -> Result<Self, Self::Err> {
+            #[derive(Deserialize)]
+            struct Envelope {
+                seq: u64,
+                timestamp: u64,
+                source: ListenerInfo,
+                #[serde(default)]
+                peer_id: Option<String>,
+                #[serde(default)]
+                dropped: Option<u64>,
+                is_success: bool,
+                result: Value,
+            }
+            let envelope = serde_json::from_str::<Envelope>(json)?;
+            let event = match envelope.is_success {
+                true => ResponseResult::Success(serde_json::from_value(envelope.result)?),
+                false => ResponseResult::Error(serde_json::from_value(envelope.result)?),
+            };
+            Ok(EventEnvelope {
+                seq: envelope.seq,
+                timestamp: envelope.timestamp,
+                source: envelope.source,
+                peer_id: envelope.peer_id,
+                dropped: envelope.dropped,
+                event,
+            })
+        }
+    }
+
+    // eventのReceiverから受け取ったメッセージを、ネストしたenumをmatchせずに扱えるようにする
+    /// Event from WebRTC Gateway flattened for Rust consumers
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum GatewayEvent {
+        PeerOpen(PeerOpenEvent),
+        PeerConnection(PeerConnectionEvent),
+        PeerCall(PeerCallEvent),
+        PeerClose(PeerCloseEvent),
+        PeerError(PeerErrorEvent),
+        DataOpen(DataConnectionIdWrapper),
+        DataClose(DataConnectionIdWrapper),
+        DataError {
+            data_connection_id: DataConnectionId,
+            message: String,
+        },
+        MediaReady(MediaConnectionIdWrapper),
+        MediaStream(MediaConnectionIdWrapper),
+        MediaClose(MediaConnectionIdWrapper),
+        MediaError {
+            media_connection_id: MediaConnectionId,
+            message: String,
+        },
+        /// A listener gave up listening
+        ListenerStopped(ListenerStopped),
+        /// A listener failed to start or returned an error
+        ListenerError(String),
+    }
+
+    impl GatewayEvent {
+        // TIMEOUTや、eventではないメッセージの場合はNoneを返す
+        pub(crate) fn from_response(result: ResponseResult) -> Option<GatewayEvent> {
+            let message = match result {
+                ResponseResult::Success(message) => message,
+                ResponseResult::Error(message) => {
+                    return Some(GatewayEvent::ListenerError(message))
+                }
+            };
+            let event = match message {
+                ResponseMessage::Peer(PeerResponse::Event(event)) => match event {
+                    PeerEventEnum::OPEN(event) => GatewayEvent::PeerOpen(event),
+                    PeerEventEnum::CONNECTION(event) => GatewayEvent::PeerConnection(event),
+                    PeerEventEnum::CALL(event) => GatewayEvent::PeerCall(event),
+                    PeerEventEnum::CLOSE(event) => GatewayEvent::PeerClose(event),
+                    PeerEventEnum::ERROR(event) => GatewayEvent::PeerError(event),
+                    PeerEventEnum::TIMEOUT => return None,
+                },
+                ResponseMessage::Data(DataResponse::Event(event)) => match event {
+                    DataConnectionEventEnum::OPEN(event) => GatewayEvent::DataOpen(event),
+                    DataConnectionEventEnum::CLOSE(event) => GatewayEvent::DataClose(event),
+                    DataConnectionEventEnum::ERROR((data_connection_id, message)) => {
+                        GatewayEvent::DataError {
+                            data_connection_id,
+                            message,
+                        }
+                    }
+                    DataConnectionEventEnum::TIMEOUT => return None,
+                },
+                ResponseMessage::Media(MediaResponse::Event(event)) => match event {
+                    MediaConnectionEventEnum::READY(event) => GatewayEvent::MediaReady(event),
+                    MediaConnectionEventEnum::STREAM(event) => GatewayEvent::MediaStream(event),
+                    MediaConnectionEventEnum::CLOSE(event) => GatewayEvent::MediaClose(event),
+                    MediaConnectionEventEnum::ERROR((media_connection_id, message)) => {
+                        GatewayEvent::MediaError {
+                            media_connection_id,
+                            message,
+                        }
+                    }
+                    MediaConnectionEventEnum::TIMEOUT => return None,
+                },
+                ResponseMessage::Listener(ListenerResponse::Stopped(stopped)) => {
+                    GatewayEvent::ListenerStopped(stopped)
+                }
+                _ => return None,
+            };
+            Some(event)
+        }
+    }
+
     #[cfg(test)]
     mod response_message_serialize_deserialize {
         use crate::application::dto::response_message::{
@@ -357,5 +647,109 @@ pub mod response_message {
             //evaluate
             assert_eq!(result, ret_message);
         }
+
+        #[test]
+        fn listener_stopped() {
+            use crate::application::dto::response_message::ListenerResponse;
+            use crate::domain::listener::entity::{ListenerInfo, ListenerKind, ListenerStopped};
+
+            let stopped = ListenerStopped {
+                listener: ListenerInfo::new(ListenerKind::Data, "dc-id"),
+                reason: "reason".into(),
+            };
+            let ret_message = ListenerResponse::Stopped(stopped).create_response_message();
+
+            // ListenerInfoは展開される
+            let message = serde_json::to_value(&ret_message).unwrap();
+            assert_eq!(
+                message,
+                serde_json::json!({
+                    "is_success": true,
+                    "result": {
+                        "request_type": "LISTENER",
+                        "command": "LISTENER_STOPPED",
+                        "kind": "DATA",
+                        "resource_id": "dc-id",
+                        "reason": "reason"
+                    }
+                })
+            );
+
+            let result = ResponseResult::from_str(&message.to_string()).unwrap();
+            assert_eq!(result, ret_message);
+        }
+
+        #[test]
+        fn event_envelope() {
+            use crate::application::dto::response_message::{EventEnvelope, SourcedEvent};
+            use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+
+            let peer_info =
+                PeerInfo::try_create("peer_id", "pt-9749250e-d157-4f80-9ee2-359ce8524308").unwrap();
+            let event =
+                ResponseResult::Success(ResponseMessage::Peer(PeerResponse::Create(peer_info)));
+            let envelope = EventEnvelope::new(
+                1,
+                SourcedEvent {
+                    source: ListenerInfo::new(ListenerKind::Peer, "peer_id"),
+                    peer_id: Some("peer_id".into()),
+                    event: event.clone(),
+                },
+            );
+            let message = serde_json::to_string(&envelope).unwrap();
+
+            // metadataを含めて復元できる
+            assert_eq!(message.parse::<EventEnvelope>().unwrap(), envelope);
+            // 展開されているので、従来通りResponseResultとしても扱える
+            assert_eq!(ResponseResult::from_str(&message).unwrap(), event);
+
+            // 取りこぼしがあった場合のみdroppedを含める
+            assert!(!message.contains("dropped"));
+            let envelope = EventEnvelope {
+                dropped: Some(2),
+                ..envelope
+            };
+            let message = serde_json::to_string(&envelope).unwrap();
+            assert_eq!(message.parse::<EventEnvelope>().unwrap(), envelope);
+        }
+
+        #[test]
+        fn gateway_event() {
+            use crate::application::dto::response_message::{DataResponse, GatewayEvent};
+            use crate::domain::webrtc::data::entity::{
+                DataConnectionEventEnum, DataConnectionIdWrapper,
+            };
+            use crate::domain::webrtc::data::value_object::DataConnectionId;
+            use crate::domain::webrtc::peer::entity::PeerEventEnum;
+
+            let wrapper = DataConnectionIdWrapper {
+                data_connection_id: DataConnectionId::try_create(
+                    "dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c",
+                )
+                .unwrap(),
+            };
+            let event = DataResponse::Event(DataConnectionEventEnum::OPEN(wrapper.clone()))
+                .create_response_message();
+            assert_eq!(
+                GatewayEvent::from_response(event),
+                Some(GatewayEvent::DataOpen(wrapper))
+            );
+
+            // TIMEOUTは通知しない
+            let event = PeerResponse::Event(PeerEventEnum::TIMEOUT).create_response_message();
+            assert_eq!(GatewayEvent::from_response(event), None);
+
+            // eventではないメッセージは通知しない
+            let peer_info =
+                PeerInfo::try_create("peer_id", "pt-9749250e-d157-4f80-9ee2-359ce8524308").unwrap();
+            let event = PeerResponse::Create(peer_info).create_response_message();
+            assert_eq!(GatewayEvent::from_response(event), None);
+
+            let event = ResponseResult::Error("invalid peer_info".into());
+            assert_eq!(
+                GatewayEvent::from_response(event),
+                Some(GatewayEvent::ListenerError("invalid peer_info".into()))
+            );
+        }
     }
 }
@@ -3,12 +3,14 @@ use usecase::factory::service_factory;
 pub(crate) mod dto;
 pub(crate) mod usecase;
 
+use crate::di::SharedState;
 use dto::request_message::ServiceParams;
 use dto::response_message::ResponseResult;
 
-pub(crate) async fn run(params: ServiceParams) -> ResponseResult {
+pub(crate) async fn run(params: ServiceParams, state: &SharedState) -> ResponseResult {
     // 与えられたパラメータに応じて、各UseCaseをサービスとして生成し、同時にパラメータも生成する
-    let (params, service) = service_factory(params);
+    // UseCase間で共有すべき状態はstateとして与えられる
+    let (params, service) = service_factory(params, state);
 
     // UseCaseの実行
     let result = service.execute(params).await;
@@ -1,13 +1,22 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use serde_json::Value;
 use shaku::*;
 
 use crate::application::dto::request_message::Parameter;
 use crate::application::dto::response_message::{DataResponse, ResponseResult};
 use crate::application::usecase::service::Service;
-use crate::domain::webrtc::data::entity::{ConnectQuery, DataConnectionIdWrapper};
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+use crate::domain::listener::repository::{EventPublisher, ListenerRegistry};
+use crate::domain::listener::retry::RetryPolicy;
+use crate::domain::registry::allocation::allocate_data_redirect;
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::webrtc::common::value_object::SerializableSocket;
+use crate::domain::webrtc::data::entity::{ConnectQuery, DataConnectionResult};
 use crate::domain::webrtc::data::repository::DataRepository;
+use crate::domain::webrtc::data::service::{take_wait_open, wait_open};
 use crate::error;
 
 // Serviceの具象Struct
@@ -17,21 +26,91 @@ use crate::error;
 pub(crate) struct ConnectService {
     #[shaku(inject)]
     repository: Arc<dyn DataRepository>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
+    #[shaku(inject)]
+    publisher: Arc<dyn EventPublisher>,
+    #[shaku(default)]
+    retry: RetryPolicy,
+}
+
+impl ConnectService {
+    async fn connect(&self, mut value: Value) -> Result<ResponseResult, error::Error> {
+        let wait = take_wait_open(&mut value);
+        let query = Parameter(value).deserialize::<ConnectQuery>()?;
+        let recv_socket = query.redirect_params.clone();
+        let peer_id = query.peer_id.clone();
+        let data_connection_id = self.repository.connect(query).await?;
+        let mut opened = None;
+        if let Some(timeout) = wait {
+            let result = wait_open(
+                self.repository.clone(),
+                self.listeners.as_ref(),
+                self.publisher.as_ref(),
+                &self.retry,
+                &data_connection_id,
+                timeout,
+            )
+            .await;
+            match result {
+                Ok(event) => opened = event,
+                Err(e) => {
+                    // 利用できないDataConnectionが残らないよう切断する
+                    // CLOSE済みの場合は失敗するが、結果は問わない
+                    let _ = self.repository.disconnect(&data_connection_id).await;
+                    return Err(e);
+                }
+            }
+        }
+        // 確立したDataConnectionのredirect先を記録する
+        if let Some(ref socket) = recv_socket {
+            let address = SocketAddr::new(socket.ip(), socket.port());
+            self.registry
+                .reserve_redirect(data_connection_id.as_str(), vec![address]);
+        }
+        // PeerのCLOSE時に監視を終了できるよう、所有者を記録する
+        self.listeners.set_owner(
+            peer_id.as_str(),
+            ListenerInfo::new(ListenerKind::Data, data_connection_id.as_str()),
+        );
+        // 待機中に取得したOPENは、監視ループが取得したeventと同様に配信する
+        if let Some(event) = opened {
+            self.publisher
+                .publish_data(&data_connection_id, event)
+                .await;
+        }
+        let result = DataConnectionResult {
+            data_connection_id,
+            recv_socket,
+        };
+        Ok(DataResponse::Connect(result).create_response_message())
+    }
 }
 
 #[async_trait]
 impl Service for ConnectService {
     async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
-        let query = params.deserialize::<ConnectQuery>()?;
-        let data_connection_id = self.repository.connect(query).await?;
-        let wrapper = DataConnectionIdWrapper { data_connection_id };
-        Ok(DataResponse::Connect(wrapper).create_response_message())
+        // "auto"と指定されたredirect先にportを割り当てる
+        let mut value = params.0;
+        let allocated = allocate_data_redirect(self.registry.as_ref(), &mut value)?;
+        let result = self.connect(value).await;
+        // connectに失敗した場合は、割り当てたportを開放する
+        if result.is_err() {
+            self.registry.release_ports(&allocated);
+        }
+        result
     }
 }
 
 #[cfg(test)]
 mod test_create_data {
+    use std::sync::Mutex;
+
     use crate::di::DataConnectServiceContainer;
+    use crate::domain::listener::repository::MockEventPublisher;
+    use crate::domain::webrtc::data::entity::{DataConnectionEventEnum, DataConnectionIdWrapper};
     use crate::domain::webrtc::data::repository::MockDataRepository;
     use crate::domain::webrtc::data::value_object::DataConnectionId;
     use crate::domain::webrtc::peer::value_object::{PeerId, Token};
@@ -39,14 +118,30 @@ mod test_create_data {
 
     use super::*;
 
+    // wait_openを指定したCONNECTの引数を生成
+    fn wait_open_message() -> serde_json::Value {
+        let message = ConnectQuery {
+            peer_id: PeerId("peer_id".into()),
+            token: Token::try_create("pt-9749250e-d157-4f80-9ee2-359ce8524308").unwrap(),
+            options: None,
+            target_id: PeerId("target_id".into()),
+            params: None,
+            redirect_params: None,
+        };
+        let mut message = serde_json::to_value(message).unwrap();
+        message["wait_open"] = serde_json::Value::Bool(true);
+        message
+    }
+
     #[tokio::test]
     async fn success() {
         let data_connection_id =
             DataConnectionId::try_create("dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c").unwrap();
 
         // 期待値を生成
-        let expected = DataResponse::Connect(DataConnectionIdWrapper {
+        let expected = DataResponse::Connect(DataConnectionResult {
             data_connection_id: data_connection_id.clone(),
+            recv_socket: None,
         })
         .create_response_message();
 
@@ -79,6 +174,150 @@ mod test_create_data {
         assert_eq!(result, expected);
     }
 
+    #[tokio::test]
+    async fn wait_open() {
+        let data_connection_id =
+            DataConnectionId::try_create("dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c").unwrap();
+
+        // 期待値を生成
+        let expected = DataResponse::Connect(DataConnectionResult {
+            data_connection_id: data_connection_id.clone(),
+            recv_socket: None,
+        })
+        .create_response_message();
+
+        // CONNECTに成功し、OPEN eventを返すMockを作成
+        let mut mock = MockDataRepository::default();
+        let id = data_connection_id.clone();
+        mock.expect_connect().returning(move |_| Ok(id.clone()));
+        let id = data_connection_id.clone();
+        mock.expect_event().times(1).returning(move |_| {
+            Ok(DataConnectionEventEnum::OPEN(DataConnectionIdWrapper {
+                data_connection_id: id.clone(),
+            }))
+        });
+        // OPENした場合は切断されない
+        mock.expect_disconnect().times(0);
+        // 待機中に取得したOPENは配信される
+        let mut publisher = MockEventPublisher::default();
+        let id = data_connection_id.clone();
+        publisher
+            .expect_publish_data()
+            .times(1)
+            .returning(move |data_connection_id, event| {
+                assert_eq!(data_connection_id, &id);
+                assert_eq!(
+                    event,
+                    DataConnectionEventEnum::OPEN(DataConnectionIdWrapper {
+                        data_connection_id: id.clone(),
+                    })
+                );
+            });
+
+        // Mockを埋め込んだServiceを生成
+        let module = DataConnectServiceContainer::builder()
+            .with_component_override::<dyn DataRepository>(Box::new(mock))
+            .with_component_override::<dyn EventPublisher>(Box::new(publisher))
+            .build();
+        let connect_service: Arc<dyn Service> = module.resolve();
+
+        //実行
+        let result = connect_service
+            .execute(Parameter(wait_open_message()))
+            .await
+            .unwrap();
+
+        // evaluate
+        assert_eq!(result, expected);
+    }
+
+    #[tokio::test]
+    async fn open_after_timeout_event() {
+        let data_connection_id =
+            DataConnectionId::try_create("dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c").unwrap();
+
+        // 期待値を生成
+        let expected = DataResponse::Connect(DataConnectionResult {
+            data_connection_id: data_connection_id.clone(),
+            recv_socket: None,
+        })
+        .create_response_message();
+
+        // 1回目はlong pollingのTIMEOUT, 2回目はOPENを返すMockを作成
+        let mut mock = MockDataRepository::default();
+        let id = data_connection_id.clone();
+        mock.expect_connect().returning(move |_| Ok(id.clone()));
+        let counter = Mutex::new(0u8);
+        let id = data_connection_id.clone();
+        mock.expect_event().times(2).returning(move |_| {
+            let mut count = counter.lock().unwrap();
+            *count += 1;
+            if *count == 1 {
+                Ok(DataConnectionEventEnum::TIMEOUT)
+            } else {
+                Ok(DataConnectionEventEnum::OPEN(DataConnectionIdWrapper {
+                    data_connection_id: id.clone(),
+                }))
+            }
+        });
+        // TIMEOUTは失敗ではないので切断されない
+        mock.expect_disconnect().times(0);
+
+        // Mockを埋め込んだServiceを生成
+        let module = DataConnectServiceContainer::builder()
+            .with_component_override::<dyn DataRepository>(Box::new(mock))
+            .build();
+        let connect_service: Arc<dyn Service> = module.resolve();
+
+        //実行
+        let result = connect_service
+            .execute(Parameter(wait_open_message()))
+            .await
+            .unwrap();
+
+        // evaluate
+        assert_eq!(result, expected);
+    }
+
+    #[tokio::test]
+    async fn closed_before_open() {
+        let data_connection_id =
+            DataConnectionId::try_create("dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c").unwrap();
+
+        // CONNECTには成功するが、OPENの前にCLOSEするMockを作成
+        let mut mock = MockDataRepository::default();
+        let id = data_connection_id.clone();
+        mock.expect_connect().returning(move |_| Ok(id.clone()));
+        mock.expect_event().times(1).returning(move |_| {
+            Ok(DataConnectionEventEnum::CLOSE(DataConnectionIdWrapper {
+                data_connection_id: data_connection_id.clone(),
+            }))
+        });
+        // 利用できないDataConnectionは切断される
+        mock.expect_disconnect().times(1).returning(|_| Ok(()));
+
+        // Mockを埋め込んだServiceを生成
+        let module = DataConnectServiceContainer::builder()
+            .with_component_override::<dyn DataRepository>(Box::new(mock))
+            .build();
+        let connect_service: Arc<dyn Service> = module.resolve();
+
+        //実行
+        let result = connect_service
+            .execute(Parameter(wait_open_message()))
+            .await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "DataConnection dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c was closed before OPEN"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+
     #[tokio::test]
     async fn invalid_params() {
         // このMockは呼ばれないので、初期化の必要はない
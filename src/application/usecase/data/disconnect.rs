@@ -6,6 +6,7 @@ use shaku::*;
 use crate::application::dto::request_message::Parameter;
 use crate::application::dto::response_message::{DataResponse, ResponseResult};
 use crate::application::usecase::service::Service;
+use crate::domain::registry::repository::ResourceRegistry;
 use crate::domain::webrtc::data::entity::DataConnectionIdWrapper;
 use crate::domain::webrtc::data::repository::DataRepository;
 use crate::error;
@@ -17,6 +18,8 @@ use crate::error;
 pub(crate) struct DisconnectService {
     #[shaku(inject)]
     repository: Arc<dyn DataRepository>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
 }
 
 #[async_trait]
@@ -26,6 +29,8 @@ impl Service for DisconnectService {
             .deserialize::<DataConnectionIdWrapper>()?
             .data_connection_id;
         let _ = self.repository.disconnect(&data_connection_id).await?;
+        // redirect先のportを他のConnectionで利用できるようにする
+        self.registry.release_redirect(data_connection_id.as_str());
         Ok(
             DataResponse::Disconnect(DataConnectionIdWrapper { data_connection_id })
                 .create_response_message(),
@@ -5,8 +5,13 @@ use shaku::*;
 use tokio::sync::mpsc;
 
 use crate::application::dto::request_message::Parameter;
-use crate::application::dto::response_message::{DataResponse, ResponseResult};
+use crate::application::dto::response_message::{
+    DataResponse, ListenerResponse, ResponseMessage, ResponseResult,
+};
 use crate::application::usecase::service::EventListener;
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind, ListenerStopped};
+use crate::domain::listener::retry::RetryPolicy;
+use crate::domain::registry::repository::ResourceRegistry;
 use crate::domain::state::ApplicationState;
 use crate::domain::webrtc::data::entity::{DataConnectionEventEnum, DataConnectionIdWrapper};
 use crate::domain::webrtc::data::repository::DataRepository;
@@ -21,6 +26,10 @@ pub(crate) struct EventService {
     repository: Arc<dyn DataRepository>,
     #[shaku(inject)]
     state: Arc<dyn ApplicationState>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
+    #[shaku(default)]
+    retry: RetryPolicy,
 }
 
 impl EventService {
@@ -30,7 +39,10 @@ impl EventService {
         data_connection_id: DataConnectionId,
     ) -> ResponseResult {
         while self.state.is_running() {
-            let event = self.repository.event(&data_connection_id).await;
+            let event = self
+                .retry
+                .call(|| self.repository.event(&data_connection_id))
+                .await;
             match event {
                 Ok(DataConnectionEventEnum::CLOSE(data_connection_id)) => {
                     let message =
@@ -46,9 +58,12 @@ impl EventService {
                     let message = DataResponse::Event(event.clone()).create_response_message();
                     let _ = event_tx.send(message).await;
                 }
-                Err(e) => {
-                    let message = format!("error in EventListener for data. {:?}", e);
-                    let message = ResponseResult::Error(message);
+                Err(reason) => {
+                    // 再試行しても回復しなかったので、監視を諦めたことを通知する
+                    let listener =
+                        ListenerInfo::new(ListenerKind::Data, data_connection_id.as_str());
+                    let message = ListenerResponse::Stopped(ListenerStopped { listener, reason })
+                        .create_response_message();
                     let _ = event_tx.send(message.clone()).await;
                     return message;
                 }
@@ -76,7 +91,19 @@ impl EventListener for EventService {
         }
 
         let data_connection_id = data_connection_id_wrapper.unwrap().data_connection_id;
-        self.listen(event_tx, data_connection_id).await
+        let result = self.listen(event_tx, data_connection_id.clone()).await;
+        // CLOSEしたDataConnectionのredirect先は、他のConnectionで利用できるようにする
+        // UNSUBSCRIBEやLISTENER_STOPPEDで監視を終えた場合は、まだ利用中の可能性があるので開放しない
+        let is_closed = matches!(
+            result,
+            ResponseResult::Success(ResponseMessage::Data(DataResponse::Event(
+                DataConnectionEventEnum::CLOSE(_)
+            )))
+        );
+        if is_closed {
+            self.registry.release_redirect(data_connection_id.as_str());
+        }
+        result
     }
 }
 
@@ -87,6 +114,7 @@ mod test_data_event {
     use once_cell::sync::Lazy;
 
     use crate::di::DataEventServiceContainer;
+    use crate::domain::registry::repository::MockResourceRegistry;
     use crate::domain::webrtc::data::repository::MockDataRepository;
     use crate::error;
     use crate::infra::state::ApplicationStateAlwaysFalseImpl;
@@ -136,9 +164,14 @@ mod test_data_event {
             .unwrap(),
         );
 
+        // CLOSEした場合のみredirect先を開放する
+        let mut registry = MockResourceRegistry::default();
+        registry.expect_release_redirect().times(1).return_const(());
+
         // Mockを埋め込んだEventServiceを生成
         let module = DataEventServiceContainer::builder()
             .with_component_override::<dyn DataRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
             .build();
         let event_service: &dyn EventListener = module.resolve_ref();
 
@@ -194,9 +227,14 @@ mod test_data_event {
         // eventを受け取るためのチャンネルを作成
         let (event_tx, mut event_rx) = mpsc::channel::<ResponseResult>(10);
 
+        // 監視を終えてもredirect先は開放しない
+        let mut registry = MockResourceRegistry::default();
+        registry.expect_release_redirect().times(0);
+
         // Mockを埋め込んだEventServiceを生成
         let module = DataEventServiceContainer::builder()
             .with_component_override::<dyn DataRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
             .build();
         let event_service: &dyn EventListener = module.resolve_ref();
 
@@ -209,19 +247,78 @@ mod test_data_event {
         );
 
         // event_serviceはループを抜けるときに最後のEVENTを返す
-        // ERRORが発生してループを抜けたErrorが帰ってくる
+        // 再試行しないERRORが発生してループを抜けた場合は、LISTENER_STOPPEDが帰ってくる
+        let expected = ListenerResponse::Stopped(ListenerStopped {
+            listener: ListenerInfo::new(ListenerKind::Data, data_connection_id.as_str()),
+            reason: "event api returned an error: LocalError(\"error\")".into(),
+        })
+        .create_response_message();
         let message = event_service.execute(event_tx, param).await;
-        assert_eq!(
-            message,
-            ResponseResult::Error("error in EventListener for data. LocalError(\"error\")".into())
-        );
+        assert_eq!(message, expected);
 
-        // 発生したERRORを受け取る
+        // 発生したLISTENER_STOPPEDを受け取る
         let event = event_rx.recv().await.unwrap();
-        assert_eq!(
-            event,
-            ResponseResult::Error("error in EventListener for data. LocalError(\"error\")".into())
+        assert_eq!(event, expected);
+    }
+
+    // 一時的なERRORは再試行し、監視を継続する場合
+    #[tokio::test]
+    async fn retry_transient_error() {
+        // mockのcontextが上書きされてしまわないよう、並列実行を避ける
+        let _lock = LOCKER.lock();
+
+        // create params
+        let data_connection_id =
+            DataConnectionId::try_create("dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c").unwrap();
+        let close_event = DataConnectionEventEnum::CLOSE(DataConnectionIdWrapper {
+            data_connection_id: data_connection_id.clone(),
+        });
+        let expected = DataResponse::Event(close_event.clone()).create_response_message();
+
+        // 1回目はGatewayの503, 2回目はCLOSEイベントを返すMockを作る
+        let counter = Mutex::new(0u8);
+        let mut mock = MockDataRepository::default();
+        mock.expect_event().returning(move |_| {
+            let mut count = counter.lock().unwrap();
+            *count += 1;
+            if *count == 1 {
+                Err(error::Error::create_local_error(
+                    "recv invalid response: url: http://localhost:8000/data/connections/dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c/events code: 503 Service Unavailable",
+                ))
+            } else {
+                Ok(close_event.clone())
+            }
+        });
+
+        // eventを受け取るためのチャンネルを作成
+        let (event_tx, mut event_rx) = mpsc::channel::<ResponseResult>(10);
+
+        // Mockと、待ち時間の短いRetryPolicyを埋め込んだEventServiceを生成
+        let module = DataEventServiceContainer::builder()
+            .with_component_override::<dyn DataRepository>(Box::new(mock))
+            .with_component_parameters::<EventService>(EventServiceParameters {
+                retry: RetryPolicy {
+                    max_retries: 1,
+                    initial_backoff: std::time::Duration::from_millis(1),
+                    max_backoff: std::time::Duration::from_millis(1),
+                },
+            })
+            .build();
+        let event_service: &dyn EventListener = module.resolve_ref();
+
+        // 実行
+        let param = Parameter(
+            serde_json::to_value(DataConnectionIdWrapper {
+                data_connection_id: data_connection_id.clone(),
+            })
+            .unwrap(),
         );
+
+        // ERRORは通知されず、CLOSEを受信してループを抜ける
+        let message = event_service.execute(event_tx, param).await;
+        assert_eq!(message, expected);
+        let event = event_rx.recv().await.unwrap();
+        assert_eq!(event, expected);
     }
 
     // loopの継続判定がfalseになって抜ける場合
@@ -242,9 +339,14 @@ mod test_data_event {
         // eventを受け取るためのチャンネルを作成
         let (event_tx, mut event_rx) = mpsc::channel::<ResponseResult>(10);
 
+        // 監視を終えてもredirect先は開放しない
+        let mut registry = MockResourceRegistry::default();
+        registry.expect_release_redirect().times(0);
+
         // Mockを埋め込んだEventServiceを生成
         let module = DataEventServiceContainer::builder()
             .with_component_override::<dyn DataRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
             .with_component_override::<dyn ApplicationState>(Box::new(
                 ApplicationStateAlwaysFalseImpl {},
             ))
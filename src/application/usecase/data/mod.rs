@@ -5,3 +5,4 @@ pub(crate) mod disconnect;
 pub(crate) mod event;
 pub(crate) mod redirect;
 pub(crate) mod status;
+pub(crate) mod subscribe;
@@ -1,13 +1,18 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use serde_json::Value;
 use shaku::*;
 
 use crate::application::dto::request_message::Parameter;
 use crate::application::dto::response_message::{DataResponse, ResponseResult};
 use crate::application::usecase::service::Service;
+use crate::domain::registry::allocation::allocate_data_redirect;
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::webrtc::common::value_object::SerializableSocket;
 use crate::domain::webrtc::data::entity::{
-    DataConnectionIdWrapper, RedirectDataParams, RedirectParams,
+    DataConnectionResult, RedirectDataParams, RedirectParams,
 };
 use crate::domain::webrtc::data::repository::DataRepository;
 use crate::error;
@@ -19,13 +24,15 @@ use crate::error;
 pub(crate) struct RedirectService {
     #[shaku(inject)]
     repository: Arc<dyn DataRepository>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
 }
 
-#[async_trait]
-impl Service for RedirectService {
-    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
-        let params = params.deserialize::<RedirectParams>()?;
+impl RedirectService {
+    async fn redirect(&self, value: Value) -> Result<ResponseResult, error::Error> {
+        let params = Parameter(value).deserialize::<RedirectParams>()?;
         let data_connection_id = params.data_connection_id;
+        let recv_socket = params.redirect_params.clone();
         let redirect_data_params = RedirectDataParams {
             feed_params: params.feed_params,
             redirect_params: params.redirect_params,
@@ -34,11 +41,33 @@ impl Service for RedirectService {
             .repository
             .redirect(&data_connection_id, &redirect_data_params)
             .await?;
-        let wrapper = DataConnectionIdWrapper {
-            data_connection_id: data_connection_id,
+        // redirect先を記録する。以前のredirect先は置き換えられる
+        if let Some(ref socket) = recv_socket {
+            let address = SocketAddr::new(socket.ip(), socket.port());
+            self.registry
+                .reserve_redirect(data_connection_id.as_str(), vec![address]);
+        }
+        let result = DataConnectionResult {
+            data_connection_id,
+            recv_socket,
         };
 
-        Ok(DataResponse::Redirect(wrapper).create_response_message())
+        Ok(DataResponse::Redirect(result).create_response_message())
+    }
+}
+
+#[async_trait]
+impl Service for RedirectService {
+    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
+        // "auto"と指定されたredirect先にportを割り当てる
+        let mut value = params.0;
+        let allocated = allocate_data_redirect(self.registry.as_ref(), &mut value)?;
+        let result = self.redirect(value).await;
+        // redirectに失敗した場合は、割り当てたportを開放する
+        if result.is_err() {
+            self.registry.release_ports(&allocated);
+        }
+        result
     }
 }
 
@@ -46,9 +75,7 @@ impl Service for RedirectService {
 mod test_redirect_data {
     use crate::di::DataRedirectServiceContainer;
     use crate::domain::webrtc::common::value_object::SerializableId;
-    use crate::domain::webrtc::data::entity::{
-        DataConnectionId, DataConnectionIdWrapper, RedirectDataResponse,
-    };
+    use crate::domain::webrtc::data::entity::{DataConnectionId, RedirectDataResponse};
     use crate::domain::webrtc::data::repository::MockDataRepository;
     use crate::domain::webrtc::data::value_object::DataId;
     use crate::error;
@@ -58,11 +85,12 @@ mod test_redirect_data {
     #[tokio::test]
     async fn success() {
         // 期待値を生成
-        let expected = DataResponse::Redirect(DataConnectionIdWrapper {
+        let expected = DataResponse::Redirect(DataConnectionResult {
             data_connection_id: DataConnectionId::try_create(
                 "dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c",
             )
             .unwrap(),
+            recv_socket: None,
         })
         .create_response_message();
 
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use shaku::*;
+
+use crate::application::dto::request_message::Parameter;
+use crate::application::dto::response_message::{DataResponse, ResponseResult};
+use crate::application::usecase::service::Service;
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+use crate::domain::listener::repository::ListenerRegistry;
+use crate::domain::webrtc::data::entity::{DataConnectionIdWrapper, DataSubscribeResult};
+use crate::domain::webrtc::data::repository::DataRepository;
+use crate::error;
+
+// Serviceの具象Struct
+// DIコンテナからのみオブジェクトを生成できる
+// プロセスの再起動前から存在するDataConnectionについて、statusで存在を確認し、event監視を再開する
+// 監視ループ自体は、このServiceの結果を受けてevent_factoryが起動する
+#[derive(Component)]
+#[shaku(interface = Service)]
+pub(crate) struct SubscribeService {
+    #[shaku(inject)]
+    repository: Arc<dyn DataRepository>,
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
+}
+
+#[async_trait]
+impl Service for SubscribeService {
+    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
+        let data_connection_id = params
+            .deserialize::<DataConnectionIdWrapper>()?
+            .data_connection_id;
+        let info = ListenerInfo::new(ListenerKind::Data, data_connection_id.as_str());
+        if self.listeners.is_active(&info) {
+            let message = format!("events of {} are already being listened", info.resource_id);
+            return Err(error::Error::create_local_error(&message));
+        }
+
+        // 存在しないDataConnectionの場合はここでエラーになる
+        let status = self.repository.status(&data_connection_id).await?;
+        let result = DataSubscribeResult {
+            data_connection_id,
+            status,
+        };
+        Ok(DataResponse::Subscribe(result).create_response_message())
+    }
+}
+
+#[cfg(test)]
+mod test_subscribe_data {
+    use crate::di::DataSubscribeServiceContainer;
+    use crate::domain::listener::repository::MockListenerRegistry;
+    use crate::domain::webrtc::data::entity::DataConnectionStatus;
+    use crate::domain::webrtc::data::repository::MockDataRepository;
+    use crate::domain::webrtc::data::value_object::DataConnectionId;
+
+    use super::*;
+
+    fn data_connection_id() -> DataConnectionId {
+        DataConnectionId::try_create("dc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap()
+    }
+
+    fn status() -> DataConnectionStatus {
+        DataConnectionStatus {
+            remote_id: "remote_id".to_string(),
+            buffersize: 0,
+            label: "label".to_string(),
+            metadata: "meta".to_string(),
+            open: true,
+            reliable: false,
+            serialization: "BINARY".to_string(),
+            r#type: "DATA".to_string(),
+        }
+    }
+
+    fn params() -> Parameter {
+        let wrapper = DataConnectionIdWrapper {
+            data_connection_id: data_connection_id(),
+        };
+        Parameter(serde_json::to_value(wrapper).unwrap())
+    }
+
+    #[tokio::test]
+    async fn success() {
+        let mut repository = MockDataRepository::default();
+        repository.expect_status().returning(|_| Ok(status()));
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_is_active().returning(|_| false);
+
+        // Mockを埋め込んだServiceを生成
+        let module = DataSubscribeServiceContainer::builder()
+            .with_component_override::<dyn DataRepository>(Box::new(repository))
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners))
+            .build();
+        let service: Arc<dyn Service> = module.resolve();
+
+        let result = service.execute(params()).await.unwrap();
+
+        // evaluate
+        let expected = DataResponse::Subscribe(DataSubscribeResult {
+            data_connection_id: data_connection_id(),
+            status: status(),
+        })
+        .create_response_message();
+        assert_eq!(result, expected);
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        let mut repository = MockDataRepository::default();
+        repository
+            .expect_status()
+            .returning(|_| Err(error::Error::create_local_error("recv 404")));
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_is_active().returning(|_| false);
+
+        // Mockを埋め込んだServiceを生成
+        let module = DataSubscribeServiceContainer::builder()
+            .with_component_override::<dyn DataRepository>(Box::new(repository))
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners))
+            .build();
+        let service: Arc<dyn Service> = module.resolve();
+
+        let result = service.execute(params()).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(message, "recv 404");
+        } else {
+            assert!(false);
+        }
+    }
+}
@@ -4,84 +4,198 @@ use serde::Serialize;
 use shaku::HasComponent;
 
 use crate::application::dto::request_message::{
-    DataServiceParams, MediaServiceParams, Parameter, PeerServiceParams, ServiceParams,
+    DataServiceParams, ListenerServiceParams, MediaServiceParams, Parameter, PeerServiceParams,
+    ServiceParams,
 };
 use crate::application::dto::response_message::{
-    DataResponse, MediaResponse, PeerResponse, ResponseMessage,
+    DataResponse, ListenerResponse, MediaResponse, PeerResponse, ResponseMessage, ResponseResult,
 };
 use crate::application::usecase::service::{EventListener, Service};
+use crate::application::usecase::{data, media, peer};
+use crate::di::SharedState;
+use crate::domain::fanout::repository::FanoutRepository;
+use crate::domain::listener::entity::{ListenerHandle, ListenerInfo, ListenerKind, ListenerTarget};
+use crate::domain::listener::repository::{EventPublisher, ListenerRegistry};
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::state::ApplicationState;
+use crate::domain::webrtc::data::entity::{DataConnectionEventEnum, DataConnectionIdWrapper};
+use crate::domain::webrtc::data::value_object::DataConnectionId;
+use crate::domain::webrtc::media::entity::{MediaConnectionEventEnum, MediaConnectionIdWrapper};
+use crate::domain::webrtc::media::value_object::MediaConnectionId;
+use crate::domain::webrtc::peer::entity::PeerEventEnum;
+use crate::domain::webrtc::peer::value_object::PeerInfo;
+use crate::infra::state::ApplicationStateListenerImpl;
+
+// 起動する監視ループと、そのパラメータ
+// ループの終了時にhandleをListenerRegistryに返却し、記録を削除する
+pub(crate) type EventTask = (Parameter, Arc<dyn EventListener>, ListenerHandle);
 
 fn value<V: Serialize, T: HasComponent<dyn EventListener>>(
     param: V,
     component: T,
-) -> (Parameter, Arc<dyn EventListener>) {
+    handle: ListenerHandle,
+) -> EventTask {
     // paramsはserializeをimplementしているので、エラーが出ることはなく、unwrapで問題ない
     let value = serde_json::to_value(&param).unwrap();
-    (Parameter(value), component.resolve())
+    (Parameter(value), component.resolve(), handle)
+}
+
+// 監視ループの起動を記録し、停止の指示を受け取るためのStateを生成する
+// 同じリソースを監視中のループが既に存在する場合は、重複して起動しないようNoneを返す
+fn listener_state(
+    state: &SharedState,
+    info: ListenerInfo,
+) -> Option<(ListenerHandle, Box<dyn ApplicationState>)> {
+    let handle = state.listeners.start(info)?;
+    let application_state = Box::new(ApplicationStateListenerImpl::new(handle.clone()));
+    Some((handle, application_state))
+}
+
+fn peer_listener(peer_info: PeerInfo, state: &SharedState) -> Option<EventTask> {
+    use crate::di::*;
+
+    let info = ListenerInfo::new(ListenerKind::Peer, peer_info.peer_id().as_str());
+    let (handle, application_state) = listener_state(state, info)?;
+    let component = PeerEventServiceContainer::builder()
+        .with_component_override::<dyn ApplicationState>(application_state)
+        .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+        .with_component_parameters::<peer::event::EventService>(
+            peer::event::EventServiceParameters {
+                retry: state.retry.clone(),
+            },
+        )
+        .build();
+    Some(value(peer_info, component, handle))
+}
+
+fn data_listener(data_connection_id: DataConnectionId, state: &SharedState) -> Option<EventTask> {
+    use crate::di::*;
+
+    let info = ListenerInfo::new(ListenerKind::Data, data_connection_id.as_str());
+    let (handle, application_state) = listener_state(state, info)?;
+    let component = DataEventServiceContainer::builder()
+        .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+        .with_component_override::<dyn ApplicationState>(application_state)
+        .with_component_parameters::<data::event::EventService>(
+            data::event::EventServiceParameters {
+                retry: state.retry.clone(),
+            },
+        )
+        .build();
+    let params = DataConnectionIdWrapper { data_connection_id };
+    Some(value(params, component, handle))
 }
 
-fn peer_event_factory(
-    params: PeerResponse,
-) -> Option<(Parameter, std::sync::Arc<dyn EventListener>)> {
+fn media_listener(
+    media_connection_id: MediaConnectionId,
+    state: &SharedState,
+) -> Option<EventTask> {
     use crate::di::*;
 
+    let info = ListenerInfo::new(ListenerKind::Media, media_connection_id.as_str());
+    let (handle, application_state) = listener_state(state, info)?;
+    let component = MediaEventServiceContainer::builder()
+        .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+        .with_component_override::<dyn ApplicationState>(application_state)
+        .with_component_parameters::<media::event::EventService>(
+            media::event::EventServiceParameters {
+                retry: state.retry.clone(),
+            },
+        )
+        .build();
+    let params = MediaConnectionIdWrapper {
+        media_connection_id,
+    };
+    Some(value(params, component, handle))
+}
+
+fn peer_event_factory(params: PeerResponse, state: &SharedState) -> Option<EventTask> {
     match params {
-        PeerResponse::Create(params) => {
-            let component = PeerEventServiceContainer::builder().build();
-            Some(value(params, component))
+        PeerResponse::Create(params) => peer_listener(params, state),
+        PeerResponse::Subscribe(params) => peer_listener(params, state),
+        // 相手側から確立されたConnectionは、REDIRECT, ANSWERの前に閉じられる場合もあるので、
+        // 設定されている場合は通知された時点で監視を開始する
+        PeerResponse::Event(PeerEventEnum::CONNECTION(event))
+            if state.listen_announced_connections =>
+        {
+            data_listener(event.data_params.data_connection_id, state)
+        }
+        PeerResponse::Event(PeerEventEnum::CALL(event)) if state.listen_announced_connections => {
+            media_listener(event.call_params.media_connection_id, state)
         }
         _ => None,
     }
 }
 
-fn data_event_factory(
-    params: DataResponse,
-) -> Option<(Parameter, std::sync::Arc<dyn EventListener>)> {
+fn data_event_factory(params: DataResponse, state: &SharedState) -> Option<EventTask> {
+    match params {
+        // REDIRECTを繰り返しても、監視ループは1つのDataConnectionにつき1つしか起動しない
+        DataResponse::Connect(params) => data_listener(params.data_connection_id, state),
+        DataResponse::Redirect(params) => data_listener(params.data_connection_id, state),
+        DataResponse::Subscribe(params) => data_listener(params.data_connection_id, state),
+        _ => None,
+    }
+}
+
+fn media_event_factory(params: MediaResponse, state: &SharedState) -> Option<EventTask> {
     use crate::di::*;
 
     match params {
-        DataResponse::Connect(params) => {
-            let component = DataEventServiceContainer::builder().build();
-            Some(value(params, component))
-        }
-        DataResponse::Redirect(params) => {
-            let component = DataEventServiceContainer::builder().build();
-            Some(value(params, component))
+        MediaResponse::Call(params) => media_listener(params.media_connection_id, state),
+        MediaResponse::Answer(params) => media_listener(params.media_connection_id, state),
+        MediaResponse::Subscribe(params) => media_listener(params.media_connection_id, state),
+        MediaResponse::FanoutCall(params) => {
+            let info = ListenerInfo::new(ListenerKind::Media, params.media_connection_id.as_str());
+            let (handle, application_state) = listener_state(state, info)?;
+            // CLOSE時に複製先から外すため、fan-outの状態を共有する
+            let component = MediaFanoutEventServiceContainer::builder()
+                .with_component_override::<dyn FanoutRepository>(Box::new(state.fanout.clone()))
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .with_component_override::<dyn ApplicationState>(application_state)
+                .with_component_parameters::<media::fanout_event::FanoutEventService>(
+                    media::fanout_event::FanoutEventServiceParameters {
+                        retry: state.retry.clone(),
+                    },
+                )
+                .build();
+            let params = MediaConnectionIdWrapper {
+                media_connection_id: params.media_connection_id,
+            };
+            Some(value(params, component, handle))
         }
         _ => None,
     }
 }
 
-fn media_event_factory(
-    params: MediaResponse,
-) -> Option<(Parameter, std::sync::Arc<dyn EventListener>)> {
-    use crate::di::*;
-
+fn listener_event_factory(params: ListenerResponse, state: &SharedState) -> Option<EventTask> {
     match params {
-        MediaResponse::Call(params) => {
-            let component = MediaEventServiceContainer::builder().build();
-            Some(value(params, component))
+        ListenerResponse::Subscribe(ListenerTarget::Peer(peer_info)) => {
+            peer_listener(peer_info, state)
+        }
+        ListenerResponse::Subscribe(ListenerTarget::Data(wrapper)) => {
+            data_listener(wrapper.data_connection_id, state)
         }
-        MediaResponse::Answer(params) => {
-            let component = MediaEventServiceContainer::builder().build();
-            Some(value(params, component))
+        ListenerResponse::Subscribe(ListenerTarget::Media(wrapper)) => {
+            media_listener(wrapper.media_connection_id, state)
         }
         _ => None,
     }
 }
 
 // FIXME: no test
-pub(crate) fn event_factory(
-    message: ResponseMessage,
-) -> Option<(Parameter, std::sync::Arc<dyn EventListener>)> {
+pub(crate) fn event_factory(message: ResponseMessage, state: &SharedState) -> Option<EventTask> {
     match message {
-        ResponseMessage::Peer(params) => peer_event_factory(params),
-        ResponseMessage::Data(params) => data_event_factory(params),
-        ResponseMessage::Media(params) => media_event_factory(params),
+        ResponseMessage::Peer(params) => peer_event_factory(params, state),
+        ResponseMessage::Data(params) => data_event_factory(params, state),
+        ResponseMessage::Media(params) => media_event_factory(params, state),
+        ResponseMessage::Listener(params) => listener_event_factory(params, state),
     }
 }
 
-fn peer_service_factory(params: PeerServiceParams) -> (Parameter, Arc<dyn Service>) {
+fn peer_service_factory(
+    params: PeerServiceParams,
+    state: &SharedState,
+) -> (Parameter, Arc<dyn Service>) {
     use crate::di::*;
 
     match params {
@@ -96,14 +210,26 @@ fn peer_service_factory(params: PeerServiceParams) -> (Parameter, Arc<dyn Servic
             (params, service)
         }
         PeerServiceParams::Delete { params } => {
-            let module = PeerDeleteServiceContainer::builder().build();
+            let module = PeerDeleteServiceContainer::builder()
+                .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+                .build();
+            let service: Arc<dyn Service> = module.resolve();
+            (params, service)
+        }
+        PeerServiceParams::Subscribe { params } => {
+            let module = PeerSubscribeServiceContainer::builder()
+                .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+                .build();
             let service: Arc<dyn Service> = module.resolve();
             (params, service)
         }
     }
 }
 
-fn data_service_factory(params: DataServiceParams) -> (Parameter, Arc<dyn Service>) {
+fn data_service_factory(
+    params: DataServiceParams,
+    state: &SharedState,
+) -> (Parameter, Arc<dyn Service>) {
     use crate::di::*;
 
     match params {
@@ -118,12 +244,23 @@ fn data_service_factory(params: DataServiceParams) -> (Parameter, Arc<dyn Servic
             (params, service)
         }
         DataServiceParams::Connect { params } => {
-            let module = DataConnectServiceContainer::builder().build();
+            let module = DataConnectServiceContainer::builder()
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+                .with_component_override::<dyn EventPublisher>(Box::new(state.events.clone()))
+                .with_component_parameters::<data::connect::ConnectService>(
+                    data::connect::ConnectServiceParameters {
+                        retry: state.retry.clone(),
+                    },
+                )
+                .build();
             let service: Arc<dyn Service> = module.resolve();
             (params, service)
         }
         DataServiceParams::Redirect { params } => {
-            let module = DataRedirectServiceContainer::builder().build();
+            let module = DataRedirectServiceContainer::builder()
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .build();
             let service: Arc<dyn Service> = module.resolve();
             (params, service)
         }
@@ -132,52 +269,92 @@ fn data_service_factory(params: DataServiceParams) -> (Parameter, Arc<dyn Servic
             let service: Arc<dyn Service> = module.resolve();
             (params, service)
         }
+        DataServiceParams::Subscribe { params } => {
+            let module = DataSubscribeServiceContainer::builder()
+                .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+                .build();
+            let service: Arc<dyn Service> = module.resolve();
+            (params, service)
+        }
         DataServiceParams::Disconnect { params } => {
-            let module = DataDisconnectServiceContainer::builder().build();
+            let module = DataDisconnectServiceContainer::builder()
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .build();
             let service: Arc<dyn Service> = module.resolve();
             (params, service)
         }
     }
 }
 
-fn media_service_factory(params: MediaServiceParams) -> (Parameter, Arc<dyn Service>) {
+fn media_service_factory(
+    params: MediaServiceParams,
+    state: &SharedState,
+) -> (Parameter, Arc<dyn Service>) {
     use crate::di::*;
 
     match params {
         MediaServiceParams::ContentCreate { params } => {
-            let module = MediaContentCreateServiceContainer::builder().build();
+            let module = MediaContentCreateServiceContainer::builder()
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .build();
             let service: Arc<dyn Service> = module.resolve();
             (params, service)
         }
         MediaServiceParams::ContentDelete { params } => {
-            let module = MediaContentDeleteServiceContainer::builder().build();
+            let module = MediaContentDeleteServiceContainer::builder()
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .build();
             let service: Arc<dyn Service> = module.resolve();
             (params, service)
         }
         MediaServiceParams::RtcpCreate { params: _ } => {
-            let module = MediaRtcpCreateServiceContainer::builder().build();
+            let module = MediaRtcpCreateServiceContainer::builder()
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .build();
             let service: Arc<dyn Service> = module.resolve();
             // この値は使わないので何でも良い
             (Parameter(serde_json::Value::Null), service)
         }
         MediaServiceParams::RtcpDelete { params: _ } => {
-            let module = MediaRtcpDeleteServiceContainer::builder().build();
+            let module = MediaRtcpDeleteServiceContainer::builder()
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .build();
             let service: Arc<dyn Service> = module.resolve();
             // この値は使わないので何でも良い
             (Parameter(serde_json::Value::Null), service)
         }
         MediaServiceParams::Call { params } => {
-            let module = MediaCallServiceContainer::builder().build();
+            let module = MediaCallServiceContainer::builder()
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+                .with_component_override::<dyn EventPublisher>(Box::new(state.events.clone()))
+                .with_component_parameters::<media::call::CallService>(
+                    media::call::CallServiceParameters {
+                        retry: state.retry.clone(),
+                    },
+                )
+                .build();
             let service: Arc<dyn Service> = module.resolve();
             (params, service)
         }
         MediaServiceParams::Answer { params } => {
-            let module = MediaAnswerServiceContainer::builder().build();
+            let module = MediaAnswerServiceContainer::builder()
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+                .with_component_override::<dyn EventPublisher>(Box::new(state.events.clone()))
+                .with_component_parameters::<media::answer::AnswerService>(
+                    media::answer::AnswerServiceParameters {
+                        retry: state.retry.clone(),
+                    },
+                )
+                .build();
             let service: Arc<dyn Service> = module.resolve();
             (params, service)
         }
         MediaServiceParams::Disconnect { params } => {
-            let module = MediaDisconnectServiceContainer::builder().build();
+            let module = MediaDisconnectServiceContainer::builder()
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .build();
             let service: Arc<dyn Service> = module.resolve();
             (params, service)
         }
@@ -186,14 +363,148 @@ fn media_service_factory(params: MediaServiceParams) -> (Parameter, Arc<dyn Serv
             let service: Arc<dyn Service> = module.resolve();
             (params, service)
         }
+        MediaServiceParams::Subscribe { params } => {
+            let module = MediaSubscribeServiceContainer::builder()
+                .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+                .build();
+            let service: Arc<dyn Service> = module.resolve();
+            (params, service)
+        }
+        MediaServiceParams::FanoutCreate { params } => {
+            let module = MediaFanoutCreateServiceContainer::builder()
+                .with_component_override::<dyn FanoutRepository>(Box::new(state.fanout.clone()))
+                .build();
+            let service: Arc<dyn Service> = module.resolve();
+            (params, service)
+        }
+        MediaServiceParams::FanoutCall { params } => {
+            let module = MediaFanoutCallServiceContainer::builder()
+                .with_component_override::<dyn FanoutRepository>(Box::new(state.fanout.clone()))
+                .with_component_override::<dyn ResourceRegistry>(Box::new(state.registry.clone()))
+                .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+                .build();
+            let service: Arc<dyn Service> = module.resolve();
+            (params, service)
+        }
+        MediaServiceParams::FanoutDelete { params } => {
+            let module = MediaFanoutDeleteServiceContainer::builder()
+                .with_component_override::<dyn FanoutRepository>(Box::new(state.fanout.clone()))
+                .build();
+            let service: Arc<dyn Service> = module.resolve();
+            (params, service)
+        }
+    }
+}
+
+fn listener_service_factory(
+    params: ListenerServiceParams,
+    state: &SharedState,
+) -> (Parameter, Arc<dyn Service>) {
+    use crate::di::*;
+
+    match params {
+        ListenerServiceParams::Subscribe { params } => {
+            let module = ListenerSubscribeServiceContainer::builder()
+                .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+                .build();
+            let service: Arc<dyn Service> = module.resolve();
+            (params, service)
+        }
+        ListenerServiceParams::Unsubscribe { params } => {
+            let module = ListenerUnsubscribeServiceContainer::builder()
+                .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+                .build();
+            let service: Arc<dyn Service> = module.resolve();
+            (params, service)
+        }
+        ListenerServiceParams::List => {
+            let module = ListenerListServiceContainer::builder()
+                .with_component_override::<dyn ListenerRegistry>(Box::new(state.listeners.clone()))
+                .build();
+            let service: Arc<dyn Service> = module.resolve();
+            // この値は使わないので何でも良い
+            (Parameter(serde_json::Value::Null), service)
+        }
+    }
+}
+
+// 所有するPeerのCLOSEによって監視ループが停止した場合に、ConnectionのCLOSE eventを補完する
+// ループ自身がCLOSE eventを受信して終了した場合は、既に通知済みなので補完しない
+pub(crate) fn close_event(
+    handle: &ListenerHandle,
+    result: &ResponseResult,
+) -> Option<ResponseResult> {
+    if !handle.is_closed() {
+        return None;
+    }
+    match result {
+        ResponseResult::Success(ResponseMessage::Data(DataResponse::Event(
+            DataConnectionEventEnum::CLOSE(_),
+        ))) => None,
+        ResponseResult::Success(ResponseMessage::Media(MediaResponse::Event(
+            MediaConnectionEventEnum::CLOSE(_),
+        ))) => None,
+        _ => match handle.info.kind {
+            ListenerKind::Data => {
+                let data_connection_id =
+                    DataConnectionId::try_create(handle.info.resource_id.as_str()).ok()?;
+                let event =
+                    DataConnectionEventEnum::CLOSE(DataConnectionIdWrapper { data_connection_id });
+                Some(DataResponse::Event(event).create_response_message())
+            }
+            ListenerKind::Media => {
+                let media_connection_id =
+                    MediaConnectionId::try_create(handle.info.resource_id.as_str()).ok()?;
+                let event = MediaConnectionEventEnum::CLOSE(MediaConnectionIdWrapper {
+                    media_connection_id,
+                });
+                Some(MediaResponse::Event(event).create_response_message())
+            }
+            ListenerKind::Peer => None,
+        },
     }
 }
 
 // FIXME: no unit test
-pub(crate) fn service_factory(params: ServiceParams) -> (Parameter, Arc<dyn Service>) {
+pub(crate) fn service_factory(
+    params: ServiceParams,
+    state: &SharedState,
+) -> (Parameter, Arc<dyn Service>) {
     match params {
-        ServiceParams::Peer(params) => peer_service_factory(params),
-        ServiceParams::Data(params) => data_service_factory(params),
-        ServiceParams::Media(params) => media_service_factory(params),
+        ServiceParams::Peer(params) => peer_service_factory(params, state),
+        ServiceParams::Data(params) => data_service_factory(params, state),
+        ServiceParams::Media(params) => media_service_factory(params, state),
+        ServiceParams::Listener(params) => listener_service_factory(params, state),
+    }
+}
+
+#[cfg(test)]
+mod test_close_event {
+    use super::*;
+
+    const ID: &str = "dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c";
+
+    fn close_message() -> ResponseResult {
+        let event = DataConnectionEventEnum::CLOSE(DataConnectionIdWrapper {
+            data_connection_id: DataConnectionId::try_create(ID).unwrap(),
+        });
+        DataResponse::Event(event).create_response_message()
+    }
+
+    #[test]
+    fn closed_by_owner() {
+        let handle = ListenerHandle::new(ListenerInfo::new(ListenerKind::Data, ID), 0);
+        // ループの停止によって終了した場合の戻り値
+        let result =
+            DataResponse::Event(DataConnectionEventEnum::TIMEOUT).create_response_message();
+
+        // UNSUBSCRIBEによる停止では補完しない
+        handle.stop();
+        assert_eq!(close_event(&handle, &result), None);
+
+        handle.close();
+        assert_eq!(close_event(&handle, &result), Some(close_message()));
+        // 既にCLOSEを通知済みの場合は補完しない
+        assert_eq!(close_event(&handle, &close_message()), None);
     }
 }
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use shaku::*;
+
+use crate::application::dto::request_message::Parameter;
+use crate::application::dto::response_message::{ListenerResponse, ResponseResult};
+use crate::application::usecase::service::Service;
+use crate::domain::listener::entity::ListenerList;
+use crate::domain::listener::repository::ListenerRegistry;
+use crate::error;
+
+// Serviceの具象Struct
+// DIコンテナからのみオブジェクトを生成できる
+#[derive(Component)]
+#[shaku(interface = Service)]
+pub(crate) struct ListService {
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
+}
+
+#[async_trait]
+impl Service for ListService {
+    async fn execute(&self, _params: Parameter) -> Result<ResponseResult, error::Error> {
+        let listeners = self.listeners.list();
+        Ok(ListenerResponse::List(ListenerList { listeners }).create_response_message())
+    }
+}
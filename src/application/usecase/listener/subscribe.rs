@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use shaku::*;
+
+use crate::application::dto::request_message::Parameter;
+use crate::application::dto::response_message::{ListenerResponse, ResponseResult};
+use crate::application::usecase::service::Service;
+use crate::domain::listener::entity::ListenerTarget;
+use crate::domain::listener::repository::ListenerRegistry;
+use crate::error;
+
+// Serviceの具象Struct
+// DIコンテナからのみオブジェクトを生成できる
+// 監視ループ自体は、このServiceの結果を受けてevent_factoryが起動する
+#[derive(Component)]
+#[shaku(interface = Service)]
+pub(crate) struct SubscribeService {
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
+}
+
+#[async_trait]
+impl Service for SubscribeService {
+    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
+        let target = params.deserialize::<ListenerTarget>()?;
+        let info = target.listener_info();
+        if self.listeners.is_active(&info) {
+            let message = format!("events of {} are already being listened", info.resource_id);
+            return Err(error::Error::create_local_error(&message));
+        }
+        Ok(ListenerResponse::Subscribe(target).create_response_message())
+    }
+}
+
+#[cfg(test)]
+mod test_subscribe {
+    use crate::di::ListenerSubscribeServiceContainer;
+    use crate::domain::listener::repository::MockListenerRegistry;
+
+    use super::*;
+
+    fn params() -> Parameter {
+        Parameter(serde_json::json!({
+            "media_connection_id": "mc-50a32bab-b3d9-4913-8e20-f79c90a6a211"
+        }))
+    }
+
+    #[tokio::test]
+    async fn success() {
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_is_active().returning(|_| false);
+
+        // Mockを埋め込んだServiceを生成
+        let module = ListenerSubscribeServiceContainer::builder()
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners))
+            .build();
+        let service: Arc<dyn Service> = module.resolve();
+
+        let result = service.execute(params()).await.unwrap();
+
+        // evaluate
+        let target = params().deserialize::<ListenerTarget>().unwrap();
+        assert_eq!(
+            result,
+            ListenerResponse::Subscribe(target).create_response_message()
+        );
+    }
+
+    #[tokio::test]
+    async fn already_listened() {
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_is_active().returning(|_| true);
+
+        // Mockを埋め込んだServiceを生成
+        let module = ListenerSubscribeServiceContainer::builder()
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners))
+            .build();
+        let service: Arc<dyn Service> = module.resolve();
+
+        let result = service.execute(params()).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "events of mc-50a32bab-b3d9-4913-8e20-f79c90a6a211 are already being listened"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use shaku::*;
+
+use crate::application::dto::request_message::Parameter;
+use crate::application::dto::response_message::{ListenerResponse, ResponseResult};
+use crate::application::usecase::service::Service;
+use crate::domain::listener::entity::ListenerInfo;
+use crate::domain::listener::repository::ListenerRegistry;
+use crate::error;
+
+// Serviceの具象Struct
+// DIコンテナからのみオブジェクトを生成できる
+#[derive(Component)]
+#[shaku(interface = Service)]
+pub(crate) struct UnsubscribeService {
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
+}
+
+#[async_trait]
+impl Service for UnsubscribeService {
+    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
+        // LISTの結果をそのまま与えられるよう、ListenerInfoの形式で受け取る
+        let info = params.deserialize::<ListenerInfo>()?;
+        if !self.listeners.stop(&info) {
+            let message = format!("events of {} are not being listened", info.resource_id);
+            return Err(error::Error::create_local_error(&message));
+        }
+        Ok(ListenerResponse::Unsubscribe(info).create_response_message())
+    }
+}
+
+#[cfg(test)]
+mod test_unsubscribe {
+    use crate::di::ListenerUnsubscribeServiceContainer;
+    use crate::domain::listener::entity::ListenerKind;
+    use crate::domain::listener::repository::MockListenerRegistry;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn success() {
+        let info = ListenerInfo::new(
+            ListenerKind::Data,
+            "dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c",
+        );
+
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_stop().times(1).returning(|_| true);
+
+        // Mockを埋め込んだServiceを生成
+        let module = ListenerUnsubscribeServiceContainer::builder()
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners))
+            .build();
+        let service: Arc<dyn Service> = module.resolve();
+
+        let params = Parameter(serde_json::to_value(&info).unwrap());
+        let result = service.execute(params).await.unwrap();
+
+        // evaluate
+        assert_eq!(
+            result,
+            ListenerResponse::Unsubscribe(info).create_response_message()
+        );
+    }
+
+    #[tokio::test]
+    async fn not_listened() {
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_stop().returning(|_| false);
+
+        // Mockを埋め込んだServiceを生成
+        let module = ListenerUnsubscribeServiceContainer::builder()
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners))
+            .build();
+        let service: Arc<dyn Service> = module.resolve();
+
+        let params = Parameter(serde_json::json!({
+            "kind": "PEER",
+            "resource_id": "peer_id"
+        }));
+        let result = service.execute(params).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(message, "events of peer_id are not being listened");
+        } else {
+            assert!(false);
+        }
+    }
+}
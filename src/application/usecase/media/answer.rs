@@ -1,14 +1,23 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use shaku::*;
 
 use crate::application::dto::request_message::Parameter;
 use crate::application::dto::response_message::{MediaResponse, ResponseResult};
 use crate::application::usecase::service::Service;
+use crate::domain::listener::repository::{EventPublisher, ListenerRegistry};
+use crate::domain::listener::retry::RetryPolicy;
+use crate::domain::registry::allocation::allocate_media_redirect;
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::registry::validation::{reserve_redirect_params, validate_resources};
 use crate::domain::webrtc::media::entity::{AnswerQuery, AnswerResponseParams, AnswerResult};
+use crate::domain::webrtc::media::preset::{expand_presets, validate_constraints};
 use crate::domain::webrtc::media::repository::MediaRepository;
+use crate::domain::webrtc::media::service::{take_wait_ready, wait_ready};
 use crate::domain::webrtc::media::value_object::MediaConnectionId;
 use crate::error;
 
@@ -25,12 +34,30 @@ struct AnswerParameters {
 pub(crate) struct AnswerService {
     #[shaku(inject)]
     repository: Arc<dyn MediaRepository>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
+    #[shaku(inject)]
+    publisher: Arc<dyn EventPublisher>,
+    #[shaku(default)]
+    retry: RetryPolicy,
 }
 
-#[async_trait]
-impl Service for AnswerService {
-    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
-        let answer_parameters = params.deserialize::<AnswerParameters>()?;
+impl AnswerService {
+    async fn answer(
+        &self,
+        value: Value,
+        wait: Option<Duration>,
+    ) -> Result<ResponseResult, error::Error> {
+        let answer_parameters = Parameter(value).deserialize::<AnswerParameters>()?;
+        validate_constraints(&answer_parameters.answer_query.constraints)?;
+        // 参照しているリソースが利用可能か確認する
+        validate_resources(
+            self.registry.as_ref(),
+            Some(&answer_parameters.answer_query.constraints),
+            answer_parameters.answer_query.redirect_params.as_ref(),
+        )?;
         let status = self
             .repository
             .status(&answer_parameters.media_connection_id)
@@ -44,6 +71,38 @@ impl Service for AnswerService {
                     &answer_parameters.answer_query,
                 )
                 .await?;
+            reserve_redirect_params(
+                self.registry.as_ref(),
+                answer_parameters.media_connection_id.as_str(),
+                answer_parameters.answer_query.redirect_params.as_ref(),
+            );
+            if let Some(timeout) = wait {
+                let media_connection_id = &answer_parameters.media_connection_id;
+                let result = wait_ready(
+                    self.repository.clone(),
+                    self.listeners.as_ref(),
+                    self.publisher.as_ref(),
+                    &self.retry,
+                    media_connection_id,
+                    timeout,
+                )
+                .await;
+                match result {
+                    // 待機中に取得したREADYは、監視ループが取得したeventと同様に配信する
+                    Ok(Some(event)) => {
+                        self.publisher
+                            .publish_media(media_connection_id, event)
+                            .await
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        // READYにならなかったMediaConnectionは切断し、redirect先を開放する
+                        let _ = self.repository.disconnect(media_connection_id).await;
+                        self.registry.release_redirect(media_connection_id.as_str());
+                        return Err(e);
+                    }
+                }
+            }
             let video_params = result.params.video_id;
             let audio_params = result.params.audio_id;
             let send_socket = if video_params.is_none() && audio_params.is_none() {
@@ -71,15 +130,42 @@ impl Service for AnswerService {
     }
 }
 
+#[async_trait]
+impl Service for AnswerService {
+    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
+        // presetを展開し、codecの設定に不整合がないか確認してからanswerする
+        let mut value = params.0;
+        let wait = take_wait_ready(&mut value);
+        if let Some(constraints) = value.pointer_mut("/answer_query/constraints") {
+            expand_presets(constraints)?;
+        }
+        // "auto"と指定されたredirect先にportを割り当てる
+        let allocated = match value.get_mut("answer_query") {
+            Some(answer_query) => allocate_media_redirect(self.registry.as_ref(), answer_query)?,
+            None => vec![],
+        };
+        let result = self.answer(value, wait).await;
+        // answerしなかった場合は、割り当てたportを開放する
+        if !matches!(result, Ok(ResponseResult::Success(_))) {
+            self.registry.release_ports(&allocated);
+        }
+        result
+    }
+}
+
 #[cfg(test)]
 mod test_answer {
     use crate::di::MediaAnswerServiceContainer;
+    use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+    use crate::domain::listener::repository::MockEventPublisher;
     use crate::domain::webrtc::media::entity::{
-        AnswerResponse, AnswerResponseParams, AnswerResult, Constraints, MediaConnectionStatus,
+        AnswerResponse, AnswerResponseParams, AnswerResult, Constraints, MediaConnectionEventEnum,
+        MediaConnectionIdWrapper, MediaConnectionStatus,
     };
     use crate::domain::webrtc::media::repository::MockMediaRepository;
     use crate::domain::webrtc::peer::value_object::PeerId;
     use crate::error;
+    use crate::infra::listener::ListenerRegistryImpl;
 
     use super::*;
 
@@ -151,6 +237,210 @@ mod test_answer {
         assert_eq!(result, expected);
     }
 
+    // READYにならなかった場合は切断され、エラーが返る
+    #[tokio::test]
+    async fn rollback_when_not_ready() {
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+
+        // answerには成功するが、READYの前にCLOSEするMockを作成
+        let mut mock = MockMediaRepository::default();
+        mock.expect_status().returning(move |_| {
+            Ok(MediaConnectionStatus {
+                metadata: "metadata".to_string(),
+                open: false,
+                remote_id: PeerId::new("peer_id"),
+                ssrc: None,
+            })
+        });
+        mock.expect_answer().returning(move |_, _| {
+            Ok(AnswerResponse {
+                command_type: "ANSWER".to_string(),
+                params: AnswerResponseParams {
+                    video_id: None,
+                    audio_id: None,
+                },
+            })
+        });
+        let id = media_connection_id.clone();
+        mock.expect_event().times(1).returning(move |_| {
+            Ok(MediaConnectionEventEnum::CLOSE(MediaConnectionIdWrapper {
+                media_connection_id: id.clone(),
+            }))
+        });
+        mock.expect_disconnect().times(1).returning(|_| Ok(()));
+
+        // Mockを埋め込んだEventServiceを生成
+        let module = MediaAnswerServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .build();
+        let answer_service: Arc<dyn Service> = module.resolve();
+
+        // 実行パラメータの生成
+        let params = serde_json::json!({
+            "media_connection_id": media_connection_id.as_str(),
+            "answer_query": {
+                "constraints": {
+                    "video": false,
+                    "audio": false
+                }
+            },
+            "wait_ready": true
+        });
+        // 実行
+        let result = answer_service.execute(Parameter(params)).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "MediaConnection mc-50a32bab-b3d9-4913-8e20-f79c90a6a211 was closed before READY"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+
+    // READYを待った場合は、取得したREADYが配信される
+    #[tokio::test]
+    async fn publish_ready() {
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+        let ready_event = MediaConnectionEventEnum::READY(MediaConnectionIdWrapper {
+            media_connection_id: media_connection_id.clone(),
+        });
+
+        // answerに成功し、READYを返すMockを作成
+        let mut mock = MockMediaRepository::default();
+        mock.expect_status().returning(move |_| {
+            Ok(MediaConnectionStatus {
+                metadata: "metadata".to_string(),
+                open: false,
+                remote_id: PeerId::new("peer_id"),
+                ssrc: None,
+            })
+        });
+        mock.expect_answer().returning(move |_, _| {
+            Ok(AnswerResponse {
+                command_type: "ANSWER".to_string(),
+                params: AnswerResponseParams {
+                    video_id: None,
+                    audio_id: None,
+                },
+            })
+        });
+        let event = ready_event.clone();
+        mock.expect_event()
+            .times(1)
+            .returning(move |_| Ok(event.clone()));
+        mock.expect_disconnect().times(0);
+        let mut publisher = MockEventPublisher::default();
+        let id = media_connection_id.clone();
+        publisher
+            .expect_publish_media()
+            .times(1)
+            .returning(move |media_connection_id, event| {
+                assert_eq!(media_connection_id, &id);
+                assert_eq!(event, ready_event);
+            });
+
+        // Mockを埋め込んだEventServiceを生成
+        let module = MediaAnswerServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn EventPublisher>(Box::new(publisher))
+            .build();
+        let answer_service: Arc<dyn Service> = module.resolve();
+
+        // 実行パラメータの生成
+        let params = serde_json::json!({
+            "media_connection_id": media_connection_id.as_str(),
+            "answer_query": {
+                "constraints": {
+                    "video": false,
+                    "audio": false
+                }
+            },
+            "wait_ready": true
+        });
+        // 実行
+        let result = answer_service.execute(Parameter(params)).await.unwrap();
+
+        // evaluate
+        let expected = MediaResponse::Answer(AnswerResult {
+            media_connection_id,
+            send_sockets: None,
+            recv_sockets: None,
+        })
+        .create_response_message();
+        assert_eq!(result, expected);
+    }
+
+    // READYを待った後も、PeerのCALL eventで記録された所有者は残り、Peerの削除で監視ループが停止する
+    #[tokio::test]
+    async fn keep_owner_after_wait() {
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+        let info = ListenerInfo::new(ListenerKind::Media, media_connection_id.as_str());
+
+        // answerに成功し、READYを返すMockを作成
+        let mut mock = MockMediaRepository::default();
+        mock.expect_status().returning(move |_| {
+            Ok(MediaConnectionStatus {
+                metadata: "metadata".to_string(),
+                open: false,
+                remote_id: PeerId::new("peer_id"),
+                ssrc: None,
+            })
+        });
+        mock.expect_answer().returning(move |_, _| {
+            Ok(AnswerResponse {
+                command_type: "ANSWER".to_string(),
+                params: AnswerResponseParams {
+                    video_id: None,
+                    audio_id: None,
+                },
+            })
+        });
+        let id = media_connection_id.clone();
+        mock.expect_event().times(1).returning(move |_| {
+            Ok(MediaConnectionEventEnum::READY(MediaConnectionIdWrapper {
+                media_connection_id: id.clone(),
+            }))
+        });
+
+        // CALL eventを受信した時点で所有者が記録されている
+        let listeners = ListenerRegistryImpl::default();
+        listeners.set_owner("peer_id", info.clone());
+
+        // Mockを埋め込んだEventServiceを生成
+        let module = MediaAnswerServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners.clone()))
+            .build();
+        let answer_service: Arc<dyn Service> = module.resolve();
+
+        // 実行パラメータの生成
+        let params = serde_json::json!({
+            "media_connection_id": media_connection_id.as_str(),
+            "answer_query": {
+                "constraints": {
+                    "video": false,
+                    "audio": false
+                }
+            },
+            "wait_ready": true
+        });
+        // 実行
+        let _ = answer_service.execute(Parameter(params)).await.unwrap();
+
+        // evaluate
+        assert_eq!(listeners.owner(&info), Some("peer_id".to_string()));
+        // ANSWERの応答後に起動した監視ループは、Peerの削除で停止する
+        let handle = listeners.start(info.clone()).unwrap();
+        assert_eq!(listeners.close_owned("peer_id"), vec![info]);
+        assert!(handle.is_closed());
+    }
+
     #[tokio::test]
     async fn already_connected() {
         // 期待値を生成
@@ -236,4 +526,41 @@ mod test_answer {
             assert!(false);
         }
     }
+
+    #[tokio::test]
+    async fn inconsistent_constraints() {
+        // codecの設定に不整合があるのでstatusもanswerも呼ばれない
+        let mock = MockMediaRepository::default();
+        let module = MediaAnswerServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .build();
+        let answer_service: Arc<dyn Service> = module.resolve();
+
+        // PCMUのpresetに対し、動的なpayload typeを上書きする
+        let params = serde_json::json!({
+            "media_connection_id": "mc-50a32bab-b3d9-4913-8e20-f79c90a6a211",
+            "answer_query": {
+                "constraints": {
+                    "video": false,
+                    "audio": true,
+                    "audio_params": {
+                        "preset": "pcmu",
+                        "media_id": "au-4d053831-5dc2-461b-a358-d062d6115216",
+                        "payload_type": 111
+                    }
+                }
+            }
+        });
+        let result = answer_service.execute(Parameter(params)).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "invalid constraints: audio_params: payload_type of PCMU must be 0, but 111 was given"
+            );
+        } else {
+            assert!(false);
+        }
+    }
 }
@@ -1,13 +1,27 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use serde_json::Value;
 use shaku::*;
 
 use crate::application::dto::request_message::Parameter;
 use crate::application::dto::response_message::{MediaResponse, ResponseResult};
 use crate::application::usecase::service::Service;
-use crate::domain::webrtc::media::entity::MediaConnectionIdWrapper;
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+use crate::domain::listener::repository::{EventPublisher, ListenerRegistry};
+use crate::domain::listener::retry::RetryPolicy;
+use crate::domain::registry::allocation::allocate_media_redirect;
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::registry::validation::{reserve_redirect_params, validate_resources};
+use crate::domain::webrtc::common::value_object::{PhantomId, SerializableSocket, SocketInfo};
+use crate::domain::webrtc::media::entity::{
+    CallQuery, CallResult, Constraints, MediaParams, RedirectParameters,
+};
+use crate::domain::webrtc::media::preset::{expand_presets, validate_constraints};
 use crate::domain::webrtc::media::repository::MediaRepository;
+use crate::domain::webrtc::media::service::{take_wait_ready, wait_ready};
 use crate::error;
 
 // Serviceの具象Struct
@@ -17,24 +31,174 @@ use crate::error;
 pub(crate) struct CallService {
     #[shaku(inject)]
     repository: Arc<dyn MediaRepository>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
+    #[shaku(inject)]
+    publisher: Arc<dyn EventPublisher>,
+    #[shaku(default)]
+    retry: RetryPolicy,
+}
+
+// media socketとrtcp socketのアドレス
+type SocketPair = (Option<SocketInfo<PhantomId>>, Option<SocketInfo<PhantomId>>);
+
+// 記録済みのアドレスをSocketInfoに変換する
+fn socket_info(address: Option<SocketAddr>) -> Result<Option<SocketInfo<PhantomId>>, error::Error> {
+    match address {
+        Some(address) => {
+            let socket = SocketInfo::<PhantomId>::try_create(
+                None,
+                &address.ip().to_string(),
+                address.port(),
+            )?;
+            Ok(Some(socket))
+        }
+        None => Ok(None),
+    }
+}
+
+impl CallService {
+    // media_params内のmedia_id, rtcp_idに対応するアドレスを返す
+    fn media_sockets(&self, params: Option<&MediaParams>) -> Result<SocketPair, error::Error> {
+        let params = match params {
+            Some(params) => params,
+            None => return Ok((None, None)),
+        };
+        let media = socket_info(self.registry.media_address(&params.media_id))?;
+        let rtcp = socket_info(
+            params
+                .rtcp_id
+                .as_ref()
+                .and_then(|rtcp_id| self.registry.rtcp_address(rtcp_id)),
+        )?;
+        Ok((media, rtcp))
+    }
+
+    // 送信に利用するmedia socket, rtcp socketのアドレスを、redirect_paramsと同じ形式で返す
+    fn send_sockets(
+        &self,
+        constraints: Option<&Constraints>,
+    ) -> Result<Option<RedirectParameters>, error::Error> {
+        let constraints = match constraints {
+            Some(constraints) => constraints,
+            None => return Ok(None),
+        };
+        let (video, video_rtcp) = self.media_sockets(constraints.video_params.as_ref())?;
+        let (audio, audio_rtcp) = self.media_sockets(constraints.audio_params.as_ref())?;
+        Ok(Some(RedirectParameters {
+            video,
+            video_rtcp,
+            audio,
+            audio_rtcp,
+        }))
+    }
+
+    async fn call(
+        &self,
+        value: Value,
+        wait: Option<Duration>,
+    ) -> Result<ResponseResult, error::Error> {
+        let call_query = Parameter(value).deserialize::<CallQuery>()?;
+        if let Some(ref constraints) = call_query.constraints {
+            validate_constraints(constraints)?;
+        }
+        // 参照しているリソースが利用可能か確認する
+        validate_resources(
+            self.registry.as_ref(),
+            call_query.constraints.as_ref(),
+            call_query.redirect_params.as_ref(),
+        )?;
+        let redirect_params = call_query.redirect_params.clone();
+        let constraints = call_query.constraints.clone();
+        let peer_id = call_query.peer_id.clone();
+        let result = self.repository.call(call_query).await?;
+        let media_connection_id = result.params.media_connection_id;
+        reserve_redirect_params(
+            self.registry.as_ref(),
+            media_connection_id.as_str(),
+            redirect_params.as_ref(),
+        );
+        // READYを待つ場合は、送信に利用するアドレスも返す
+        let mut ready = None;
+        let send_sockets = match wait {
+            Some(timeout) => {
+                let result = wait_ready(
+                    self.repository.clone(),
+                    self.listeners.as_ref(),
+                    self.publisher.as_ref(),
+                    &self.retry,
+                    &media_connection_id,
+                    timeout,
+                )
+                .await
+                .and_then(|event| {
+                    ready = event;
+                    self.send_sockets(constraints.as_ref())
+                });
+                match result {
+                    Ok(send_sockets) => send_sockets,
+                    Err(e) => {
+                        // READYにならなかったMediaConnectionは切断し、redirect先を開放する
+                        let _ = self.repository.disconnect(&media_connection_id).await;
+                        self.registry.release_redirect(media_connection_id.as_str());
+                        return Err(e);
+                    }
+                }
+            }
+            None => None,
+        };
+        // PeerのCLOSE時に監視を終了できるよう、所有者を記録する
+        self.listeners.set_owner(
+            peer_id.as_str(),
+            ListenerInfo::new(ListenerKind::Media, media_connection_id.as_str()),
+        );
+        // 待機中に取得したREADYは、監視ループが取得したeventと同様に配信する
+        if let Some(event) = ready {
+            self.publisher
+                .publish_media(&media_connection_id, event)
+                .await;
+        }
+        let result = CallResult {
+            media_connection_id,
+            send_sockets,
+            recv_sockets: redirect_params,
+        };
+        Ok(MediaResponse::Call(result).create_response_message())
+    }
 }
 
 #[async_trait]
 impl Service for CallService {
     async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
-        let call_query = params.deserialize()?;
-        let result = self.repository.call(call_query).await?;
-        let wrapper = MediaConnectionIdWrapper {
-            media_connection_id: result.params.media_connection_id,
-        };
-        Ok(MediaResponse::Call(wrapper).create_response_message())
+        // presetを展開し、codecの設定に不整合がないか確認してからcallする
+        let mut value = params.0;
+        let wait = take_wait_ready(&mut value);
+        if let Some(constraints) = value.get_mut("constraints") {
+            expand_presets(constraints)?;
+        }
+        // "auto"と指定されたredirect先にportを割り当てる
+        let allocated = allocate_media_redirect(self.registry.as_ref(), &mut value)?;
+        let result = self.call(value, wait).await;
+        // callに失敗した場合は、割り当てたportを開放する
+        if result.is_err() {
+            self.registry.release_ports(&allocated);
+        }
+        result
     }
 }
 
 #[cfg(test)]
 mod test_create_media {
+    use crate::application::dto::response_message::ResponseMessage;
     use crate::di::MediaCallServiceContainer;
-    use crate::domain::webrtc::media::entity::{CallQuery, CallResponse, MediaConnectionIdWrapper};
+    use crate::domain::listener::repository::MockEventPublisher;
+    use crate::domain::registry::entity::MediaKind;
+    use crate::domain::registry::repository::MockResourceRegistry;
+    use crate::domain::webrtc::media::entity::{
+        CallResponse, MediaConnectionEventEnum, MediaConnectionIdWrapper,
+    };
     use crate::domain::webrtc::media::repository::MockMediaRepository;
     use crate::domain::webrtc::media::value_object::MediaConnectionId;
     use crate::domain::webrtc::peer::value_object::{PeerId, Token};
@@ -46,8 +210,10 @@ mod test_create_media {
         // 期待値を生成
         let media_connection_id =
             MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
-        let expected = MediaResponse::Call(MediaConnectionIdWrapper {
+        let expected = MediaResponse::Call(CallResult {
             media_connection_id: media_connection_id.clone(),
+            send_sockets: None,
+            recv_sockets: None,
         })
         .create_response_message();
 
@@ -111,4 +277,428 @@ mod test_create_media {
             assert!(false);
         }
     }
+
+    #[tokio::test]
+    async fn expand_preset() {
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+
+        // presetが展開された値でcallされる
+        let mut mock = MockMediaRepository::default();
+        mock.expect_call().returning(move |query| {
+            let video_params = query.constraints.unwrap().video_params.unwrap();
+            assert_eq!(video_params.codec, "VP8");
+            assert_eq!(video_params.payload_type.map(|pt| pt as u64), Some(96));
+            assert_eq!(
+                video_params.sampling_rate.map(|rate| rate as u64),
+                Some(90000)
+            );
+            Ok(CallResponse {
+                command_type: "CALL".to_string(),
+                params: MediaConnectionIdWrapper {
+                    media_connection_id: media_connection_id.clone(),
+                },
+            })
+        });
+
+        // 参照するmedia socketは生成済みである
+        let mut registry = MockResourceRegistry::default();
+        registry
+            .expect_find_media()
+            .returning(|_| Some(MediaKind::Video));
+        registry.expect_allocates_omitted().returning(|| false);
+
+        // Mockを埋め込んだCallServiceを生成
+        let module = MediaCallServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
+            .build();
+        let call_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let call_query = serde_json::json!({
+            "peer_id": "peer_id",
+            "token": "pt-9749250e-d157-4f80-9ee2-359ce8524308",
+            "target_id": "target_id",
+            "constraints": {
+                "video": true,
+                "audio": false,
+                "video_params": {
+                    "preset": "vp8_low",
+                    "media_id": "vi-4d053831-5dc2-461b-a358-d062d6115216"
+                }
+            }
+        });
+        let result = call_service.execute(Parameter(call_query)).await;
+
+        // evaluate
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn inconsistent_constraints() {
+        // codecの設定に不整合があるのでcallは呼ばれない
+        let mock = MockMediaRepository::default();
+        let module = MediaCallServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .build();
+        let call_service: Arc<dyn Service> = module.resolve();
+
+        // OPUSに8000Hzを指定する
+        let call_query = serde_json::json!({
+            "peer_id": "peer_id",
+            "token": "pt-9749250e-d157-4f80-9ee2-359ce8524308",
+            "target_id": "target_id",
+            "constraints": {
+                "video": false,
+                "audio": true,
+                "audio_params": {
+                    "band_width": 128,
+                    "codec": "OPUS",
+                    "media_id": "au-4d053831-5dc2-461b-a358-d062d6115216",
+                    "sampling_rate": 8000
+                }
+            }
+        });
+        let result = call_service.execute(Parameter(call_query)).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "invalid constraints: audio_params: sampling_rate of OPUS must be 48000, but 8000 was given"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_resources() {
+        // 事前チェックに失敗するのでcallは呼ばれない
+        let mut mock = MockMediaRepository::default();
+        mock.expect_call().never();
+
+        // 削除済みのmedia socketと、他のMediaConnectionが利用中のportを参照しているケース
+        let mut registry = MockResourceRegistry::default();
+        registry.expect_find_media().returning(|_| None);
+        registry.expect_allocates_omitted().returning(|| false);
+        // 割り当てたportはないので、空のまま開放される
+        registry.expect_release_ports().returning(|_| ());
+        registry
+            .expect_redirect_owner()
+            .returning(|_| Some("mc-00000000-b3d9-4913-8e20-f79c90a6a211".to_string()));
+        registry.expect_reserve_redirect().never();
+
+        // Mockを埋め込んだCallServiceを生成
+        let module = MediaCallServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
+            .build();
+        let call_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let call_query = serde_json::json!({
+            "peer_id": "peer_id",
+            "token": "pt-9749250e-d157-4f80-9ee2-359ce8524308",
+            "target_id": "target_id",
+            "constraints": {
+                "video": true,
+                "audio": false,
+                "video_params": {
+                    "band_width": 1500,
+                    "codec": "H264",
+                    "media_id": "vi-4d053831-5dc2-461b-a358-d062d6115216"
+                }
+            },
+            "redirect_params": {
+                "video": {
+                    "ip_v4": "127.0.0.1",
+                    "port": 13000
+                }
+            }
+        });
+        let result = call_service.execute(Parameter(call_query)).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "invalid resources: \
+                 video_params.media_id vi-4d053831-5dc2-461b-a358-d062d6115216 does not exist or has been deleted, \
+                 redirect_params.video 127.0.0.1:13000 is already used by mc-00000000-b3d9-4913-8e20-f79c90a6a211"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+
+    // "auto"と指定されたredirect先にportが割り当てられ、レスポンスで返される
+    #[tokio::test]
+    async fn allocate_redirect() {
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+
+        // 割り当てられたportでcallされる
+        let mut mock = MockMediaRepository::default();
+        let id = media_connection_id.clone();
+        mock.expect_call().returning(move |query| {
+            let video = query.redirect_params.unwrap().video.unwrap();
+            assert_eq!(video.port(), 13000);
+            Ok(CallResponse {
+                command_type: "CALL".to_string(),
+                params: MediaConnectionIdWrapper {
+                    media_connection_id: id.clone(),
+                },
+            })
+        });
+
+        // portを割り当て、確立したMediaConnectionに紐付けるMockを作成
+        let mut registry = MockResourceRegistry::default();
+        registry
+            .expect_allocate_port()
+            .returning(|| Ok("127.0.0.1:13000".parse().unwrap()));
+        registry.expect_allocates_omitted().returning(|| false);
+        registry.expect_redirect_owner().returning(|_| None);
+        registry
+            .expect_reserve_redirect()
+            .times(1)
+            .returning(|connection_id, addresses| {
+                assert_eq!(connection_id, "mc-50a32bab-b3d9-4913-8e20-f79c90a6a211");
+                assert_eq!(addresses.len(), 1);
+            });
+        registry.expect_release_ports().never();
+
+        // Mockを埋め込んだCallServiceを生成
+        let module = MediaCallServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
+            .build();
+        let call_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let call_query = serde_json::json!({
+            "peer_id": "peer_id",
+            "token": "pt-9749250e-d157-4f80-9ee2-359ce8524308",
+            "target_id": "target_id",
+            "redirect_params": {
+                "video": "auto"
+            }
+        });
+        let result = call_service.execute(Parameter(call_query)).await.unwrap();
+
+        // evaluate
+        if let ResponseResult::Success(ResponseMessage::Media(MediaResponse::Call(result))) = result
+        {
+            assert_eq!(result.media_connection_id, media_connection_id);
+            let video = result.recv_sockets.unwrap().video.unwrap();
+            assert_eq!(video.port(), 13000);
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[tokio::test]
+    async fn release_allocated_ports() {
+        // callに失敗するMockを作成
+        let mut mock = MockMediaRepository::default();
+        mock.expect_call()
+            .returning(|_| Err(error::Error::create_local_error("call error")));
+
+        // 割り当てたportが開放される
+        let mut registry = MockResourceRegistry::default();
+        registry
+            .expect_allocate_port()
+            .returning(|| Ok("127.0.0.1:13000".parse().unwrap()));
+        registry.expect_allocates_omitted().returning(|| false);
+        registry.expect_redirect_owner().returning(|_| None);
+        registry.expect_reserve_redirect().never();
+        registry.expect_release_ports().times(1).returning(|_| ());
+
+        // Mockを埋め込んだCallServiceを生成
+        let module = MediaCallServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
+            .build();
+        let call_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let call_query = serde_json::json!({
+            "peer_id": "peer_id",
+            "token": "pt-9749250e-d157-4f80-9ee2-359ce8524308",
+            "target_id": "target_id",
+            "redirect_params": {
+                "video": "auto"
+            }
+        });
+        let result = call_service.execute(Parameter(call_query)).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(message, "call error");
+        } else {
+            assert!(false);
+        }
+    }
+
+    // READYを待つ場合は、送信に利用するアドレスも返される
+    #[tokio::test]
+    async fn wait_ready() {
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+
+        // callに成功し、READY eventを返すMockを作成
+        let mut mock = MockMediaRepository::default();
+        let id = media_connection_id.clone();
+        mock.expect_call().returning(move |_| {
+            Ok(CallResponse {
+                command_type: "CALL".to_string(),
+                params: MediaConnectionIdWrapper {
+                    media_connection_id: id.clone(),
+                },
+            })
+        });
+        let id = media_connection_id.clone();
+        mock.expect_event().times(1).returning(move |_| {
+            Ok(MediaConnectionEventEnum::READY(MediaConnectionIdWrapper {
+                media_connection_id: id.clone(),
+            }))
+        });
+        mock.expect_disconnect().never();
+        // 待機中に取得したREADYは配信される
+        let mut publisher = MockEventPublisher::default();
+        let id = media_connection_id.clone();
+        publisher
+            .expect_publish_media()
+            .times(1)
+            .returning(move |media_connection_id, event| {
+                assert_eq!(media_connection_id, &id);
+                assert_eq!(
+                    event,
+                    MediaConnectionEventEnum::READY(MediaConnectionIdWrapper {
+                        media_connection_id: id.clone(),
+                    })
+                );
+            });
+
+        // 参照するmedia socketは生成済みである
+        let mut registry = MockResourceRegistry::default();
+        registry
+            .expect_find_media()
+            .returning(|_| Some(MediaKind::Video));
+        registry
+            .expect_media_address()
+            .returning(|_| Some("127.0.0.1:10000".parse().unwrap()));
+        registry.expect_allocates_omitted().returning(|| false);
+
+        // Mockを埋め込んだCallServiceを生成
+        let module = MediaCallServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
+            .with_component_override::<dyn EventPublisher>(Box::new(publisher))
+            .build();
+        let call_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let call_query = serde_json::json!({
+            "peer_id": "peer_id",
+            "token": "pt-9749250e-d157-4f80-9ee2-359ce8524308",
+            "target_id": "target_id",
+            "constraints": {
+                "video": true,
+                "audio": false,
+                "video_params": {
+                    "preset": "h264_720p",
+                    "media_id": "vi-4d053831-5dc2-461b-a358-d062d6115216"
+                }
+            },
+            "wait_ready": true
+        });
+        let result = call_service.execute(Parameter(call_query)).await.unwrap();
+
+        // evaluate
+        if let ResponseResult::Success(ResponseMessage::Media(MediaResponse::Call(result))) = result
+        {
+            assert_eq!(result.media_connection_id, media_connection_id);
+            let send_sockets = result.send_sockets.unwrap();
+            assert_eq!(send_sockets.video.unwrap().port(), 10000);
+            assert!(send_sockets.video_rtcp.is_none());
+            assert!(send_sockets.audio.is_none());
+        } else {
+            assert!(false);
+        }
+    }
+
+    // READYにならなかった場合は切断され、redirect先も開放される
+    #[tokio::test]
+    async fn rollback_when_not_ready() {
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+
+        // callには成功するが、READYの前にCLOSEするMockを作成
+        let mut mock = MockMediaRepository::default();
+        let id = media_connection_id.clone();
+        mock.expect_call().returning(move |_| {
+            Ok(CallResponse {
+                command_type: "CALL".to_string(),
+                params: MediaConnectionIdWrapper {
+                    media_connection_id: id.clone(),
+                },
+            })
+        });
+        mock.expect_event().times(1).returning(move |_| {
+            Ok(MediaConnectionEventEnum::CLOSE(MediaConnectionIdWrapper {
+                media_connection_id: media_connection_id.clone(),
+            }))
+        });
+        mock.expect_disconnect().times(1).returning(|_| Ok(()));
+
+        let mut registry = MockResourceRegistry::default();
+        registry
+            .expect_allocate_port()
+            .returning(|| Ok("127.0.0.1:13000".parse().unwrap()));
+        registry.expect_allocates_omitted().returning(|| false);
+        registry.expect_redirect_owner().returning(|_| None);
+        registry
+            .expect_reserve_redirect()
+            .times(1)
+            .returning(|_, _| ());
+        registry
+            .expect_release_redirect()
+            .times(1)
+            .returning(|connection_id| {
+                assert_eq!(connection_id, "mc-50a32bab-b3d9-4913-8e20-f79c90a6a211");
+            });
+        registry.expect_release_ports().returning(|_| ());
+
+        // Mockを埋め込んだCallServiceを生成
+        let module = MediaCallServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
+            .build();
+        let call_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let call_query = serde_json::json!({
+            "peer_id": "peer_id",
+            "token": "pt-9749250e-d157-4f80-9ee2-359ce8524308",
+            "target_id": "target_id",
+            "redirect_params": {
+                "video": "auto"
+            },
+            "wait_ready": true,
+            "wait_ready_timeout_ms": 1000
+        });
+        let result = call_service.execute(Parameter(call_query)).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "MediaConnection mc-50a32bab-b3d9-4913-8e20-f79c90a6a211 was closed before READY"
+            );
+        } else {
+            assert!(false);
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -7,6 +8,9 @@ use shaku::*;
 use crate::application::dto::request_message::Parameter;
 use crate::application::dto::response_message::{MediaResponse, ResponseResult};
 use crate::application::usecase::service::Service;
+use crate::domain::registry::entity::MediaKind;
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::webrtc::common::value_object::SerializableSocket;
 use crate::domain::webrtc::media::repository::MediaRepository;
 use crate::error;
 
@@ -23,6 +27,8 @@ struct IsVideo {
 pub(crate) struct CreateMediaService {
     #[shaku(inject)]
     repository: Arc<dyn MediaRepository>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
 }
 
 #[async_trait]
@@ -30,6 +36,12 @@ impl Service for CreateMediaService {
     async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
         let is_video = params.deserialize::<IsVideo>()?.is_video;
         let socket = self.repository.create_media(is_video).await?;
+        // CALL, ANSWERの事前チェックのため、生成したmedia socketを記録する
+        if let Some(media_id) = socket.get_id() {
+            let address = SocketAddr::new(socket.ip(), socket.port());
+            self.registry
+                .register_media(&media_id, MediaKind::from_is_video(is_video), address);
+        }
         Ok(MediaResponse::ContentCreate(socket).create_response_message())
     }
 }
@@ -1,3 +1,4 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -6,6 +7,8 @@ use shaku::*;
 use crate::application::dto::request_message::Parameter;
 use crate::application::dto::response_message::{MediaResponse, ResponseResult};
 use crate::application::usecase::service::Service;
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::webrtc::common::value_object::SerializableSocket;
 use crate::domain::webrtc::media::repository::MediaRepository;
 use crate::error;
 
@@ -16,12 +19,19 @@ use crate::error;
 pub(crate) struct CreateRtcpService {
     #[shaku(inject)]
     repository: Arc<dyn MediaRepository>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
 }
 
 #[async_trait]
 impl Service for CreateRtcpService {
     async fn execute(&self, _params: Parameter) -> Result<ResponseResult, error::Error> {
         let socket = self.repository.create_rtcp().await?;
+        // CALL, ANSWERの事前チェックのため、生成したrtcp socketを記録する
+        if let Some(rtcp_id) = socket.get_id() {
+            let address = SocketAddr::new(socket.ip(), socket.port());
+            self.registry.register_rtcp(&rtcp_id, address);
+        }
         Ok(MediaResponse::RtcpCreate(socket).create_response_message())
     }
 }
@@ -6,6 +6,7 @@ use shaku::*;
 use crate::application::dto::request_message::Parameter;
 use crate::application::dto::response_message::{MediaResponse, ResponseResult};
 use crate::application::usecase::service::Service;
+use crate::domain::registry::repository::ResourceRegistry;
 use crate::domain::webrtc::media::entity::MediaIdWrapper;
 use crate::domain::webrtc::media::repository::MediaRepository;
 use crate::error;
@@ -17,6 +18,8 @@ use crate::error;
 pub(crate) struct DeleteMediaService {
     #[shaku(inject)]
     repository: Arc<dyn MediaRepository>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
 }
 
 #[async_trait]
@@ -24,6 +27,7 @@ impl Service for DeleteMediaService {
     async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
         let media_id = params.deserialize::<MediaIdWrapper>()?.media_id;
         let _ = self.repository.delete_media(&media_id).await?;
+        self.registry.unregister_media(&media_id);
         Ok(MediaResponse::ContentDelete(MediaIdWrapper { media_id }).create_response_message())
     }
 }
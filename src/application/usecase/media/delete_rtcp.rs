@@ -6,6 +6,7 @@ use shaku::*;
 use crate::application::dto::request_message::Parameter;
 use crate::application::dto::response_message::{MediaResponse, ResponseResult};
 use crate::application::usecase::service::Service;
+use crate::domain::registry::repository::ResourceRegistry;
 use crate::domain::webrtc::media::entity::RtcpIdWrapper;
 use crate::domain::webrtc::media::repository::MediaRepository;
 use crate::error;
@@ -17,6 +18,8 @@ use crate::error;
 pub(crate) struct DeleteRtcpService {
     #[shaku(inject)]
     repository: Arc<dyn MediaRepository>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
 }
 
 #[async_trait]
@@ -25,6 +28,7 @@ impl Service for DeleteRtcpService {
         let rtcp_id = params.deserialize::<RtcpIdWrapper>()?.rtcp_id;
 
         let _ = self.repository.delete_rtcp(&rtcp_id).await?;
+        self.registry.unregister_rtcp(&rtcp_id);
         Ok(MediaResponse::RtcpDelete(RtcpIdWrapper { rtcp_id }).create_response_message())
     }
 }
@@ -6,6 +6,7 @@ use shaku::*;
 use crate::application::dto::request_message::Parameter;
 use crate::application::dto::response_message::{MediaResponse, ResponseResult};
 use crate::application::usecase::service::Service;
+use crate::domain::registry::repository::ResourceRegistry;
 use crate::domain::webrtc::media::entity::MediaConnectionIdWrapper;
 use crate::domain::webrtc::media::repository::MediaRepository;
 use crate::error;
@@ -17,6 +18,8 @@ use crate::error;
 pub(crate) struct DisconnectService {
     #[shaku(inject)]
     repository: Arc<dyn MediaRepository>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
 }
 
 #[async_trait]
@@ -26,6 +29,8 @@ impl Service for DisconnectService {
             .deserialize::<MediaConnectionIdWrapper>()?
             .media_connection_id;
         let _ = self.repository.disconnect(&media_connection_id).await?;
+        // redirect先のportを他のMediaConnectionで利用できるようにする
+        self.registry.release_redirect(media_connection_id.as_str());
         Ok(MediaResponse::Disconnect(None).create_response_message())
     }
 }
@@ -5,8 +5,13 @@ use shaku::*;
 use tokio::sync::mpsc;
 
 use crate::application::dto::request_message::Parameter;
-use crate::application::dto::response_message::{MediaResponse, ResponseResult};
+use crate::application::dto::response_message::{
+    ListenerResponse, MediaResponse, ResponseMessage, ResponseResult,
+};
 use crate::application::usecase::service::EventListener;
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind, ListenerStopped};
+use crate::domain::listener::retry::RetryPolicy;
+use crate::domain::registry::repository::ResourceRegistry;
 use crate::domain::state::ApplicationState;
 use crate::domain::webrtc::media::entity::{MediaConnectionEventEnum, MediaConnectionIdWrapper};
 use crate::domain::webrtc::media::repository::MediaRepository;
@@ -21,42 +26,61 @@ pub(crate) struct EventService {
     repository: Arc<dyn MediaRepository>,
     #[shaku(inject)]
     state: Arc<dyn ApplicationState>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
+    #[shaku(default)]
+    retry: RetryPolicy,
 }
 
-impl EventService {
-    async fn listen(
-        &self,
-        event_tx: mpsc::Sender<ResponseResult>,
-        media_connection_id: MediaConnectionId,
-    ) -> ResponseResult {
-        while self.state.is_running() {
-            let event = self.repository.event(&media_connection_id).await;
-            match event {
-                Ok(MediaConnectionEventEnum::CLOSE(media_connection_id)) => {
-                    let message =
-                        MediaResponse::Event(MediaConnectionEventEnum::CLOSE(media_connection_id))
-                            .create_response_message();
-                    let _ = event_tx.send(message.clone()).await;
-                    return message;
-                }
-                Ok(MediaConnectionEventEnum::TIMEOUT) => {
-                    // TIMEOUTはユーザに通知する必要がない
-                }
-                Ok(event) => {
-                    let message = MediaResponse::Event(event).create_response_message();
-                    let _ = event_tx.send(message).await;
-                }
-                Err(e) => {
-                    let message = format!("error in EventListener for Media {:?}", e);
-                    let message = ResponseResult::Error(message);
-                    let _ = event_tx.send(message.clone()).await;
-                    return message;
-                }
+// CLOSEを受信するか、Errorから回復できなくなるまで、MediaConnectionのイベントを監視する
+// fan-outのevent listenerからも利用するため、EventServiceから独立させている
+pub(crate) async fn listen(
+    repository: &dyn MediaRepository,
+    state: &dyn ApplicationState,
+    retry: &RetryPolicy,
+    event_tx: mpsc::Sender<ResponseResult>,
+    media_connection_id: MediaConnectionId,
+) -> ResponseResult {
+    while state.is_running() {
+        let event = retry.call(|| repository.event(&media_connection_id)).await;
+        match event {
+            Ok(MediaConnectionEventEnum::CLOSE(media_connection_id)) => {
+                let message =
+                    MediaResponse::Event(MediaConnectionEventEnum::CLOSE(media_connection_id))
+                        .create_response_message();
+                let _ = event_tx.send(message.clone()).await;
+                return message;
+            }
+            Ok(MediaConnectionEventEnum::TIMEOUT) => {
+                // TIMEOUTはユーザに通知する必要がない
+            }
+            Ok(event) => {
+                let message = MediaResponse::Event(event).create_response_message();
+                let _ = event_tx.send(message).await;
+            }
+            Err(reason) => {
+                // 再試行しても回復しなかったので、監視を諦めたことを通知する
+                let listener = ListenerInfo::new(ListenerKind::Media, media_connection_id.as_str());
+                let message = ListenerResponse::Stopped(ListenerStopped { listener, reason })
+                    .create_response_message();
+                let _ = event_tx.send(message.clone()).await;
+                return message;
             }
         }
-
-        MediaResponse::Event(MediaConnectionEventEnum::TIMEOUT).create_response_message()
     }
+
+    MediaResponse::Event(MediaConnectionEventEnum::TIMEOUT).create_response_message()
+}
+
+// listenがCLOSEを受信して終了したかを判定する
+// UNSUBSCRIBEやLISTENER_STOPPEDで終了した場合、MediaConnectionはまだ利用中の可能性がある
+pub(crate) fn is_closed(result: &ResponseResult) -> bool {
+    matches!(
+        result,
+        ResponseResult::Success(ResponseMessage::Media(MediaResponse::Event(
+            MediaConnectionEventEnum::CLOSE(_)
+        )))
+    )
 }
 
 #[async_trait]
@@ -75,16 +99,31 @@ impl EventListener for EventService {
             return ResponseResult::Error(message);
         }
         let media_connection_id = media_connection_id_wrapper.unwrap().media_connection_id;
-        self.listen(event_tx, media_connection_id).await
+        let result = listen(
+            self.repository.as_ref(),
+            self.state.as_ref(),
+            &self.retry,
+            event_tx,
+            media_connection_id.clone(),
+        )
+        .await;
+        // CLOSEしたMediaConnectionのredirect先は、他のMediaConnectionで利用できるようにする
+        // UNSUBSCRIBEやLISTENER_STOPPEDで監視を終えた場合は、まだ利用中の可能性があるので開放しない
+        if is_closed(&result) {
+            self.registry.release_redirect(media_connection_id.as_str());
+        }
+        result
     }
 }
 
 #[cfg(test)]
 mod test_delete_media {
     use crate::di::MediaEventServiceContainer;
+    use crate::domain::registry::repository::MockResourceRegistry;
     use crate::domain::webrtc::media::entity::MediaConnectionStatus;
     use crate::domain::webrtc::media::repository::MockMediaRepository;
     use crate::domain::webrtc::peer::value_object::PeerId;
+    use crate::error;
     use crate::infra::state::ApplicationStateAlwaysFalseImpl;
 
     use super::*;
@@ -140,8 +179,13 @@ mod test_delete_media {
             });
         });
 
+        // CLOSEした場合のみredirect先を開放する
+        let mut registry = MockResourceRegistry::default();
+        registry.expect_release_redirect().times(1).return_const(());
+
         let module = &MediaEventServiceContainer::builder()
             .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
             .build();
         let event_service: &dyn EventListener = module.resolve_ref();
 
@@ -222,8 +266,13 @@ mod test_delete_media {
             });
         });
 
+        // 監視を終えてもredirect先は開放しない
+        let mut registry = MockResourceRegistry::default();
+        registry.expect_release_redirect().times(0);
+
         let module = &MediaEventServiceContainer::builder()
             .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
             // 常にfalseを返すStateObject
             .with_component_override::<dyn ApplicationState>(Box::new(
                 ApplicationStateAlwaysFalseImpl {},
@@ -247,6 +296,45 @@ mod test_delete_media {
         );
     }
 
+    // 再試行しないErrorを受信した場合は、LISTENER_STOPPEDを通知して監視を終了する
+    #[tokio::test]
+    async fn stopped() {
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+        let expected = ListenerResponse::Stopped(ListenerStopped {
+            listener: ListenerInfo::new(ListenerKind::Media, media_connection_id.as_str()),
+            reason: "event api returned an error: LocalError(\"recv Not Found\")".into(),
+        })
+        .create_response_message();
+
+        // eventを受け取るためのチャンネルを作成
+        let (event_tx, mut event_rx) = mpsc::channel::<ResponseResult>(10);
+
+        // 既に存在しないMediaConnectionの場合のMockを作成
+        let mut mock = MockMediaRepository::default();
+        mock.expect_event()
+            .returning(move |_| Err(error::Error::create_local_error("recv Not Found")));
+
+        // 監視を終えてもredirect先は開放しない
+        let mut registry = MockResourceRegistry::default();
+        registry.expect_release_redirect().times(0);
+
+        let module = &MediaEventServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn ResourceRegistry>(Box::new(registry))
+            .build();
+        let event_service: &dyn EventListener = module.resolve_ref();
+        let param = MediaConnectionIdWrapper {
+            media_connection_id,
+        };
+        let result = event_service
+            .execute(event_tx, Parameter(serde_json::to_value(param).unwrap()))
+            .await;
+
+        assert_eq!(result, expected);
+        assert_eq!(event_rx.recv().await.unwrap(), expected);
+    }
+
     #[tokio::test]
     async fn invalid_param() {
         // eventを受け取るためのチャンネルを作成
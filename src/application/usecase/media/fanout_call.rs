@@ -0,0 +1,412 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use shaku::*;
+
+use crate::application::dto::request_message::Parameter;
+use crate::application::dto::response_message::{MediaResponse, ResponseResult};
+use crate::application::usecase::service::Service;
+use crate::domain::fanout::entity::FanoutCallResult;
+use crate::domain::fanout::repository::FanoutRepository;
+use crate::domain::fanout::value_object::FanoutId;
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+use crate::domain::listener::repository::ListenerRegistry;
+use crate::domain::registry::entity::MediaKind;
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::registry::validation::{reserve_redirect_params, validate_resources};
+use crate::domain::webrtc::common::value_object::{SerializableId, SerializableSocket};
+use crate::domain::webrtc::media::entity::CallQuery;
+use crate::domain::webrtc::media::preset::{expand_presets, validate_constraints};
+use crate::domain::webrtc::media::repository::MediaRepository;
+use crate::domain::webrtc::media::value_object::MediaId;
+use crate::error;
+
+// エンドユーザから渡されるJSONのparamsフィールドを構造化するためのStruct
+// call_queryはCallQueryと同じ形式だが、fan-outの種別(video/audio)に対応するmedia_idは
+// このServiceが生成するため省略できる
+#[derive(Serialize, Deserialize)]
+struct FanoutCallParameters {
+    fanout_id: FanoutId,
+    call_query: Value,
+}
+
+// Serviceの具象Struct
+// DIコンテナからのみオブジェクトを生成できる
+#[derive(Component)]
+#[shaku(interface = Service)]
+pub(crate) struct FanoutCallService {
+    #[shaku(inject)]
+    repository: Arc<dyn MediaRepository>,
+    #[shaku(inject)]
+    fanout: Arc<dyn FanoutRepository>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
+}
+
+impl FanoutCallService {
+    // 発信に失敗した場合に、生成したmedia socketを開放する
+    async fn release_media(&self, media_id: &MediaId) {
+        let _ = self.repository.delete_media(media_id).await;
+        self.registry.unregister_media(media_id);
+    }
+}
+
+// objectでない値が入っている場合はエラーとし、存在しない場合は空のobjectを挿入する
+fn object_entry<'a>(
+    object: &'a mut Map<String, Value>,
+    key: &str,
+) -> Result<&'a mut Map<String, Value>, error::Error> {
+    let entry = object
+        .entry(key)
+        .or_insert_with(|| Value::Object(Map::new()));
+    if entry.is_null() {
+        *entry = Value::Object(Map::new());
+    }
+    entry.as_object_mut().ok_or_else(|| {
+        let message = format!("{} in call_query must be a JSON object", key);
+        error::Error::create_local_error(&message)
+    })
+}
+
+// call_queryに、生成したmedia socketのIDを埋め込んでCallQueryを生成する
+fn fill_media_id(
+    mut call_query: Value,
+    is_video: bool,
+    media_id: &MediaId,
+) -> Result<CallQuery, error::Error> {
+    let (flag, params_key, other_flag) = if is_video {
+        ("video", "video_params", "audio")
+    } else {
+        ("audio", "audio_params", "video")
+    };
+
+    let query = call_query
+        .as_object_mut()
+        .ok_or_else(|| error::Error::create_local_error("call_query must be a JSON object"))?;
+    if let Some(constraints) = query.get_mut("constraints") {
+        expand_presets(constraints)?;
+    }
+    let constraints = object_entry(query, "constraints")?;
+    constraints.insert(flag.to_string(), Value::Bool(true));
+    constraints.entry(other_flag).or_insert(Value::Bool(false));
+    let media_params = object_entry(constraints, params_key)?;
+    media_params.insert(
+        "media_id".to_string(),
+        Value::String(media_id.as_str().to_string()),
+    );
+
+    let call_query = serde_json::from_value::<CallQuery>(call_query)
+        .map_err(|e| error::Error::SerdeError { error: e })?;
+    if let Some(ref constraints) = call_query.constraints {
+        validate_constraints(constraints)?;
+    }
+    Ok(call_query)
+}
+
+#[async_trait]
+impl Service for FanoutCallService {
+    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
+        let params = params.deserialize::<FanoutCallParameters>()?;
+        let fanout = self.fanout.find(&params.fanout_id).ok_or_else(|| {
+            let message = format!("fan-out {} does not exist", params.fanout_id.as_str());
+            error::Error::create_local_error(&message)
+        })?;
+
+        // 発信ごとに送信用のmedia socketを生成する
+        let media_socket = self.repository.create_media(fanout.is_video).await?;
+        let media_id = media_socket
+            .get_id()
+            .ok_or_else(|| error::Error::create_local_error("media socket has no media_id"))?;
+
+        let address = SocketAddr::new(media_socket.ip(), media_socket.port());
+        self.registry.register_media(
+            &media_id,
+            MediaKind::from_is_video(fanout.is_video),
+            address,
+        );
+
+        // 以降で失敗した場合は、生成したmedia socketを開放してからエラーを返す
+        let call_query = match fill_media_id(params.call_query, fanout.is_video, &media_id)
+            .and_then(|call_query| {
+                validate_resources(
+                    self.registry.as_ref(),
+                    call_query.constraints.as_ref(),
+                    call_query.redirect_params.as_ref(),
+                )
+                .map(|_| call_query)
+            }) {
+            Ok(call_query) => call_query,
+            Err(e) => {
+                self.release_media(&media_id).await;
+                return Err(e);
+            }
+        };
+        let redirect_params = call_query.redirect_params.clone();
+        let peer_id = call_query.peer_id.clone();
+        let media_connection_id = match self.repository.call(call_query).await {
+            Ok(response) => response.params.media_connection_id,
+            Err(e) => {
+                self.release_media(&media_id).await;
+                return Err(e);
+            }
+        };
+        if let Err(e) = self.fanout.add_recipient(
+            &fanout.fanout_id,
+            &media_connection_id,
+            media_socket.clone(),
+        ) {
+            // 発信中にfan-outが削除された場合
+            let _ = self.repository.disconnect(&media_connection_id).await;
+            self.release_media(&media_id).await;
+            return Err(e);
+        }
+        reserve_redirect_params(
+            self.registry.as_ref(),
+            media_connection_id.as_str(),
+            redirect_params.as_ref(),
+        );
+        // PeerのCLOSE時に監視を終了できるよう、所有者を記録する
+        self.listeners.set_owner(
+            peer_id.as_str(),
+            ListenerInfo::new(ListenerKind::Media, media_connection_id.as_str()),
+        );
+
+        let result = FanoutCallResult {
+            fanout_id: fanout.fanout_id,
+            media_connection_id,
+            send_socket: media_socket,
+        };
+        Ok(MediaResponse::FanoutCall(result).create_response_message())
+    }
+}
+
+#[cfg(test)]
+mod test_fanout_call {
+    use crate::di::MediaFanoutCallServiceContainer;
+    use crate::domain::fanout::entity::FanoutInfo;
+    use crate::domain::fanout::repository::MockFanoutRepository;
+    use crate::domain::webrtc::common::value_object::{PhantomId, SocketInfo};
+    use crate::domain::webrtc::media::entity::{CallResponse, MediaConnectionIdWrapper};
+    use crate::domain::webrtc::media::repository::MockMediaRepository;
+    use crate::domain::webrtc::media::value_object::MediaConnectionId;
+
+    use super::*;
+
+    fn call_query() -> Value {
+        serde_json::json!({
+            "peer_id": "peer_id",
+            "token": "pt-9749250e-d157-4f80-9ee2-359ce8524308",
+            "target_id": "target_id",
+            "constraints": {
+                "video": true,
+                "audio": false,
+                "video_params": {
+                    "band_width": 1500,
+                    "codec": "H264"
+                }
+            }
+        })
+    }
+
+    fn fanout_info() -> FanoutInfo {
+        FanoutInfo {
+            fanout_id: FanoutId::new("fo-0"),
+            is_video: true,
+            input: SocketInfo::<PhantomId>::try_create(None, "127.0.0.1", 20000).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn success() {
+        // 期待値を生成
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+        let media_socket = SocketInfo::<MediaId>::try_create(
+            Some("vi-50a32bab-b3d9-4913-8e20-f79c90a6a211".into()),
+            "127.0.0.1",
+            10000,
+        )
+        .unwrap();
+        let expected = MediaResponse::FanoutCall(FanoutCallResult {
+            fanout_id: FanoutId::new("fo-0"),
+            media_connection_id: media_connection_id.clone(),
+            send_socket: media_socket.clone(),
+        })
+        .create_response_message();
+
+        // media socketの生成とcallに成功する場合のMockを作成
+        let mut mock = MockMediaRepository::default();
+        mock.expect_create_media()
+            .returning(move |_| Ok(media_socket.clone()));
+        mock.expect_call().returning(move |query| {
+            // 生成されたmedia socketがvideo_paramsに埋め込まれている
+            let media_id = query.constraints.unwrap().video_params.unwrap().media_id;
+            assert_eq!(media_id.as_str(), "vi-50a32bab-b3d9-4913-8e20-f79c90a6a211");
+            Ok(CallResponse {
+                command_type: "CALL".to_string(),
+                params: MediaConnectionIdWrapper {
+                    media_connection_id: media_connection_id.clone(),
+                },
+            })
+        });
+
+        // 複製先の登録に成功する場合のMockを作成
+        let mut fanout = MockFanoutRepository::default();
+        fanout.expect_find().returning(|_| Some(fanout_info()));
+        fanout
+            .expect_add_recipient()
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        // Mockを埋め込んだServiceを生成
+        let module = MediaFanoutCallServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn FanoutRepository>(Box::new(fanout))
+            .build();
+        let call_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let param = FanoutCallParameters {
+            fanout_id: FanoutId::new("fo-0"),
+            call_query: call_query(),
+        };
+        let result = call_service
+            .execute(Parameter(serde_json::to_value(&param).unwrap()))
+            .await
+            .unwrap();
+
+        // evaluate
+        assert_eq!(result, expected);
+    }
+
+    #[tokio::test]
+    async fn call_failed() {
+        let media_socket = SocketInfo::<MediaId>::try_create(
+            Some("vi-50a32bab-b3d9-4913-8e20-f79c90a6a211".into()),
+            "127.0.0.1",
+            10000,
+        )
+        .unwrap();
+
+        // callに失敗した場合は、生成したmedia socketが開放される
+        let mut mock = MockMediaRepository::default();
+        mock.expect_create_media()
+            .returning(move |_| Ok(media_socket.clone()));
+        mock.expect_call()
+            .returning(|_| Err(error::Error::create_local_error("call error")));
+        mock.expect_delete_media().times(1).returning(|_| Ok(()));
+
+        // 複製先は登録されない
+        let mut fanout = MockFanoutRepository::default();
+        fanout.expect_find().returning(|_| Some(fanout_info()));
+        fanout.expect_add_recipient().never();
+
+        // Mockを埋め込んだServiceを生成
+        let module = MediaFanoutCallServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn FanoutRepository>(Box::new(fanout))
+            .build();
+        let call_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let param = FanoutCallParameters {
+            fanout_id: FanoutId::new("fo-0"),
+            call_query: call_query(),
+        };
+        let result = call_service
+            .execute(Parameter(serde_json::to_value(&param).unwrap()))
+            .await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(message, "call error");
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[tokio::test]
+    async fn inconsistent_preset() {
+        let media_socket = SocketInfo::<MediaId>::try_create(
+            Some("vi-50a32bab-b3d9-4913-8e20-f79c90a6a211".into()),
+            "127.0.0.1",
+            10000,
+        )
+        .unwrap();
+
+        // video用のfan-outにaudioのpresetを与えた場合、callせずにmedia socketを開放する
+        let mut mock = MockMediaRepository::default();
+        mock.expect_create_media()
+            .returning(move |_| Ok(media_socket.clone()));
+        mock.expect_call().never();
+        mock.expect_delete_media().times(1).returning(|_| Ok(()));
+
+        let mut fanout = MockFanoutRepository::default();
+        fanout.expect_find().returning(|_| Some(fanout_info()));
+        fanout.expect_add_recipient().never();
+
+        // Mockを埋め込んだServiceを生成
+        let module = MediaFanoutCallServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn FanoutRepository>(Box::new(fanout))
+            .build();
+        let call_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let mut query = call_query();
+        query["constraints"]["video_params"] = serde_json::json!({ "preset": "opus_stereo" });
+        let param = FanoutCallParameters {
+            fanout_id: FanoutId::new("fo-0"),
+            call_query: query,
+        };
+        let result = call_service
+            .execute(Parameter(serde_json::to_value(&param).unwrap()))
+            .await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "invalid constraints: video_params: OPUS is not a video codec"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[tokio::test]
+    async fn fanout_not_found() {
+        // fan-outが存在しないのでmedia socketは生成されない
+        let mock = MockMediaRepository::default();
+        let mut fanout = MockFanoutRepository::default();
+        fanout.expect_find().returning(|_| None);
+
+        // Mockを埋め込んだServiceを生成
+        let module = MediaFanoutCallServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn FanoutRepository>(Box::new(fanout))
+            .build();
+        let call_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let param = FanoutCallParameters {
+            fanout_id: FanoutId::new("fo-0"),
+            call_query: call_query(),
+        };
+        let result = call_service
+            .execute(Parameter(serde_json::to_value(&param).unwrap()))
+            .await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(message, "fan-out fo-0 does not exist");
+        } else {
+            assert!(false);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use shaku::*;
+
+use crate::application::dto::request_message::Parameter;
+use crate::application::dto::response_message::{MediaResponse, ResponseResult};
+use crate::application::usecase::service::Service;
+use crate::domain::fanout::repository::FanoutRepository;
+use crate::domain::webrtc::common::value_object::{PhantomId, SocketInfo};
+use crate::error;
+
+// エンドユーザから渡されるJSONのparamsフィールドを構造化するためのStruct
+#[derive(Serialize, Deserialize)]
+struct FanoutCreateParameters {
+    is_video: bool,
+    input: SocketInfo<PhantomId>,
+}
+
+// Serviceの具象Struct
+// DIコンテナからのみオブジェクトを生成できる
+#[derive(Component)]
+#[shaku(interface = Service)]
+pub(crate) struct FanoutCreateService {
+    #[shaku(inject)]
+    fanout: Arc<dyn FanoutRepository>,
+}
+
+#[async_trait]
+impl Service for FanoutCreateService {
+    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
+        let params = params.deserialize::<FanoutCreateParameters>()?;
+        let info = self.fanout.create(params.is_video, params.input).await?;
+        Ok(MediaResponse::FanoutCreate(info).create_response_message())
+    }
+}
+
+#[cfg(test)]
+mod test_fanout_create {
+    use crate::di::MediaFanoutCreateServiceContainer;
+    use crate::domain::fanout::entity::FanoutInfo;
+    use crate::domain::fanout::repository::MockFanoutRepository;
+    use crate::domain::fanout::value_object::FanoutId;
+    use crate::domain::webrtc::common::value_object::SerializableSocket;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn success() {
+        // 期待値を生成
+        let input = SocketInfo::<PhantomId>::try_create(None, "127.0.0.1", 20000).unwrap();
+        let info = FanoutInfo {
+            fanout_id: FanoutId::new("fo-0"),
+            is_video: true,
+            input: input.clone(),
+        };
+        let expected = MediaResponse::FanoutCreate(info.clone()).create_response_message();
+
+        // bindに成功する場合のMockを作成
+        let mut mock = MockFanoutRepository::default();
+        mock.expect_create().returning(move |_, _| Ok(info.clone()));
+
+        // Mockを埋め込んだServiceを生成
+        let module = MediaFanoutCreateServiceContainer::builder()
+            .with_component_override::<dyn FanoutRepository>(Box::new(mock))
+            .build();
+        let create_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let param = FanoutCreateParameters {
+            is_video: true,
+            input,
+        };
+        let result = create_service
+            .execute(Parameter(serde_json::to_value(&param).unwrap()))
+            .await
+            .unwrap();
+
+        // evaluate
+        assert_eq!(result, expected);
+    }
+
+    #[tokio::test]
+    async fn invalid_param() {
+        // このMockは呼ばれないので、初期化の必要はない
+        let mock = MockFanoutRepository::default();
+
+        // Mockを埋め込んだServiceを生成
+        let module = MediaFanoutCreateServiceContainer::builder()
+            .with_component_override::<dyn FanoutRepository>(Box::new(mock))
+            .build();
+        let create_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let result = create_service
+            .execute(Parameter(serde_json::Value::Bool(true)))
+            .await;
+
+        // 求められるJSONとは異なるのでSerdeErrorが帰る
+        if let Err(error::Error::SerdeError { error: _ }) = result {
+            assert!(true);
+        } else {
+            assert!(false);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use shaku::*;
+
+use crate::application::dto::request_message::Parameter;
+use crate::application::dto::response_message::{MediaResponse, ResponseResult};
+use crate::application::usecase::service::Service;
+use crate::domain::fanout::entity::FanoutIdWrapper;
+use crate::domain::fanout::repository::FanoutRepository;
+use crate::error;
+
+// Serviceの具象Struct
+// DIコンテナからのみオブジェクトを生成できる
+#[derive(Component)]
+#[shaku(interface = Service)]
+pub(crate) struct FanoutDeleteService {
+    #[shaku(inject)]
+    fanout: Arc<dyn FanoutRepository>,
+}
+
+#[async_trait]
+impl Service for FanoutDeleteService {
+    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
+        let fanout_id = params.deserialize::<FanoutIdWrapper>()?.fanout_id;
+        // 入力の複製を停止するのみで、既存のMediaConnectionはCLOSEまで維持される
+        // 各MediaConnectionのmedia socketは、fan-out削除後もCLOSE受信時にevent listenerが開放する
+        let info = self.fanout.delete(&fanout_id)?;
+        Ok(MediaResponse::FanoutDelete(FanoutIdWrapper {
+            fanout_id: info.fanout_id,
+        })
+        .create_response_message())
+    }
+}
+
+#[cfg(test)]
+mod test_fanout_delete {
+    use crate::di::MediaFanoutDeleteServiceContainer;
+    use crate::domain::fanout::entity::FanoutInfo;
+    use crate::domain::fanout::repository::MockFanoutRepository;
+    use crate::domain::fanout::value_object::FanoutId;
+    use crate::domain::webrtc::common::value_object::{PhantomId, SerializableSocket, SocketInfo};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn success() {
+        // 期待値を生成
+        let fanout_id = FanoutId::new("fo-0");
+        let expected = MediaResponse::FanoutDelete(FanoutIdWrapper {
+            fanout_id: fanout_id.clone(),
+        })
+        .create_response_message();
+
+        // 削除に成功する場合のMockを作成
+        let mut mock = MockFanoutRepository::default();
+        mock.expect_delete().returning(|fanout_id| {
+            Ok(FanoutInfo {
+                fanout_id: fanout_id.clone(),
+                is_video: true,
+                input: SocketInfo::<PhantomId>::try_create(None, "127.0.0.1", 20000).unwrap(),
+            })
+        });
+
+        // Mockを埋め込んだServiceを生成
+        let module = MediaFanoutDeleteServiceContainer::builder()
+            .with_component_override::<dyn FanoutRepository>(Box::new(mock))
+            .build();
+        let delete_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let param = FanoutIdWrapper { fanout_id };
+        let result = delete_service
+            .execute(Parameter(serde_json::to_value(&param).unwrap()))
+            .await
+            .unwrap();
+
+        // evaluate
+        assert_eq!(result, expected);
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        // 存在しないfan-outを削除しようとした場合のMockを作成
+        let mut mock = MockFanoutRepository::default();
+        mock.expect_delete().returning(|_| {
+            Err(error::Error::create_local_error(
+                "fan-out fo-0 does not exist",
+            ))
+        });
+
+        // Mockを埋め込んだServiceを生成
+        let module = MediaFanoutDeleteServiceContainer::builder()
+            .with_component_override::<dyn FanoutRepository>(Box::new(mock))
+            .build();
+        let delete_service: Arc<dyn Service> = module.resolve();
+
+        // execute
+        let param = FanoutIdWrapper {
+            fanout_id: FanoutId::new("fo-0"),
+        };
+        let result = delete_service
+            .execute(Parameter(serde_json::to_value(&param).unwrap()))
+            .await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(message, "fan-out fo-0 does not exist");
+        } else {
+            assert!(false);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use shaku::*;
+use tokio::sync::mpsc;
+
+use crate::application::dto::request_message::Parameter;
+use crate::application::dto::response_message::ResponseResult;
+use crate::application::usecase::media::event::{is_closed, listen};
+use crate::application::usecase::service::EventListener;
+use crate::domain::fanout::repository::FanoutRepository;
+use crate::domain::listener::retry::RetryPolicy;
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::state::ApplicationState;
+use crate::domain::webrtc::common::value_object::SerializableSocket;
+use crate::domain::webrtc::media::entity::MediaConnectionIdWrapper;
+use crate::domain::webrtc::media::repository::MediaRepository;
+
+// Serviceの具象Struct
+// DIコンテナからのみオブジェクトを生成できる
+// fan-outから発信したMediaConnectionのイベントを監視し、
+// CLOSEを受信したら複製先から外してmedia socketを開放する
+#[derive(Component)]
+#[shaku(interface = EventListener)]
+pub(crate) struct FanoutEventService {
+    #[shaku(inject)]
+    repository: Arc<dyn MediaRepository>,
+    #[shaku(inject)]
+    fanout: Arc<dyn FanoutRepository>,
+    #[shaku(inject)]
+    state: Arc<dyn ApplicationState>,
+    #[shaku(inject)]
+    registry: Arc<dyn ResourceRegistry>,
+    #[shaku(default)]
+    retry: RetryPolicy,
+}
+
+#[async_trait]
+impl EventListener for FanoutEventService {
+    async fn execute(
+        &self,
+        event_tx: mpsc::Sender<ResponseResult>,
+        params: Parameter,
+    ) -> ResponseResult {
+        let media_connection_id_wrapper = params.deserialize::<MediaConnectionIdWrapper>();
+        if media_connection_id_wrapper.is_err() {
+            let message = format!(
+                "invalid media_connection_id {:?}",
+                media_connection_id_wrapper.err()
+            );
+            return ResponseResult::Error(message);
+        }
+        let media_connection_id = media_connection_id_wrapper.unwrap().media_connection_id;
+        let result = listen(
+            self.repository.as_ref(),
+            self.state.as_ref(),
+            &self.retry,
+            event_tx,
+            media_connection_id.clone(),
+        )
+        .await;
+        // UNSUBSCRIBEやLISTENER_STOPPEDの後も、MediaConnectionへの複製は続ける
+        if !is_closed(&result) {
+            return result;
+        }
+
+        self.registry.release_redirect(media_connection_id.as_str());
+        // 以降このMediaConnectionへは複製しない
+        if let Some(media_id) = self
+            .fanout
+            .remove_recipient(&media_connection_id)
+            .and_then(|socket| socket.get_id())
+        {
+            let _ = self.repository.delete_media(&media_id).await;
+            self.registry.unregister_media(&media_id);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test_fanout_event {
+    use crate::application::dto::response_message::MediaResponse;
+    use crate::di::MediaFanoutEventServiceContainer;
+    use crate::domain::fanout::repository::MockFanoutRepository;
+    use crate::domain::webrtc::common::value_object::{PhantomId, SerializableId, SocketInfo};
+    use crate::domain::webrtc::media::entity::MediaConnectionEventEnum;
+    use crate::domain::webrtc::media::repository::MockMediaRepository;
+    use crate::domain::webrtc::media::value_object::{MediaConnectionId, MediaId};
+    use crate::error;
+    use crate::infra::fanout::FanoutRepositoryImpl;
+
+    use super::*;
+
+    // CLOSEを受信したら複製先から外され、media socketが開放される
+    #[tokio::test]
+    async fn close() {
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+        let close_event = MediaConnectionEventEnum::CLOSE(MediaConnectionIdWrapper {
+            media_connection_id: media_connection_id.clone(),
+        });
+        let expected = MediaResponse::Event(close_event.clone()).create_response_message();
+
+        // CLOSEを返し、media socketの削除を受け付けるMockを作成
+        let mut mock = MockMediaRepository::default();
+        mock.expect_event()
+            .returning(move |_| Ok(close_event.clone()));
+        mock.expect_delete_media().times(1).returning(|media_id| {
+            assert_eq!(media_id.as_str(), "vi-50a32bab-b3d9-4913-8e20-f79c90a6a211");
+            Ok(())
+        });
+
+        // 複製先から外されるMockを作成
+        let mut fanout = MockFanoutRepository::default();
+        fanout.expect_remove_recipient().times(1).returning(|_| {
+            Some(
+                SocketInfo::<MediaId>::try_create(
+                    Some("vi-50a32bab-b3d9-4913-8e20-f79c90a6a211".into()),
+                    "127.0.0.1",
+                    10000,
+                )
+                .unwrap(),
+            )
+        });
+
+        // Mockを埋め込んだEventServiceを生成
+        let module = MediaFanoutEventServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn FanoutRepository>(Box::new(fanout))
+            .build();
+        let event_service: &dyn EventListener = module.resolve_ref();
+
+        // execute
+        let (event_tx, mut event_rx) = mpsc::channel::<ResponseResult>(10);
+        let param = MediaConnectionIdWrapper {
+            media_connection_id: media_connection_id.clone(),
+        };
+        let result = event_service
+            .execute(event_tx, Parameter(serde_json::to_value(param).unwrap()))
+            .await;
+
+        // evaluate
+        assert_eq!(result, expected);
+        assert_eq!(event_rx.recv().await, Some(expected));
+    }
+
+    // fan-outを削除した後にCLOSEを受信しても、media socketが開放される
+    #[tokio::test]
+    async fn close_after_fanout_delete() {
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+        let close_event = MediaConnectionEventEnum::CLOSE(MediaConnectionIdWrapper {
+            media_connection_id: media_connection_id.clone(),
+        });
+
+        // 複製先を登録してからfan-outを削除する
+        let fanout = FanoutRepositoryImpl::default();
+        let input = SocketInfo::<PhantomId>::try_create(None, "127.0.0.1", 0).unwrap();
+        let info = fanout.create(true, input).await.unwrap();
+        let socket = SocketInfo::<MediaId>::try_create(
+            Some("vi-50a32bab-b3d9-4913-8e20-f79c90a6a211".into()),
+            "127.0.0.1",
+            10000,
+        )
+        .unwrap();
+        fanout
+            .add_recipient(&info.fanout_id, &media_connection_id, socket)
+            .unwrap();
+        let _ = fanout.delete(&info.fanout_id).unwrap();
+
+        // CLOSEを返し、media socketの削除を受け付けるMockを作成
+        let mut mock = MockMediaRepository::default();
+        mock.expect_event()
+            .returning(move |_| Ok(close_event.clone()));
+        mock.expect_delete_media().times(1).returning(|media_id| {
+            assert_eq!(media_id.as_str(), "vi-50a32bab-b3d9-4913-8e20-f79c90a6a211");
+            Ok(())
+        });
+
+        // Mockを埋め込んだEventServiceを生成
+        let module = MediaFanoutEventServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn FanoutRepository>(Box::new(fanout))
+            .build();
+        let event_service: &dyn EventListener = module.resolve_ref();
+
+        // execute
+        let (event_tx, _event_rx) = mpsc::channel::<ResponseResult>(10);
+        let param = MediaConnectionIdWrapper {
+            media_connection_id: media_connection_id.clone(),
+        };
+        let _ = event_service
+            .execute(event_tx, Parameter(serde_json::to_value(param).unwrap()))
+            .await;
+    }
+
+    // CLOSE以外で監視を終了した場合は、複製先から外さずmedia socketも開放しない
+    #[tokio::test]
+    async fn listener_stopped() {
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+
+        // 回復しないErrorを返すMockを作成
+        let mut mock = MockMediaRepository::default();
+        mock.expect_event()
+            .returning(|_| Err(error::Error::create_local_error("error")));
+        mock.expect_delete_media().times(0);
+
+        // 複製先から外されないMockを作成
+        let mut fanout = MockFanoutRepository::default();
+        fanout.expect_remove_recipient().times(0);
+
+        // Mockを埋め込んだEventServiceを生成
+        let module = MediaFanoutEventServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(mock))
+            .with_component_override::<dyn FanoutRepository>(Box::new(fanout))
+            .build();
+        let event_service: &dyn EventListener = module.resolve_ref();
+
+        // execute
+        let (event_tx, _event_rx) = mpsc::channel::<ResponseResult>(10);
+        let param = MediaConnectionIdWrapper {
+            media_connection_id,
+        };
+        let result = event_service
+            .execute(event_tx, Parameter(serde_json::to_value(param).unwrap()))
+            .await;
+
+        // evaluate
+        assert!(!is_closed(&result));
+    }
+}
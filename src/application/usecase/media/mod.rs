@@ -6,4 +6,9 @@ pub(crate) mod delete_media;
 pub(crate) mod delete_rtcp;
 pub(crate) mod disconnect;
 pub(crate) mod event;
+pub(crate) mod fanout_call;
+pub(crate) mod fanout_create;
+pub(crate) mod fanout_delete;
+pub(crate) mod fanout_event;
 pub(crate) mod status;
+pub(crate) mod subscribe;
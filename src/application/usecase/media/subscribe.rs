@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use shaku::*;
+
+use crate::application::dto::request_message::Parameter;
+use crate::application::dto::response_message::{MediaResponse, ResponseResult};
+use crate::application::usecase::service::Service;
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+use crate::domain::listener::repository::ListenerRegistry;
+use crate::domain::webrtc::media::entity::{MediaConnectionIdWrapper, MediaSubscribeResult};
+use crate::domain::webrtc::media::repository::MediaRepository;
+use crate::error;
+
+// Serviceの具象Struct
+// DIコンテナからのみオブジェクトを生成できる
+// プロセスの再起動前から存在するMediaConnectionについて、statusで存在を確認し、event監視を再開する
+// 監視ループ自体は、このServiceの結果を受けてevent_factoryが起動する
+#[derive(Component)]
+#[shaku(interface = Service)]
+pub(crate) struct SubscribeService {
+    #[shaku(inject)]
+    repository: Arc<dyn MediaRepository>,
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
+}
+
+#[async_trait]
+impl Service for SubscribeService {
+    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
+        let media_connection_id = params
+            .deserialize::<MediaConnectionIdWrapper>()?
+            .media_connection_id;
+        let info = ListenerInfo::new(ListenerKind::Media, media_connection_id.as_str());
+        if self.listeners.is_active(&info) {
+            let message = format!("events of {} are already being listened", info.resource_id);
+            return Err(error::Error::create_local_error(&message));
+        }
+
+        // 存在しないMediaConnectionの場合はここでエラーになる
+        let status = self.repository.status(&media_connection_id).await?;
+        let result = MediaSubscribeResult {
+            media_connection_id,
+            status,
+        };
+        Ok(MediaResponse::Subscribe(result).create_response_message())
+    }
+}
+
+#[cfg(test)]
+mod test_subscribe_media {
+    use crate::di::MediaSubscribeServiceContainer;
+    use crate::domain::listener::repository::MockListenerRegistry;
+    use crate::domain::webrtc::media::entity::MediaConnectionStatus;
+    use crate::domain::webrtc::media::repository::MockMediaRepository;
+    use crate::domain::webrtc::media::value_object::MediaConnectionId;
+    use crate::domain::webrtc::peer::value_object::PeerId;
+
+    use super::*;
+
+    fn media_connection_id() -> MediaConnectionId {
+        MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap()
+    }
+
+    fn status() -> MediaConnectionStatus {
+        MediaConnectionStatus {
+            metadata: "metadata".to_string(),
+            open: true,
+            remote_id: PeerId::new("peer_id"),
+            ssrc: None,
+        }
+    }
+
+    fn params() -> Parameter {
+        let wrapper = MediaConnectionIdWrapper {
+            media_connection_id: media_connection_id(),
+        };
+        Parameter(serde_json::to_value(wrapper).unwrap())
+    }
+
+    #[tokio::test]
+    async fn success() {
+        let mut repository = MockMediaRepository::default();
+        repository.expect_status().returning(|_| Ok(status()));
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_is_active().returning(|_| false);
+
+        // Mockを埋め込んだServiceを生成
+        let module = MediaSubscribeServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(repository))
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners))
+            .build();
+        let service: Arc<dyn Service> = module.resolve();
+
+        let result = service.execute(params()).await.unwrap();
+
+        // evaluate
+        let expected = MediaResponse::Subscribe(MediaSubscribeResult {
+            media_connection_id: media_connection_id(),
+            status: status(),
+        })
+        .create_response_message();
+        assert_eq!(result, expected);
+    }
+
+    #[tokio::test]
+    async fn already_listened() {
+        // 監視中の場合はstatusを確認する必要がないので、呼ばれたらクラッシュするmockを作成
+        let mut repository = MockMediaRepository::default();
+        repository.expect_status().returning(|_| {
+            assert!(false);
+            unreachable!();
+        });
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_is_active().returning(|_| true);
+
+        // Mockを埋め込んだServiceを生成
+        let module = MediaSubscribeServiceContainer::builder()
+            .with_component_override::<dyn MediaRepository>(Box::new(repository))
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners))
+            .build();
+        let service: Arc<dyn Service> = module.resolve();
+
+        let result = service.execute(params()).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "events of mc-50a32bab-b3d9-4913-8e20-f79c90a6a211 are already being listened"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+}
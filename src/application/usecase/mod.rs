@@ -1,5 +1,6 @@
 pub(crate) mod data;
 pub(crate) mod factory;
+pub(crate) mod listener;
 pub(crate) mod media;
 pub(crate) mod peer;
 pub(crate) mod service;
@@ -6,6 +6,7 @@ use shaku::*;
 use crate::application::dto::request_message::Parameter;
 use crate::application::dto::response_message::{PeerResponse, ResponseResult};
 use crate::application::usecase::service::Service;
+use crate::domain::listener::repository::ListenerRegistry;
 use crate::domain::webrtc::peer::repository::PeerRepository;
 use crate::domain::webrtc::peer::value_object::PeerInfo;
 use crate::error;
@@ -17,6 +18,8 @@ use crate::error;
 pub(crate) struct DeleteService {
     #[shaku(inject)]
     repository: Arc<dyn PeerRepository>,
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
 }
 
 #[async_trait]
@@ -25,6 +28,8 @@ impl Service for DeleteService {
         // 汎用的なDTOオブジェクトであるParameterから必要な値を取り出せるかチェックするのはアプリケーション層の責務である
         let peer_info = param.deserialize::<PeerInfo>()?;
         let _ = self.repository.delete(&peer_info).await?;
+        // 削除したPeerが所有するConnectionの監視も終了する
+        let _ = self.listeners.close_owned(peer_info.peer_id().as_str());
         // APIは削除するのみでpeer_infoを返さないが、削除に成功した場合は、ユーザの不利便性のためにpeer_infoを返す
         Ok(PeerResponse::Delete(peer_info).create_response_message())
     }
@@ -34,6 +39,7 @@ impl Service for DeleteService {
 mod test_delete_peer {
     use super::*;
     use crate::di::PeerDeleteServiceContainer;
+    use crate::domain::listener::repository::MockListenerRegistry;
     use crate::domain::webrtc::peer::repository::MockPeerRepository;
     use crate::error;
 
@@ -49,10 +55,20 @@ mod test_delete_peer {
         // 削除に成功するケースのmockを作成
         let mut mock = MockPeerRepository::default();
         mock.expect_delete().returning(|_| Ok(()));
+        // 所有するConnectionの監視が終了されることを確認するmockを作成
+        let mut listeners = MockListenerRegistry::default();
+        listeners
+            .expect_close_owned()
+            .times(1)
+            .returning(|peer_id| {
+                assert_eq!(peer_id, "peer_id");
+                vec![]
+            });
 
         // mockを埋め込んだサービスを作成
         let module = PeerDeleteServiceContainer::builder()
             .with_component_override::<dyn PeerRepository>(Box::new(mock))
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners))
             .build();
         let delete_service: Arc<dyn Service> = module.resolve();
 
@@ -5,8 +5,11 @@ use shaku::*;
 use tokio::sync::mpsc;
 
 use crate::application::dto::request_message::Parameter;
-use crate::application::dto::response_message::{PeerResponse, ResponseResult};
+use crate::application::dto::response_message::{ListenerResponse, PeerResponse, ResponseResult};
 use crate::application::usecase::service::EventListener;
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind, ListenerStopped};
+use crate::domain::listener::repository::ListenerRegistry;
+use crate::domain::listener::retry::RetryPolicy;
 use crate::domain::state::ApplicationState;
 use crate::domain::webrtc::peer::entity::PeerEventEnum;
 use crate::domain::webrtc::peer::repository::PeerRepository;
@@ -21,6 +24,28 @@ pub(crate) struct EventService {
     repository: Arc<dyn PeerRepository>,
     #[shaku(inject)]
     state: Arc<dyn ApplicationState>,
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
+    #[shaku(default)]
+    retry: RetryPolicy,
+}
+
+impl EventService {
+    // CONNECTION, CALL eventで通知されたConnectionは、このPeerが所有するものとして記録する
+    fn record_owner(&self, peer_info: &PeerInfo, event: &PeerEventEnum) {
+        let info = match event {
+            PeerEventEnum::CONNECTION(event) => ListenerInfo::new(
+                ListenerKind::Data,
+                event.data_params.data_connection_id.as_str(),
+            ),
+            PeerEventEnum::CALL(event) => ListenerInfo::new(
+                ListenerKind::Media,
+                event.call_params.media_connection_id.as_str(),
+            ),
+            _ => return,
+        };
+        self.listeners.set_owner(peer_info.peer_id().as_str(), info);
+    }
 }
 
 #[async_trait]
@@ -45,9 +70,15 @@ impl EventListener for EventService {
         let peer_info = peer_info.unwrap();
 
         while self.state.is_running() {
-            let event = self.repository.event(peer_info.clone()).await;
+            let event = self
+                .retry
+                .call(|| self.repository.event(peer_info.clone()))
+                .await;
             match event {
                 Ok(PeerEventEnum::CLOSE(event)) => {
+                    // このPeerが所有するConnectionの監視も終了する
+                    // 各監視ループの終了時に、ConnectionのCLOSE eventが補完される
+                    let _ = self.listeners.close_owned(peer_info.peer_id().as_str());
                     let message = PeerResponse::Event(PeerEventEnum::CLOSE(event).clone())
                         .create_response_message();
                     let _ = event_tx.send(message.clone()).await;
@@ -57,12 +88,16 @@ impl EventListener for EventService {
                     // TIMEOUTはユーザに通知する必要がない
                 }
                 Ok(event) => {
+                    self.record_owner(&peer_info, &event);
                     let message = PeerResponse::Event(event).create_response_message();
                     let _ = event_tx.send(message.clone()).await;
                 }
-                Err(e) => {
-                    let message = format!("error in EventService for Peer {:?}", e);
-                    let message = ResponseResult::Error(message);
+                Err(reason) => {
+                    // 再試行しても回復しなかったので、監視を諦めたことを通知する
+                    let listener =
+                        ListenerInfo::new(ListenerKind::Peer, peer_info.peer_id().as_str());
+                    let message = ListenerResponse::Stopped(ListenerStopped { listener, reason })
+                        .create_response_message();
                     let _ = event_tx.send(message.clone()).await;
                     return message;
                 }
@@ -86,6 +121,77 @@ mod test_peer_event {
 
     use super::*;
 
+    // CONNECTIONで通知されたConnectionの所有者を記録し、CLOSE時に監視を終了させる
+    #[tokio::test]
+    async fn close_owned_listeners() {
+        use crate::domain::listener::repository::MockListenerRegistry;
+
+        let peer_info =
+            PeerInfo::try_create("peer_id", "pt-9749250e-d157-4f80-9ee2-359ce8524308").unwrap();
+        let connect_event = PeerEventEnum::CONNECTION(PeerConnectionEvent {
+            params: peer_info.clone(),
+            data_params: DataConnectionIdWrapper {
+                data_connection_id: DataConnectionId::try_create(
+                    "dc-102127d9-30de-413b-93f7-41a33e39d82b",
+                )
+                .unwrap(),
+            },
+        });
+        let close_event = PeerEventEnum::CLOSE(PeerCloseEvent {
+            params: peer_info.clone(),
+        });
+
+        // CONNECTION, CLOSEの順に返すmockを作成
+        let counter = Mutex::new(0u8);
+        let mut mock = MockPeerRepository::default();
+        mock.expect_event().returning(move |_| {
+            let mut counter_ref = counter.lock().unwrap();
+            *counter_ref += 1;
+            if *counter_ref == 1 {
+                Ok(connect_event.clone())
+            } else {
+                Ok(close_event.clone())
+            }
+        });
+
+        // 所有者の記録と、CLOSE時の停止を確認するmockを作成
+        let mut listeners = MockListenerRegistry::default();
+        listeners
+            .expect_set_owner()
+            .times(1)
+            .returning(|peer_id, info| {
+                assert_eq!(peer_id, "peer_id");
+                assert_eq!(
+                    info,
+                    ListenerInfo::new(
+                        ListenerKind::Data,
+                        "dc-102127d9-30de-413b-93f7-41a33e39d82b"
+                    )
+                );
+            });
+        listeners
+            .expect_close_owned()
+            .times(1)
+            .returning(|peer_id| {
+                assert_eq!(peer_id, "peer_id");
+                vec![]
+            });
+
+        let module = &PeerEventServiceContainer::builder()
+            .with_component_override::<dyn PeerRepository>(Box::new(mock))
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners))
+            .build();
+        let event_service: &dyn EventListener = module.resolve_ref();
+
+        let (event_tx, _event_rx) = mpsc::channel::<ResponseResult>(10);
+        let _ = event_service
+            .execute(
+                event_tx,
+                Parameter(serde_json::to_value(&peer_info).unwrap()),
+            )
+            .await;
+    }
+
     // 成功する場合
     #[tokio::test]
     async fn success() {
@@ -285,14 +391,12 @@ mod test_peer_event {
             )
             .await;
 
-        // errorが帰ってくる
-        if let ResponseResult::Error(e) = result {
-            assert_eq!(
-                e,
-                "error in EventService for Peer LocalError(\"event error\")"
-            );
-        } else {
-            assert!(false);
-        }
+        // 再試行しないerrorなので、LISTENER_STOPPEDが帰ってくる
+        let expected = ListenerResponse::Stopped(ListenerStopped {
+            listener: ListenerInfo::new(ListenerKind::Peer, "peer_id"),
+            reason: "event api returned an error: LocalError(\"event error\")".into(),
+        })
+        .create_response_message();
+        assert_eq!(result, expected);
     }
 }
@@ -2,3 +2,4 @@ pub(crate) mod create;
 pub(crate) mod delete;
 pub(crate) mod event;
 pub(crate) mod status;
+pub(crate) mod subscribe;
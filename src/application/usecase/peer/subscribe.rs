@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use shaku::*;
+
+use crate::application::dto::request_message::Parameter;
+use crate::application::dto::response_message::{PeerResponse, ResponseResult};
+use crate::application::usecase::service::Service;
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+use crate::domain::listener::repository::ListenerRegistry;
+use crate::domain::webrtc::peer::repository::PeerRepository;
+use crate::domain::webrtc::peer::value_object::PeerInfo;
+use crate::error;
+
+// Serviceの具象Struct
+// DIコンテナからのみオブジェクトを生成できる
+// プロセスの再起動前から存在するPeerについて、statusで存在を確認し、event監視を再開する
+// 監視ループ自体は、このServiceの結果を受けてevent_factoryが起動する
+#[derive(Component)]
+#[shaku(interface = Service)]
+pub(crate) struct SubscribeService {
+    #[shaku(inject)]
+    repository: Arc<dyn PeerRepository>,
+    #[shaku(inject)]
+    listeners: Arc<dyn ListenerRegistry>,
+}
+
+#[async_trait]
+impl Service for SubscribeService {
+    async fn execute(&self, params: Parameter) -> Result<ResponseResult, error::Error> {
+        let peer_info = params.deserialize::<PeerInfo>()?;
+        let info = ListenerInfo::new(ListenerKind::Peer, peer_info.peer_id().as_str());
+        if self.listeners.is_active(&info) {
+            let message = format!("events of {} are already being listened", info.resource_id);
+            return Err(error::Error::create_local_error(&message));
+        }
+
+        // 存在しないPeer, tokenが一致しないPeerの場合はここでエラーになる
+        let status = self.repository.status(&peer_info).await?;
+        if status.disconnected {
+            let message = format!("Peer {} is disconnected", peer_info.peer_id().as_str());
+            return Err(error::Error::create_local_error(&message));
+        }
+        Ok(PeerResponse::Subscribe(peer_info).create_response_message())
+    }
+}
+
+#[cfg(test)]
+mod test_subscribe_peer {
+    use super::*;
+    use crate::di::PeerSubscribeServiceContainer;
+    use crate::domain::listener::repository::MockListenerRegistry;
+    use crate::domain::webrtc::peer::entity::PeerStatusMessage;
+    use crate::domain::webrtc::peer::repository::MockPeerRepository;
+
+    fn peer_info() -> PeerInfo {
+        PeerInfo::try_create("peer_id", "pt-9749250e-d157-4f80-9ee2-359ce8524308").unwrap()
+    }
+
+    fn create_service(disconnected: bool, active: bool) -> Arc<dyn Service> {
+        let mut repository = MockPeerRepository::default();
+        repository.expect_status().returning(move |peer_info| {
+            Ok(PeerStatusMessage {
+                peer_id: peer_info.peer_id().clone(),
+                disconnected,
+            })
+        });
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_is_active().returning(move |_| active);
+
+        // Mockを埋め込んだServiceを生成
+        let module = PeerSubscribeServiceContainer::builder()
+            .with_component_override::<dyn PeerRepository>(Box::new(repository))
+            .with_component_override::<dyn ListenerRegistry>(Box::new(listeners))
+            .build();
+        module.resolve()
+    }
+
+    #[tokio::test]
+    async fn success() {
+        let service = create_service(false, false);
+        let param = Parameter(serde_json::to_value(peer_info()).unwrap());
+
+        let result = service.execute(param).await.unwrap();
+
+        // evaluate
+        assert_eq!(
+            result,
+            PeerResponse::Subscribe(peer_info()).create_response_message()
+        );
+    }
+
+    #[tokio::test]
+    async fn disconnected() {
+        let service = create_service(true, false);
+        let param = Parameter(serde_json::to_value(peer_info()).unwrap());
+
+        let result = service.execute(param).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(message, "Peer peer_id is disconnected");
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[tokio::test]
+    async fn already_listened() {
+        let service = create_service(false, true);
+        let param = Parameter(serde_json::to_value(peer_info()).unwrap());
+
+        let result = service.execute(param).await;
+
+        // evaluate
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(message, "events of peer_id are already being listened");
+        } else {
+            assert!(false);
+        }
+    }
+}
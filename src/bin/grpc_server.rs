@@ -0,0 +1,38 @@
+// gRPCで操作するためのserver
+//
+// proto/skyway_caller.protoで定義されたRPCを、PEER, DATA, MEDIAのcommandとして実行する。
+// Events RPCでは、eventをserver streamingで送信する。
+//
+// usage: skyway-caller-grpc [listen address]
+// WebRTC GatewayのURLは環境変数SKYWAY_GATEWAY_URLで与える
+
+use std::env;
+use std::net::SocketAddr;
+
+use tonic::transport::Server;
+
+use skyway_webrtc_gateway_caller::grpc::GrpcService;
+use skyway_webrtc_gateway_caller::prelude::Config;
+use skyway_webrtc_gateway_caller::run_with_event_hub;
+
+const DEFAULT_GATEWAY_URL: &str = "http://localhost:8000";
+const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:50051";
+
+#[tokio::main]
+async fn main() {
+    let base_url = env::var("SKYWAY_GATEWAY_URL").unwrap_or_else(|_| DEFAULT_GATEWAY_URL.into());
+    let address: SocketAddr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_LISTEN_ADDRESS.into())
+        .parse()
+        .expect("invalid listen address");
+
+    // eventはEvents RPCごとの購読で受け取るので、run()が返すReceiverは利用しない
+    let (message_tx, _, hub) = run_with_event_hub(&base_url, Config::default()).await;
+
+    println!("listening on {}", address);
+    let service = GrpcService::new(message_tx, hub).into_server();
+    if let Err(e) = Server::builder().add_service(service).serve(address).await {
+        eprintln!("server error: {:?}", e);
+    }
+}
@@ -0,0 +1,332 @@
+// REST APIとServer-Sent Eventsで操作するフロントエンド
+//
+// RESTのrouteをPEER, DATA, MEDIAのcommandに変換してcrateに与え、一次的な結果をHTTP statusと共に返す。
+// GET /eventsでは、eventをServer-Sent Eventsとして送信する。
+//
+// usage: skyway-caller-http [listen address]
+// WebRTC GatewayのURLは環境変数SKYWAY_GATEWAY_URLで与える
+
+use std::convert::Infallible;
+use std::env;
+use std::net::SocketAddr;
+
+use hyper::header::{CACHE_CONTROL, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+
+use skyway_webrtc_gateway_caller::error::ErrorKind;
+use skyway_webrtc_gateway_caller::prelude::hub::{
+    EventFilter, EventHub, LagPolicy, ReplayFrom, SubscriptionError,
+};
+use skyway_webrtc_gateway_caller::prelude::listener::ListenerKind;
+use skyway_webrtc_gateway_caller::prelude::response_parser::{EventEnvelope, ResponseResult};
+use skyway_webrtc_gateway_caller::prelude::session::{self, CommandSender};
+use skyway_webrtc_gateway_caller::prelude::Config;
+use skyway_webrtc_gateway_caller::run_with_event_hub;
+
+const DEFAULT_GATEWAY_URL: &str = "http://localhost:8000";
+const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:8080";
+
+#[tokio::main]
+async fn main() {
+    let base_url = env::var("SKYWAY_GATEWAY_URL").unwrap_or_else(|_| DEFAULT_GATEWAY_URL.into());
+    let address: SocketAddr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_LISTEN_ADDRESS.into())
+        .parse()
+        .expect("invalid listen address");
+
+    // eventはGET /eventsごとの購読で受け取るので、run()が返すReceiverは利用しない
+    let (message_tx, _, hub) = run_with_event_hub(&base_url, Config::default()).await;
+
+    let make_service = make_service_fn(move |_| {
+        let message_tx = message_tx.clone();
+        let hub = hub.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle(request, message_tx.clone(), hub.clone())
+            }))
+        }
+    });
+    println!("listening on http://{}", address);
+    if let Err(e) = Server::bind(&address).serve(make_service).await {
+        eprintln!("server error: {:?}", e);
+    }
+}
+
+async fn handle(
+    request: Request<Body>,
+    message_tx: CommandSender,
+    hub: EventHub,
+) -> Result<Response<Body>, Infallible> {
+    if request.method() == Method::GET && request.uri().path() == "/events" {
+        return Ok(events(&request, &hub));
+    }
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let query = request.uri().query().map(|query| query.to_string());
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) if body.is_empty() => json!({}),
+        Ok(body) => match serde_json::from_slice::<Value>(&body) {
+            Ok(body) => body,
+            Err(e) => {
+                let message = format!("invalid json body {:?}", e);
+                return Ok(error_response(StatusCode::BAD_REQUEST, message));
+            }
+        },
+        Err(e) => {
+            let message = format!("failed to read body {:?}", e);
+            return Ok(error_response(StatusCode::BAD_REQUEST, message));
+        }
+    };
+
+    let command = match route(&method, &path, query.as_deref(), body) {
+        Some(command) => command,
+        None => {
+            let message = format!("{} {} is not found", method, path);
+            return Ok(error_response(StatusCode::NOT_FOUND, message));
+        }
+    };
+    let created = is_create_command(&command);
+    let response = session::call(&message_tx, command.to_string()).await;
+    Ok(json_response(status_code(created, &response), response))
+}
+
+// REST APIのrouteを、ServiceParamsのJSONに変換する
+// pathに含まれるidはparamsに追加する
+fn route(method: &Method, path: &str, query: Option<&str>, body: Value) -> Option<Value> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let (r#type, command, params) = match (method, segments.as_slice()) {
+        (&Method::POST, ["peers"]) => ("PEER", "CREATE", body),
+        (&Method::GET, ["peers", peer_id]) => ("PEER", "STATUS", peer_info(peer_id, query)?),
+        (&Method::DELETE, ["peers", peer_id]) => ("PEER", "DELETE", peer_info(peer_id, query)?),
+        (&Method::POST, ["data"]) => ("DATA", "CREATE", body),
+        (&Method::DELETE, ["data", data_id]) => ("DATA", "DELETE", json!({ "data_id": data_id })),
+        (&Method::POST, ["data", "connections"]) => ("DATA", "CONNECT", body),
+        (&Method::GET, ["data", "connections", id]) => {
+            ("DATA", "STATUS", json!({ "data_connection_id": id }))
+        }
+        (&Method::PUT, ["data", "connections", id]) => {
+            ("DATA", "REDIRECT", with_id(body, "data_connection_id", id))
+        }
+        (&Method::DELETE, ["data", "connections", id]) => {
+            ("DATA", "DISCONNECT", json!({ "data_connection_id": id }))
+        }
+        (&Method::POST, ["media"]) => ("MEDIA", "CONTENT_CREATE", body),
+        (&Method::POST, ["media", "rtcp"]) => ("MEDIA", "RTCP_CREATE", body),
+        (&Method::DELETE, ["media", "rtcp", rtcp_id]) => {
+            ("MEDIA", "RTCP_DELETE", json!({ "rtcp_id": rtcp_id }))
+        }
+        (&Method::DELETE, ["media", media_id]) => {
+            ("MEDIA", "CONTENT_DELETE", json!({ "media_id": media_id }))
+        }
+        (&Method::POST, ["media", "calls"]) => ("MEDIA", "CALL", body),
+        (&Method::GET, ["media", "connections", id]) => {
+            ("MEDIA", "STATUS", json!({ "media_connection_id": id }))
+        }
+        (&Method::POST, ["media", "connections", id, "answer"]) => {
+            ("MEDIA", "ANSWER", with_id(body, "media_connection_id", id))
+        }
+        (&Method::DELETE, ["media", "connections", id]) => {
+            ("MEDIA", "DISCONNECT", json!({ "media_connection_id": id }))
+        }
+        _ => return None,
+    };
+    Some(json!({ "type": r#type, "command": command, "params": params }))
+}
+
+// Peerの操作にはtokenが必要なので、queryのtokenと合わせてPeerInfoを生成する
+fn peer_info(peer_id: &str, query: Option<&str>) -> Option<Value> {
+    let token = query_value(query, "token")?;
+    Some(json!({ "peer_id": peer_id, "token": token }))
+}
+
+fn query_value<'a>(query: Option<&'a str>, key: &str) -> Option<&'a str> {
+    query?.split('&').find_map(|pair| {
+        let mut pair = pair.splitn(2, '=');
+        match (pair.next(), pair.next()) {
+            (Some(k), Some(value)) if k == key => Some(value),
+            _ => None,
+        }
+    })
+}
+
+fn with_id(mut body: Value, key: &str, id: &str) -> Value {
+    if let Value::Object(ref mut map) = body {
+        map.insert(key.to_string(), json!(id));
+    }
+    body
+}
+
+fn is_create_command(command: &Value) -> bool {
+    matches!(
+        command["command"].as_str(),
+        Some("CREATE")
+            | Some("CONTENT_CREATE")
+            | Some("RTCP_CREATE")
+            | Some("CONNECT")
+            | Some("CALL")
+    )
+}
+
+// ResponseResult::Errorの内容から、HTTP statusを決める
+// WebRTC Gatewayが返したstatus codeと、このcrateでのパラメータの検証結果に対応させる
+fn status_code(created: bool, response: &str) -> StatusCode {
+    match ResponseResult::from_str(response) {
+        Ok(ResponseResult::Success(_)) if created => StatusCode::CREATED,
+        Ok(ResponseResult::Success(_)) => StatusCode::OK,
+        Ok(ResponseResult::Error(message)) => match ErrorKind::from_response(&message) {
+            ErrorKind::InvalidRequest => StatusCode::BAD_REQUEST,
+            ErrorKind::Forbidden => StatusCode::FORBIDDEN,
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorKind::BadGateway => StatusCode::BAD_GATEWAY,
+        },
+        Err(_) => StatusCode::BAD_GATEWAY,
+    }
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn error_response(status: StatusCode, message: String) -> Response<Body> {
+    let body = serde_json::to_string(&ResponseResult::Error(message)).unwrap();
+    json_response(status, body)
+}
+
+// GET /events
+// queryのkind, event, resource_idでeventを絞り込める
+// Last-Event-IDが与えられた場合は、履歴に残っているその次のeventから再送する
+fn events(request: &Request<Body>, hub: &EventHub) -> Response<Body> {
+    let query = request.uri().query();
+    let filter = EventFilter {
+        kind: query_value(query, "kind")
+            .and_then(|kind| serde_json::from_value::<ListenerKind>(json!(kind)).ok()),
+        event: query_value(query, "event").map(|event| event.to_string()),
+        resource_id: query_value(query, "resource_id").map(|id| id.to_string()),
+    };
+    let last_event_id = request
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    let subscription = match last_event_id {
+        Some(seq) => hub.subscribe_from(filter, LagPolicy::Report, ReplayFrom::Seq(seq + 1)),
+        None => hub.subscribe(filter, LagPolicy::Report),
+    };
+
+    let stream = futures::stream::unfold(subscription, |mut subscription| async move {
+        let frame = match subscription.recv().await {
+            Ok(event) => {
+                let seq = event
+                    .parse::<EventEnvelope>()
+                    .map(|envelope| envelope.seq)
+                    .unwrap_or_default();
+                format!("id: {}\ndata: {}\n\n", seq, event)
+            }
+            // 取りこぼしたeventの数を通知する
+            Err(SubscriptionError::Lagged(count)) => format!("event: lagged\ndata: {}\n\n", count),
+            Err(SubscriptionError::Closed) => return None,
+        };
+        Some((Ok::<_, Infallible>(frame), subscription))
+    });
+    Response::builder()
+        .header(CONTENT_TYPE, "text/event-stream")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(Body::wrap_stream(stream))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test_route {
+    use skyway_webrtc_gateway_caller::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn peer() {
+        let command = route(
+            &Method::DELETE,
+            "/peers/peer_id",
+            Some("token=pt-9749250e-d157-4f80-9ee2-359ce8524308"),
+            json!({}),
+        );
+        assert_eq!(
+            command,
+            Some(json!({
+                "type": "PEER",
+                "command": "DELETE",
+                "params": {
+                    "peer_id": "peer_id",
+                    "token": "pt-9749250e-d157-4f80-9ee2-359ce8524308"
+                }
+            }))
+        );
+
+        // tokenがない場合は操作できない
+        assert_eq!(route(&Method::GET, "/peers/peer_id", None, json!({})), None);
+    }
+
+    #[test]
+    fn path_id() {
+        let command = route(
+            &Method::POST,
+            "/media/connections/mc-50a32bab-b3d9-4913-8e20-f79c90a6a211/answer",
+            None,
+            json!({ "answer_query": {} }),
+        )
+        .unwrap();
+        assert_eq!(command["command"], "ANSWER");
+        assert_eq!(
+            command["params"],
+            json!({
+                "media_connection_id": "mc-50a32bab-b3d9-4913-8e20-f79c90a6a211",
+                "answer_query": {}
+            })
+        );
+        assert!(!is_create_command(&command));
+    }
+
+    #[test]
+    fn status() {
+        // skyway-webrtc-gateway-apiが生成するエラーと、対応するHTTP status
+        let table = [
+            ("recv message\ninvalid peer_id", StatusCode::BAD_REQUEST),
+            ("recv Forbidden", StatusCode::FORBIDDEN),
+            ("recv Not Found", StatusCode::NOT_FOUND),
+            (
+                "recv invalid response: url: http://localhost:8000/media/connections/mc-50a32bab-b3d9-4913-8e20-f79c90a6a211 code: 404 Not Found",
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                "recv invalid response: url: http://localhost:8000/media/connections code: 503 Service Unavailable",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                "recv invalid response: url: http://localhost:8000/media code: 500 Internal Server Error",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            ("recv Method Not Allowed", StatusCode::BAD_GATEWAY),
+            // このcrateでのパラメータの検証に失敗した場合
+            ("media socket has no media_id", StatusCode::BAD_REQUEST),
+        ];
+        for (message, status) in table.iter() {
+            let error = serde_json::to_string(&Error::create_local_error(message)).unwrap();
+            let response = serde_json::to_string(&ResponseResult::Error(error)).unwrap();
+            assert_eq!(status_code(true, &response), *status, "{}", message);
+        }
+
+        // JSONとして解釈できないcommand
+        let error = serde_json::from_str::<Value>("invalid json").unwrap_err();
+        let error = serde_json::to_string(&Error::SerdeError { error }).unwrap();
+        let response = serde_json::to_string(&ResponseResult::Error(error)).unwrap();
+        assert_eq!(status_code(false, &response), StatusCode::BAD_REQUEST);
+    }
+}
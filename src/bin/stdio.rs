@@ -0,0 +1,118 @@
+// 標準入出力でJSON linesを中継するフロントエンド
+//
+// 標準入力の1行を1つのServiceParamsとしてcrateに与え、結果を1行ずつ標準出力に書き込む。
+// 出力は以下のいずれかの形式となる。
+// {"type": "response", "id": <入力のid>, "body": <ResponseResult>}
+// {"type": "event", "body": <EventEnvelope>}
+// 入力のidは応答との対応付けのためだけに利用し、crateには与えない。
+// 標準入力がEOFに達した場合は、処理中のcommandの終了を待ち、生成したリソースを削除してから終了する。
+// 削除によって発生したCLOSE eventも、eventが途切れるまで出力してから終了する。
+//
+// usage: skyway-caller [WebRTC GatewayのURL]
+// URLを省略した場合は、環境変数SKYWAY_GATEWAY_URLを利用する
+
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+
+use skyway_webrtc_gateway_caller::prelude::session::{self, ClientResources};
+use skyway_webrtc_gateway_caller::run;
+
+const DEFAULT_GATEWAY_URL: &str = "http://localhost:8000";
+// リソースの削除後、この時間eventが届かなければ、CLOSE eventを全て出力したとみなす
+const DRAIN_IDLE_TIMEOUT: Duration = Duration::from_millis(500);
+// eventが途切れない場合も、この時間で出力を打ち切る
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[tokio::main]
+async fn main() {
+    let base_url = env::args()
+        .nth(1)
+        .or_else(|| env::var("SKYWAY_GATEWAY_URL").ok())
+        .unwrap_or_else(|| DEFAULT_GATEWAY_URL.into());
+    let (message_tx, mut event_rx) = run(&base_url).await;
+
+    // 応答とeventの両方を標準出力に書き込むので、行が混ざらないようchannelで集約する
+    let (line_tx, mut line_rx) = mpsc::channel::<Value>(100);
+    let writer = tokio::spawn(async move {
+        let mut stdout = io::stdout();
+        while let Some(line) = line_rx.recv().await {
+            let line = format!("{}\n", line);
+            if stdout.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+            let _ = stdout.flush().await;
+        }
+    });
+
+    // リソースの削除が終わったら、drain_txで通知する
+    let (drain_tx, mut drain_rx) = oneshot::channel::<()>();
+    let events = {
+        let line_tx = line_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    event = event_rx.recv() => event,
+                    _ = &mut drain_rx => break,
+                };
+                match event {
+                    Some(event) => {
+                        if line_tx.send(session::event_frame(&event)).await.is_err() {
+                            return;
+                        }
+                    }
+                    None => return,
+                }
+            }
+
+            // 削除によって発生したCLOSE eventを、eventが途切れるまで出力する
+            let drain = async {
+                while let Ok(Some(event)) =
+                    tokio::time::timeout(DRAIN_IDLE_TIMEOUT, event_rx.recv()).await
+                {
+                    if line_tx.send(session::event_frame(&event)).await.is_err() {
+                        return;
+                    }
+                }
+            };
+            let _ = tokio::time::timeout(DRAIN_TIMEOUT, drain).await;
+        })
+    };
+
+    // commandは入力の順にcrateに与え、AWAITのように時間のかかるcommandもあるので結果のみ並行して待つ
+    // 全てのcommandの終了を待てるよう、各taskにin_flight_txを持たせる
+    let resources = Arc::new(Mutex::new(ClientResources::default()));
+    let (in_flight_tx, mut in_flight_rx) = mpsc::channel::<()>(1);
+    let mut lines = BufReader::new(io::stdin()).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (id, command) = session::split_id(&line);
+        let response = session::send(&message_tx, command).await;
+        let line_tx = line_tx.clone();
+        let resources = resources.clone();
+        let in_flight_tx = in_flight_tx.clone();
+        tokio::spawn(async move {
+            let response = response.await;
+            resources.lock().unwrap().record(&response);
+            let _ = line_tx.send(session::response_frame(id, &response)).await;
+            drop(in_flight_tx);
+        });
+    }
+
+    // EOFに達したので、処理中のcommandを待ってからリソースを削除する
+    drop(in_flight_tx);
+    let _ = in_flight_rx.recv().await;
+    let resources = resources.lock().unwrap().clone();
+    session::cleanup(&message_tx, &resources).await;
+
+    let _ = drain_tx.send(());
+    let _ = events.await;
+    drop(line_tx);
+    let _ = writer.await;
+}
@@ -0,0 +1,250 @@
+// Unix domain socketで改行区切りのJSONメッセージを中継するフロントエンド
+//
+// 各クライアントが送信した1行を1つのServiceParamsとしてcrateに与え、一次的な結果を同じクライアントに1行で返す。
+// 出力は標準入出力のフロントエンドと同じく、以下のいずれかの形式となる。
+// {"type": "response", "id": <入力のid>, "body": <ResponseResult>}
+// {"type": "event", "body": <EventEnvelope>}
+// eventは全てのクライアントに送信するか、そのeventに関するPeerを生成したクライアントのみに送信するかを選択できる。
+// クライアントが切断した場合は、処理中のcommandの終了を待ち、そのクライアントが生成したリソースを削除する。
+//
+// usage: skyway-caller-uds [socket path] [all|owner]
+// WebRTC GatewayのURLは環境変数SKYWAY_GATEWAY_URLで与える
+// socket pathで他のサーバが待ち受けている場合は、起動せずに終了する
+
+// tokio::net::UnixListenerはunixでのみ利用できる
+#[cfg(unix)]
+mod server {
+    use std::io::ErrorKind;
+    use std::os::unix::fs::FileTypeExt;
+    use std::sync::{Arc, Mutex};
+    use std::{env, fs};
+
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::sync::mpsc;
+
+    use skyway_webrtc_gateway_caller::prelude::hub::{EventFilter, EventHub, LagPolicy};
+    use skyway_webrtc_gateway_caller::prelude::session::{self, ClientResources, CommandSender};
+    use skyway_webrtc_gateway_caller::prelude::Config;
+    use skyway_webrtc_gateway_caller::run_with_event_hub;
+
+    const DEFAULT_GATEWAY_URL: &str = "http://localhost:8000";
+    const DEFAULT_SOCKET_PATH: &str = "/tmp/skyway-caller.sock";
+
+    // eventを送信するクライアントの範囲
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Delivery {
+        // 全てのクライアントに送信する
+        All,
+        // eventに関するPeerを生成したクライアントのみに送信する
+        Owner,
+    }
+
+    impl Delivery {
+        fn parse(value: Option<&str>) -> Option<Self> {
+            match value {
+                None | Some("owner") => Some(Delivery::Owner),
+                Some("all") => Some(Delivery::All),
+                _ => None,
+            }
+        }
+    }
+
+    // 前回の起動時に残ったsocket fileがあるとbindできないので削除する
+    // 他のサーバが利用中のsocketや、socket以外のfileは削除せずにエラーを返す
+    fn remove_stale_socket(path: &str) -> Result<(), String> {
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(format!("failed to inspect {}: {}", path, e)),
+        };
+        if !metadata.file_type().is_socket() {
+            return Err(format!("{} exists and is not a socket", path));
+        }
+        // 接続できた場合は他のサーバが待ち受けている
+        match std::os::unix::net::UnixStream::connect(path) {
+            Ok(_) => Err(format!("another server is listening on {}", path)),
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+                fs::remove_file(path).map_err(|e| format!("failed to remove {}: {}", path, e))
+            }
+            Err(e) => Err(format!("failed to check {}: {}", path, e)),
+        }
+    }
+
+    #[tokio::main]
+    pub(crate) async fn serve() {
+        let base_url =
+            env::var("SKYWAY_GATEWAY_URL").unwrap_or_else(|_| DEFAULT_GATEWAY_URL.into());
+        let path = env::args()
+            .nth(1)
+            .unwrap_or_else(|| DEFAULT_SOCKET_PATH.into());
+        let delivery = Delivery::parse(env::args().nth(2).as_deref())
+            .expect("event delivery must be either \"all\" or \"owner\"");
+        if let Err(message) = remove_stale_socket(&path) {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+
+        // eventはクライアントごとの購読で受け取るので、run()が返すReceiverは利用しない
+        let (message_tx, _, hub) = run_with_event_hub(&base_url, Config::default()).await;
+
+        let listener = UnixListener::bind(&path).expect("failed to bind the socket path");
+        println!("listening on {}", path);
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle_client(
+                stream,
+                message_tx.clone(),
+                hub.clone(),
+                delivery,
+            ));
+        }
+    }
+
+    async fn handle_client(
+        stream: UnixStream,
+        message_tx: CommandSender,
+        hub: EventHub,
+        delivery: Delivery,
+    ) {
+        let (reader, mut writer) = stream.into_split();
+        let resources = Arc::new(Mutex::new(ClientResources::default()));
+
+        // 応答とeventの両方を同じsocketに書き込むので、channelで集約する
+        let (line_tx, mut line_rx) = mpsc::channel::<String>(100);
+        let writer = tokio::spawn(async move {
+            while let Some(line) = line_rx.recv().await {
+                let line = format!("{}\n", line);
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // 他のクライアントの処理の遅れに影響されないよう、クライアントごとに購読する
+        let mut subscription = hub.subscribe(EventFilter::default(), LagPolicy::Skip);
+        let events = {
+            let line_tx = line_tx.clone();
+            let resources = resources.clone();
+            tokio::spawn(async move {
+                while let Ok(event) = subscription.recv().await {
+                    let deliver =
+                        delivery == Delivery::All || resources.lock().unwrap().owns_event(&event);
+                    if !deliver {
+                        continue;
+                    }
+                    let line = session::event_frame(&event).to_string();
+                    if line_tx.send(line).await.is_err() {
+                        break;
+                    }
+                }
+            })
+        };
+
+        // commandは受信した順にcrateに与え、AWAITのように時間のかかるcommandもあるので結果のみ並行して待つ
+        // 全てのcommandの終了を待てるよう、各taskにin_flight_txを持たせる
+        let (in_flight_tx, mut in_flight_rx) = mpsc::channel::<()>(1);
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (id, command) = session::split_id(&line);
+            let response = session::send(&message_tx, command).await;
+            let line_tx = line_tx.clone();
+            let resources = resources.clone();
+            let in_flight_tx = in_flight_tx.clone();
+            tokio::spawn(async move {
+                let response = response.await;
+                resources.lock().unwrap().record(&response);
+                let line = session::response_frame(id, &response).to_string();
+                let _ = line_tx.send(line).await;
+                drop(in_flight_tx);
+            });
+        }
+
+        // 切断されたので、処理中のcommandを待ってから、このクライアントが生成したリソースを削除する
+        events.abort();
+        writer.abort();
+        drop(in_flight_tx);
+        let _ = in_flight_rx.recv().await;
+        let resources = resources.lock().unwrap().clone();
+        session::cleanup(&message_tx, &resources).await;
+    }
+
+    #[cfg(test)]
+    mod test_delivery {
+        use super::*;
+
+        #[test]
+        fn parse() {
+            assert_eq!(Delivery::parse(None), Some(Delivery::Owner));
+            assert_eq!(Delivery::parse(Some("all")), Some(Delivery::All));
+            assert_eq!(Delivery::parse(Some("hoge")), None);
+        }
+    }
+
+    #[cfg(test)]
+    mod test_remove_stale_socket {
+        use std::os::unix::net::UnixListener;
+
+        use super::*;
+
+        fn socket_path(name: &str) -> String {
+            let path =
+                env::temp_dir().join(format!("skyway-caller-{}-{}", std::process::id(), name));
+            let _ = fs::remove_file(&path);
+            path.to_str().unwrap().to_string()
+        }
+
+        #[test]
+        fn not_exist() {
+            let path = socket_path("not_exist");
+            assert!(remove_stale_socket(&path).is_ok());
+        }
+
+        #[test]
+        fn stale() {
+            // 待ち受けを終えたsocket fileのみが残っている場合は削除する
+            let path = socket_path("stale");
+            drop(UnixListener::bind(&path).unwrap());
+            assert!(remove_stale_socket(&path).is_ok());
+            assert!(fs::metadata(&path).is_err());
+        }
+
+        #[test]
+        fn listening() {
+            // 他のサーバが待ち受けている場合は削除しない
+            let path = socket_path("listening");
+            let listener = UnixListener::bind(&path).unwrap();
+            assert_eq!(
+                remove_stale_socket(&path),
+                Err(format!("another server is listening on {}", path))
+            );
+            assert!(fs::metadata(&path).is_ok());
+            drop(listener);
+            let _ = fs::remove_file(&path);
+        }
+
+        #[test]
+        fn not_socket() {
+            // socket以外のfileは削除しない
+            let path = socket_path("not_socket");
+            fs::write(&path, "data").unwrap();
+            assert_eq!(
+                remove_stale_socket(&path),
+                Err(format!("{} exists and is not a socket", path))
+            );
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn main() {
+    server::serve();
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("unix domain sockets are not supported on this platform");
+}
@@ -0,0 +1,118 @@
+// WebSocketでJSONメッセージを中継するフロントエンド
+//
+// 各クライアントが送信したtext frameをServiceParamsとしてcrateに与え、一次的な結果を同じsocketに返す。
+// 送信するframeは標準入出力のフロントエンドと同じく、以下のいずれかの形式となる。
+// {"type": "response", "id": <受信したframeのid>, "body": <ResponseResult>}
+// {"type": "event", "body": <EventEnvelope>}
+// eventはクライアントごとに購読し、そのクライアントが生成したPeerに関するもののみをframeとして送信する。
+// クライアントが切断した場合は、処理中のcommandの終了を待ち、そのクライアントが生成したリソースを削除する。
+//
+// usage: skyway-caller-ws [listen address]
+// WebRTC GatewayのURLは環境変数SKYWAY_GATEWAY_URLで与える
+
+use std::env;
+use std::sync::{Arc, Mutex};
+
+use futures::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+use skyway_webrtc_gateway_caller::prelude::hub::{EventFilter, EventHub, LagPolicy};
+use skyway_webrtc_gateway_caller::prelude::session::{self, ClientResources, CommandSender};
+use skyway_webrtc_gateway_caller::prelude::Config;
+use skyway_webrtc_gateway_caller::run_with_event_hub;
+
+const DEFAULT_GATEWAY_URL: &str = "http://localhost:8000";
+const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:9001";
+
+#[tokio::main]
+async fn main() {
+    let base_url = env::var("SKYWAY_GATEWAY_URL").unwrap_or_else(|_| DEFAULT_GATEWAY_URL.into());
+    let address = env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_LISTEN_ADDRESS.into());
+
+    // eventはクライアントごとの購読で受け取るので、run()が返すReceiverは利用しない
+    let (message_tx, _, hub) = run_with_event_hub(&base_url, Config::default()).await;
+
+    let listener = TcpListener::bind(&address)
+        .await
+        .expect("failed to bind the listen address");
+    println!("listening on ws://{}", address);
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(handle_client(stream, message_tx.clone(), hub.clone()));
+    }
+}
+
+async fn handle_client(stream: TcpStream, message_tx: CommandSender, hub: EventHub) {
+    let socket = match tokio_tungstenite::accept_async(stream).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("websocket handshake failed: {:?}", e);
+            return;
+        }
+    };
+    let (mut sink, mut source) = socket.split();
+    let resources = Arc::new(Mutex::new(ClientResources::default()));
+
+    // 応答とeventの両方を同じsocketに書き込むので、channelで集約する
+    let (frame_tx, mut frame_rx) = mpsc::channel::<String>(100);
+    let writer = tokio::spawn(async move {
+        while let Some(text) = frame_rx.recv().await {
+            if sink.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // 他のクライアントの処理の遅れに影響されないよう、クライアントごとに購読する
+    let mut subscription = hub.subscribe(EventFilter::default(), LagPolicy::Skip);
+    let events = {
+        let frame_tx = frame_tx.clone();
+        let resources = resources.clone();
+        tokio::spawn(async move {
+            while let Ok(event) = subscription.recv().await {
+                if !resources.lock().unwrap().owns_event(&event) {
+                    continue;
+                }
+                let frame = session::event_frame(&event).to_string();
+                if frame_tx.send(frame).await.is_err() {
+                    break;
+                }
+            }
+        })
+    };
+
+    // commandは受信した順にcrateに与え、AWAITのように時間のかかるcommandもあるので結果のみ並行して待つ
+    // 全てのcommandの終了を待てるよう、各taskにin_flight_txを持たせる
+    let (in_flight_tx, mut in_flight_rx) = mpsc::channel::<()>(1);
+    while let Some(Ok(frame)) = source.next().await {
+        match frame {
+            Message::Text(text) => {
+                let (id, command) = session::split_id(&text);
+                let response = session::send(&message_tx, command).await;
+                let frame_tx = frame_tx.clone();
+                let resources = resources.clone();
+                let in_flight_tx = in_flight_tx.clone();
+                tokio::spawn(async move {
+                    let response = response.await;
+                    resources.lock().unwrap().record(&response);
+                    let frame = session::response_frame(id, &response).to_string();
+                    let _ = frame_tx.send(frame).await;
+                    drop(in_flight_tx);
+                });
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+
+    // 切断されたので、処理中のcommandを待ってから、このクライアントが生成したリソースを削除する
+    events.abort();
+    writer.abort();
+    drop(in_flight_tx);
+    let _ = in_flight_rx.recv().await;
+    let resources = resources.lock().unwrap().clone();
+    session::cleanup(&message_tx, &resources).await;
+}
@@ -0,0 +1,150 @@
+// tokioを利用していない同期的なプログラムから、このcrateを利用するためのwrapper
+//
+// BlockingCallerは自身のtokio runtimeを保持し、run()と同じSender, Receiverをその上で動かす。
+// 各メソッドは結果が得られるまで呼び出したthreadをblockする。
+// runtimeの内部(async関数の中)から呼び出すとpanicするので、通常のthreadから呼び出すこと。
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+
+use crate::application::dto::request_message::ServiceParams;
+use crate::application::dto::response_message::{EventEnvelope, GatewayEvent, ResponseResult};
+use crate::config::Config;
+use crate::error;
+use crate::session::{self, CommandSender};
+
+/// Error returned from `BlockingCaller::recv_event`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecvTimeoutError {
+    /// No event arrived before the timeout
+    Timeout,
+    /// No more events will be delivered
+    Closed,
+}
+
+/// Synchronous counterpart of `run`, which owns its own tokio runtime
+pub struct BlockingCaller {
+    runtime: Runtime,
+    message_tx: CommandSender,
+    event_rx: Mutex<mpsc::Receiver<String>>,
+}
+
+impl BlockingCaller {
+    /// Start WebRTC Gateway operation.
+    pub fn new(base_url: &str) -> Result<Self, error::Error> {
+        Self::with_config(base_url, Config::default())
+    }
+
+    /// Start WebRTC Gateway operation with the given configuration.
+    pub fn with_config(base_url: &str, config: Config) -> Result<Self, error::Error> {
+        let runtime = Runtime::new().map_err(|e| {
+            error::Error::create_local_error(&format!("failed to start runtime {:?}", e))
+        })?;
+        let (message_tx, event_rx) = runtime.block_on(crate::run_with_config(base_url, config));
+        Ok(BlockingCaller {
+            runtime,
+            message_tx,
+            event_rx: Mutex::new(event_rx),
+        })
+    }
+
+    /// Give a JSON message for operation and block until the result JSON is returned.
+    pub fn send(&self, message: &str) -> String {
+        self.runtime
+            .block_on(session::call(&self.message_tx, message.to_string()))
+    }
+
+    /// Typed version of `send`.
+    pub fn send_params(&self, params: &ServiceParams) -> Result<ResponseResult, error::Error> {
+        self.send_serializable(params)
+    }
+
+    /// Give any object serialized into a JSON message for operation, and parse the result.
+    pub fn send_serializable<T: Serialize>(
+        &self,
+        params: &T,
+    ) -> Result<ResponseResult, error::Error> {
+        let message = serde_json::to_string(params)
+            .map_err(|e| error::Error::create_local_error(&format!("{:?}", e)))?;
+        ResponseResult::from_str(&self.send(&message))
+    }
+
+    /// Block until an event arrives, for at most `timeout`.
+    pub fn recv_event(&self, timeout: Duration) -> Result<String, RecvTimeoutError> {
+        let mut event_rx = self.event_rx.lock().unwrap();
+        // timerはruntimeの内部で生成する必要があるので、async blockの中でtimeoutを設定する
+        let result = self
+            .runtime
+            .block_on(async { tokio::time::timeout(timeout, event_rx.recv()).await });
+        match result {
+            Ok(Some(event)) => Ok(event),
+            Ok(None) => Err(RecvTimeoutError::Closed),
+            Err(_) => Err(RecvTimeoutError::Timeout),
+        }
+    }
+
+    /// Typed version of `recv_event`. Events which are not GatewayEvent such as TIMEOUT are skipped.
+    /// `timeout` is applied to the whole call, not to each skipped event.
+    pub fn recv_gateway_event(&self, timeout: Duration) -> Result<GatewayEvent, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let event = self.recv_event(remaining)?;
+            let event = event
+                .parse::<EventEnvelope>()
+                .ok()
+                .and_then(|envelope| GatewayEvent::from_response(envelope.event));
+            if let Some(event) = event {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Iterator of JSON events. It finishes when no event arrives within `timeout` or the event stream is closed.
+    pub fn events(&self, timeout: Duration) -> Events<'_> {
+        Events {
+            caller: self,
+            timeout,
+        }
+    }
+}
+
+/// Iterator returned from `BlockingCaller::events`
+pub struct Events<'a> {
+    caller: &'a BlockingCaller,
+    timeout: Duration,
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.caller.recv_event(self.timeout).ok()
+    }
+}
+
+#[cfg(test)]
+mod test_blocking_caller {
+    use super::*;
+
+    #[test]
+    fn send_and_recv() {
+        let caller = BlockingCaller::new("http://localhost:8000").unwrap();
+
+        // 不正なJSONに対しては、エラーが返される
+        let response = caller.send("invalid");
+        assert!(matches!(
+            ResponseResult::from_str(&response),
+            Ok(ResponseResult::Error(_))
+        ));
+
+        // eventはまだ発生していない
+        let timeout = Duration::from_millis(10);
+        assert_eq!(caller.recv_event(timeout), Err(RecvTimeoutError::Timeout));
+        assert_eq!(caller.events(timeout).next(), None);
+    }
+}
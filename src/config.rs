@@ -0,0 +1,41 @@
+// crate利用者が起動時に与える設定
+// lib.rs内のfoldが保持するSharedStateの初期化にのみ利用される
+
+use std::net::{IpAddr, Ipv4Addr};
+use std::ops::RangeInclusive;
+
+use crate::domain::listener::retry::RetryPolicy;
+use crate::hub::{EventChannelConfig, HistoryLimit};
+
+/// Configuration to start WebRTC Gateway operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// IP address to which the gateway redirects received media and data when ports are allocated automatically
+    pub redirect_ip: IpAddr,
+    /// Range of local UDP ports used for automatic allocation
+    pub redirect_ports: RangeInclusive<u16>,
+    /// Allocate ports also for omitted redirect_params entries, not only for entries marked "auto"
+    pub allocate_omitted_redirects: bool,
+    /// Retry policy applied when long polling of events fails
+    pub event_retry: RetryPolicy,
+    /// Start listening to data and media connections as soon as peer CONNECTION and CALL events announce them
+    pub listen_announced_connections: bool,
+    /// Bounds of the history of recent events replayed to late subscribers
+    pub event_history: HistoryLimit,
+    /// Capacities and overflow policies of the event pipeline
+    pub event_channel: EventChannelConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            redirect_ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            redirect_ports: 50000..=50999,
+            allocate_omitted_redirects: false,
+            event_retry: RetryPolicy::default(),
+            listen_announced_connections: false,
+            event_history: HistoryLimit::default(),
+            event_channel: EventChannelConfig::default(),
+        }
+    }
+}
@@ -1,13 +1,59 @@
 use shaku::*;
+use tokio::sync::mpsc;
+
+use crate::application::dto::response_message::SourcedEvent;
 
 use crate::application::usecase::data;
+use crate::application::usecase::listener;
 use crate::application::usecase::media;
 use crate::application::usecase::peer;
+use crate::config::Config;
+use crate::domain::listener::retry::RetryPolicy;
+use crate::hub::EventHub;
+use crate::infra::fanout::FanoutRepositoryImpl;
+use crate::infra::listener::publisher::EventPublisherImpl;
+use crate::infra::listener::ListenerRegistryImpl;
+use crate::infra::registry::ResourceRegistryImpl;
 use crate::infra::state::ApplicationStateAlwaysTrueImpl;
 use crate::infra::webrtc::data::DataRepositoryImpl;
 use crate::infra::webrtc::media::MediaRepositoryImpl;
 use crate::infra::webrtc::peer::PeerRepositoryImpl;
 
+//========== Shared State ==========
+// UseCase間で共有されなければならない状態
+// lib.rs内のfoldのみが保持し、UseCaseの生成時に各DIコンテナへcloneして注入する
+// 各Structは内部の状態をArcで保持するので、cloneしたインスタンスも同じ状態を参照する
+// 注入せずにDIコンテナを生成した場合(テストなど)は、各Structのdefaultの状態から始まる
+#[derive(Clone, Default)]
+pub(crate) struct SharedState {
+    pub(crate) fanout: FanoutRepositoryImpl,
+    pub(crate) registry: ResourceRegistryImpl,
+    pub(crate) listeners: ListenerRegistryImpl,
+    // 監視ループを介さずに取得したeventを、監視ループのeventと同じ経路で配信する
+    pub(crate) events: EventPublisherImpl,
+    pub(crate) retry: RetryPolicy,
+    // CONNECTION, CALL eventで通知されたConnectionの監視を、REDIRECT, ANSWERを待たずに開始する
+    pub(crate) listen_announced_connections: bool,
+}
+
+impl SharedState {
+    pub(crate) fn new(
+        config: &Config,
+        event_tx: mpsc::Sender<SourcedEvent>,
+        hub: EventHub,
+    ) -> Self {
+        let listeners = ListenerRegistryImpl::default();
+        SharedState {
+            fanout: FanoutRepositoryImpl::default(),
+            registry: ResourceRegistryImpl::new(config),
+            events: EventPublisherImpl::new(event_tx, hub, listeners.clone()),
+            listeners,
+            retry: config.event_retry.clone(),
+            listen_announced_connections: config.listen_announced_connections,
+        }
+    }
+}
+
 //========== Peer Refactor Service ==========
 
 module! {
@@ -24,16 +70,23 @@ module! {
     }
 }
 
+module! {
+    pub(crate) PeerSubscribeServiceContainer {
+        components = [peer::subscribe::SubscribeService, PeerRepositoryImpl, ListenerRegistryImpl],
+        providers = []
+    }
+}
+
 module! {
     pub(crate) PeerDeleteServiceContainer {
-        components = [peer::delete::DeleteService, PeerRepositoryImpl],
+        components = [peer::delete::DeleteService, PeerRepositoryImpl, ListenerRegistryImpl],
         providers = []
     }
 }
 
 module! {
     pub(crate) PeerEventServiceContainer {
-        components = [peer::event::EventService, PeerRepositoryImpl, ApplicationStateAlwaysTrueImpl],
+        components = [peer::event::EventService, PeerRepositoryImpl, ApplicationStateAlwaysTrueImpl, ListenerRegistryImpl],
         providers = []
     }
 }
@@ -55,28 +108,28 @@ module! {
 
 module! {
     pub(crate) DataConnectServiceContainer {
-        components = [data::connect::ConnectService, DataRepositoryImpl],
+        components = [data::connect::ConnectService, DataRepositoryImpl, ResourceRegistryImpl, ListenerRegistryImpl, EventPublisherImpl],
         providers = []
     }
 }
 
 module! {
     pub(crate) DataDisconnectServiceContainer {
-        components = [data::disconnect::DisconnectService, DataRepositoryImpl],
+        components = [data::disconnect::DisconnectService, DataRepositoryImpl, ResourceRegistryImpl],
         providers = []
     }
 }
 
 module! {
     pub(crate) DataRedirectServiceContainer {
-        components = [data::redirect::RedirectService, DataRepositoryImpl],
+        components = [data::redirect::RedirectService, DataRepositoryImpl, ResourceRegistryImpl],
         providers = []
     }
 }
 
 module! {
     pub(crate) DataEventServiceContainer {
-        components = [data::event::EventService, DataRepositoryImpl, ApplicationStateAlwaysTrueImpl],
+        components = [data::event::EventService, DataRepositoryImpl, ApplicationStateAlwaysTrueImpl, ResourceRegistryImpl],
         providers = []
     }
 }
@@ -88,59 +141,66 @@ module! {
     }
 }
 
+module! {
+    pub(crate) DataSubscribeServiceContainer {
+        components = [data::subscribe::SubscribeService, DataRepositoryImpl, ListenerRegistryImpl],
+        providers = []
+    }
+}
+
 //========== Media Service ==========
 module! {
     pub(crate) MediaContentCreateServiceContainer {
-        components = [media::create_media::CreateMediaService, MediaRepositoryImpl],
+        components = [media::create_media::CreateMediaService, MediaRepositoryImpl, ResourceRegistryImpl],
         providers = []
     }
 }
 
 module! {
     pub(crate) MediaContentDeleteServiceContainer {
-        components = [media::delete_media::DeleteMediaService, MediaRepositoryImpl],
+        components = [media::delete_media::DeleteMediaService, MediaRepositoryImpl, ResourceRegistryImpl],
         providers = []
     }
 }
 
 module! {
     pub(crate) MediaRtcpCreateServiceContainer {
-        components = [media::create_rtcp::CreateRtcpService, MediaRepositoryImpl],
+        components = [media::create_rtcp::CreateRtcpService, MediaRepositoryImpl, ResourceRegistryImpl],
         providers = []
     }
 }
 
 module! {
     pub(crate) MediaRtcpDeleteServiceContainer {
-        components = [media::delete_rtcp::DeleteRtcpService, MediaRepositoryImpl],
+        components = [media::delete_rtcp::DeleteRtcpService, MediaRepositoryImpl, ResourceRegistryImpl],
         providers = []
     }
 }
 
 module! {
     pub(crate) MediaCallServiceContainer {
-        components = [media::call::CallService, MediaRepositoryImpl],
+        components = [media::call::CallService, MediaRepositoryImpl, ResourceRegistryImpl, ListenerRegistryImpl, EventPublisherImpl],
         providers = []
     }
 }
 
 module! {
     pub(crate) MediaAnswerServiceContainer {
-        components = [media::answer::AnswerService, MediaRepositoryImpl],
+        components = [media::answer::AnswerService, MediaRepositoryImpl, ResourceRegistryImpl, ListenerRegistryImpl, EventPublisherImpl],
         providers = []
     }
 }
 
 module! {
     pub(crate) MediaDisconnectServiceContainer {
-        components = [media::disconnect::DisconnectService, MediaRepositoryImpl],
+        components = [media::disconnect::DisconnectService, MediaRepositoryImpl, ResourceRegistryImpl],
         providers = []
     }
 }
 
 module! {
     pub(crate) MediaEventServiceContainer {
-        components = [media::event::EventService, MediaRepositoryImpl, ApplicationStateAlwaysTrueImpl],
+        components = [media::event::EventService, MediaRepositoryImpl, ApplicationStateAlwaysTrueImpl, ResourceRegistryImpl],
         providers = []
     }
 }
@@ -151,3 +211,61 @@ module! {
         providers = []
     }
 }
+
+module! {
+    pub(crate) MediaSubscribeServiceContainer {
+        components = [media::subscribe::SubscribeService, MediaRepositoryImpl, ListenerRegistryImpl],
+        providers = []
+    }
+}
+
+module! {
+    pub(crate) MediaFanoutCreateServiceContainer {
+        components = [media::fanout_create::FanoutCreateService, FanoutRepositoryImpl],
+        providers = []
+    }
+}
+
+module! {
+    pub(crate) MediaFanoutCallServiceContainer {
+        components = [media::fanout_call::FanoutCallService, MediaRepositoryImpl, FanoutRepositoryImpl, ResourceRegistryImpl, ListenerRegistryImpl],
+        providers = []
+    }
+}
+
+module! {
+    pub(crate) MediaFanoutDeleteServiceContainer {
+        components = [media::fanout_delete::FanoutDeleteService, FanoutRepositoryImpl],
+        providers = []
+    }
+}
+
+module! {
+    pub(crate) MediaFanoutEventServiceContainer {
+        components = [media::fanout_event::FanoutEventService, MediaRepositoryImpl, FanoutRepositoryImpl, ApplicationStateAlwaysTrueImpl, ResourceRegistryImpl],
+        providers = []
+    }
+}
+
+//========== Listener Service ==========
+
+module! {
+    pub(crate) ListenerSubscribeServiceContainer {
+        components = [listener::subscribe::SubscribeService, ListenerRegistryImpl],
+        providers = []
+    }
+}
+
+module! {
+    pub(crate) ListenerUnsubscribeServiceContainer {
+        components = [listener::unsubscribe::UnsubscribeService, ListenerRegistryImpl],
+        providers = []
+    }
+}
+
+module! {
+    pub(crate) ListenerListServiceContainer {
+        components = [listener::list::ListService, ListenerRegistryImpl],
+        providers = []
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::fanout::value_object::FanoutId;
+use crate::domain::webrtc::common::value_object::{PhantomId, SocketInfo};
+use crate::domain::webrtc::media::value_object::{MediaConnectionId, MediaId};
+
+/// Information of a fan-out relay
+/// RTP packets sent to `input` are duplicated to the media socket of every outgoing call
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FanoutInfo {
+    pub fanout_id: FanoutId,
+    pub is_video: bool,
+    pub input: SocketInfo<PhantomId>,
+}
+
+/// Wrapper to adapt to JSON format
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FanoutIdWrapper {
+    pub fanout_id: FanoutId,
+}
+
+/// Result of a call made from a fan-out relay
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FanoutCallResult {
+    pub fanout_id: FanoutId,
+    pub media_connection_id: MediaConnectionId,
+    pub send_socket: SocketInfo<MediaId>,
+}
@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use shaku::Interface;
+
+use crate::domain::fanout::entity::FanoutInfo;
+use crate::domain::fanout::value_object::FanoutId;
+use crate::domain::webrtc::common::value_object::{PhantomId, SocketInfo};
+use crate::domain::webrtc::media::value_object::{MediaConnectionId, MediaId};
+use crate::error;
+
+#[cfg(test)]
+use mockall::automock;
+
+/// 1つのローカルRTP入力を複数のmedia socketへ複製する機能を定義する
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait FanoutRepository: Interface {
+    /// 入力用のUDP portをbindし、複製を開始する
+    async fn create(
+        &self,
+        is_video: bool,
+        input: SocketInfo<PhantomId>,
+    ) -> Result<FanoutInfo, error::Error>;
+    /// 複製を停止し、入力用のportを開放する
+    /// 複製先のmedia socketは、remove_recipientで取り出されるまで保持する
+    fn delete(&self, fanout_id: &FanoutId) -> Result<FanoutInfo, error::Error>;
+    fn find(&self, fanout_id: &FanoutId) -> Option<FanoutInfo>;
+    /// MediaConnectionの送信用media socketを複製先として追加する
+    fn add_recipient(
+        &self,
+        fanout_id: &FanoutId,
+        media_connection_id: &MediaConnectionId,
+        media_socket: SocketInfo<MediaId>,
+    ) -> Result<(), error::Error>;
+    /// MediaConnectionを複製先から外し、そのmedia socketを返す
+    fn remove_recipient(
+        &self,
+        media_connection_id: &MediaConnectionId,
+    ) -> Option<SocketInfo<MediaId>>;
+}
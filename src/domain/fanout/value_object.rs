@@ -0,0 +1,18 @@
+// Fan-outはSkyWay WebRTC GatewayのAPIではなく、このcrate内で完結する機能である
+// そのためIDもskyway-webrtc-gateway-api crateには存在せず、ここで定義する
+
+use serde::{Deserialize, Serialize};
+
+/// ID associated with a fan-out relay
+#[derive(Serialize, Deserialize, Debug, Clone, PartialOrd, PartialEq, Eq, Ord, Hash)]
+pub struct FanoutId(pub String);
+
+impl FanoutId {
+    pub fn new(id: impl Into<String>) -> Self {
+        FanoutId(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
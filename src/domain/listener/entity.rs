@@ -0,0 +1,213 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::domain::webrtc::data::entity::DataConnectionIdWrapper;
+use crate::domain::webrtc::media::entity::MediaConnectionIdWrapper;
+use crate::domain::webrtc::peer::value_object::PeerInfo;
+
+/// Kind of a resource whose events are listened
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListenerKind {
+    #[serde(rename = "PEER")]
+    Peer,
+    #[serde(rename = "DATA")]
+    Data,
+    #[serde(rename = "MEDIA")]
+    Media,
+}
+
+/// Information of an active event listener
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ListenerInfo {
+    pub kind: ListenerKind,
+    /// peer_id, data_connection_id or media_connection_id
+    pub resource_id: String,
+}
+
+impl ListenerInfo {
+    pub fn new(kind: ListenerKind, resource_id: impl Into<String>) -> Self {
+        ListenerInfo {
+            kind,
+            resource_id: resource_id.into(),
+        }
+    }
+}
+
+/// Wrapper to adapt to JSON format
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ListenerList {
+    pub listeners: Vec<ListenerInfo>,
+}
+
+// 監視ループがeventの取得を諦めて終了した場合に、その理由を通知する
+// CLOSEによる正常な終了や、UNSUBSCRIBEによる停止では通知しない
+/// Notification that an event listener stopped because events could not be fetched
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ListenerStopped {
+    #[serde(flatten)]
+    pub listener: ListenerInfo,
+    pub reason: String,
+}
+
+// JSONの形からどのリソースのeventを監視するか判別する
+/// Resource whose events are listened
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ListenerTarget {
+    Data(DataConnectionIdWrapper),
+    Media(MediaConnectionIdWrapper),
+    Peer(PeerInfo),
+}
+
+impl ListenerTarget {
+    pub fn listener_info(&self) -> ListenerInfo {
+        match self {
+            ListenerTarget::Data(wrapper) => {
+                ListenerInfo::new(ListenerKind::Data, wrapper.data_connection_id.as_str())
+            }
+            ListenerTarget::Media(wrapper) => {
+                ListenerInfo::new(ListenerKind::Media, wrapper.media_connection_id.as_str())
+            }
+            ListenerTarget::Peer(peer_info) => {
+                ListenerInfo::new(ListenerKind::Peer, peer_info.peer_id().as_str())
+            }
+        }
+    }
+}
+
+// 起動した監視ループ1つに対応する
+// runningフラグをループと共有し、UNSUBSCRIBE時にはフラグを下ろしてループを終了させる
+// 同じリソースの監視を停止後に再開した場合に、古いループの終了で新しい記録を消さないよう、generationで区別する
+// 所有するPeerのCLOSEによって停止した場合は、closedフラグを立ててCLOSE eventの補完が必要なことを示す
+// 停止を指示されたループも実行中のevent取得から戻るまでは動いているので、
+// 再開したループは古いループの終了を待ってからeventの取得を始める
+#[derive(Debug, Clone)]
+pub(crate) struct ListenerHandle {
+    pub(crate) info: ListenerInfo,
+    pub(crate) generation: u64,
+    running: Arc<AtomicBool>,
+    closed: Arc<AtomicBool>,
+    finished_tx: Arc<watch::Sender<bool>>,
+    finished_rx: watch::Receiver<bool>,
+    // 終了を待つ必要がある、同じリソースを監視していた古いループ
+    previous: Option<watch::Receiver<bool>>,
+}
+
+impl ListenerHandle {
+    pub(crate) fn new(info: ListenerInfo, generation: u64) -> Self {
+        let (finished_tx, finished_rx) = watch::channel(false);
+        ListenerHandle {
+            info,
+            generation,
+            running: Arc::new(AtomicBool::new(true)),
+            closed: Arc::new(AtomicBool::new(false)),
+            finished_tx: Arc::new(finished_tx),
+            finished_rx,
+            previous: None,
+        }
+    }
+
+    // previousのループが終了するまで、eventの取得を待たせる
+    pub(crate) fn after(mut self, previous: &ListenerHandle) -> Self {
+        self.previous = Some(previous.finished_rx.clone());
+        self
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    // 所有するPeerがCLOSEしたため、監視ループを停止する
+    pub(crate) fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.stop();
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    // ループのtaskが終了したことを記録する
+    pub(crate) fn finish(&self) {
+        let _ = self.finished_tx.send(true);
+    }
+
+    // 同じリソースを監視していた古いループがあれば、その終了を待つ
+    pub(crate) async fn wait_previous(&self) {
+        if let Some(ref previous) = self.previous {
+            let mut previous = previous.clone();
+            while !*previous.borrow() {
+                if previous.changed().await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_listener_entity {
+    use super::*;
+
+    #[test]
+    fn target_from_json() {
+        let target = serde_json::from_str::<ListenerTarget>(
+            r#"{"data_connection_id": "dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            target.listener_info(),
+            ListenerInfo::new(
+                ListenerKind::Data,
+                "dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c"
+            )
+        );
+
+        let target = serde_json::from_str::<ListenerTarget>(
+            r#"{"media_connection_id": "mc-50a32bab-b3d9-4913-8e20-f79c90a6a211"}"#,
+        )
+        .unwrap();
+        assert_eq!(target.listener_info().kind, ListenerKind::Media);
+
+        let target = serde_json::from_str::<ListenerTarget>(
+            r#"{"peer_id": "peer_id", "token": "pt-9749250e-d157-4f80-9ee2-359ce8524308"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            target.listener_info(),
+            ListenerInfo::new(ListenerKind::Peer, "peer_id")
+        );
+    }
+
+    #[test]
+    fn stop_handle() {
+        let handle = ListenerHandle::new(ListenerInfo::new(ListenerKind::Peer, "peer_id"), 0);
+        // cloneしたhandleとフラグを共有する
+        let cloned = handle.clone();
+        assert!(cloned.is_running());
+        handle.stop();
+        assert!(!cloned.is_running());
+    }
+
+    #[tokio::test]
+    async fn wait_previous() {
+        let info = ListenerInfo::new(ListenerKind::Peer, "peer_id");
+        let old = ListenerHandle::new(info.clone(), 0);
+        let new = ListenerHandle::new(info, 1).after(&old);
+
+        // 古いループが終了するまでは待機する
+        let mut wait = Box::pin(new.wait_previous());
+        let pending = tokio::time::timeout(std::time::Duration::from_millis(10), &mut wait).await;
+        assert!(pending.is_err());
+
+        old.finish();
+        wait.await;
+    }
+}
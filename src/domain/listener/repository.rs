@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use shaku::Interface;
+
+use crate::domain::listener::entity::{ListenerHandle, ListenerInfo};
+use crate::domain::webrtc::data::entity::DataConnectionEventEnum;
+use crate::domain::webrtc::data::value_object::DataConnectionId;
+use crate::domain::webrtc::media::entity::MediaConnectionEventEnum;
+use crate::domain::webrtc::media::value_object::MediaConnectionId;
+
+#[cfg(test)]
+use mockall::automock;
+
+/// 起動中のevent監視ループを記録する機能を定義する
+/// 同じリソースに対して複数の監視ループが起動し、eventが重複して通知されることを防ぐ
+#[cfg_attr(test, automock)]
+pub(crate) trait ListenerRegistry: Interface {
+    /// 監視ループの起動を記録する
+    /// 同じリソースを監視中のループが既に存在する場合はNoneを返す
+    fn start(&self, info: ListenerInfo) -> Option<ListenerHandle>;
+    /// 監視ループに停止を指示する。監視中でない場合はfalseを返す
+    /// ループは実行中のevent取得から戻った時点で終了する
+    /// 終了するまでの間に同じリソースの監視を起動した場合、新しいループはその終了を待ってから動き始める
+    fn stop(&self, info: &ListenerInfo) -> bool;
+    /// 終了した監視ループの記録を削除する
+    fn finish(&self, handle: &ListenerHandle);
+    /// OPEN, READYを待つために一時的に起動した監視の記録を削除する
+    /// Connectionは引き続き利用されるので、finishと異なり所有者の記録は残す
+    fn finish_wait(&self, handle: &ListenerHandle);
+    fn is_active(&self, info: &ListenerInfo) -> bool;
+    fn list(&self) -> Vec<ListenerInfo>;
+    /// DataConnection, MediaConnectionを所有するPeerを記録する
+    /// 監視ループの起動前に記録しても良い
+    fn set_owner(&self, peer_id: &str, info: ListenerInfo);
+    /// リソースを所有するPeerのpeer_idを返す。記録されていない場合はNoneを返す
+    fn owner(&self, info: &ListenerInfo) -> Option<String>;
+    /// Peerが所有するリソースの記録を削除し、監視中のループにはCLOSEによる停止を指示する
+    /// 停止を指示したリソースを返す
+    fn close_owned(&self, peer_id: &str) -> Vec<ListenerInfo>;
+}
+
+/// 監視ループを介さずに取得したeventを、監視ループが通知したeventと同じ経路で配信する機能を定義する
+/// 既に起動している監視ループが配信するeventも、この機能を介して受け取る
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub(crate) trait EventPublisher: Interface {
+    /// DataConnectionのeventに通知順の番号を振り、購読者に配信する
+    async fn publish_data(
+        &self,
+        data_connection_id: &DataConnectionId,
+        event: DataConnectionEventEnum,
+    );
+    /// DataConnectionの監視ループが配信したeventを、履歴に残っているものから順に返す
+    fn subscribe_data(
+        &self,
+        data_connection_id: &DataConnectionId,
+    ) -> BoxStream<'static, DataConnectionEventEnum>;
+    /// MediaConnectionのeventに通知順の番号を振り、購読者に配信する
+    async fn publish_media(
+        &self,
+        media_connection_id: &MediaConnectionId,
+        event: MediaConnectionEventEnum,
+    );
+    /// MediaConnectionの監視ループが配信したeventを、履歴に残っているものから順に返す
+    fn subscribe_media(
+        &self,
+        media_connection_id: &MediaConnectionId,
+    ) -> BoxStream<'static, MediaConnectionEventEnum>;
+}
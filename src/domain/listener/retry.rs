@@ -0,0 +1,158 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::error;
+
+/// Retry policy for long polling of events
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of retries after a transient error before the listener gives up
+    pub max_retries: u32,
+    /// Wait before the first retry. It doubles on each retry
+    pub initial_backoff: Duration,
+    /// Upper bound of the wait between retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+// Gatewayからエラーを示すレスポンスが返された場合は、status codeに応じたLocalErrorとして通知される
+// 400, 403, 404(既にCLOSEされたリソース)やJSONの解析エラーはリトライしても結果が変わらないので、再試行しない
+// Gatewayまで到達できなかったネットワークエラーと、5xx, 408のみ再試行する
+pub(crate) fn is_transient(error: &error::Error) -> bool {
+    error::ErrorKind::from_error(error) == error::ErrorKind::Unavailable
+}
+
+impl RetryPolicy {
+    // attempt回目(0始まり)の再試行前に待機する時間
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .unwrap_or(self.max_backoff);
+        std::cmp::min(backoff, self.max_backoff)
+    }
+
+    // event long pollingを実行し、一時的なエラーの場合はbackoffを挟んで再試行する
+    // 再試行を諦めた場合は、LISTENER_STOPPEDで通知する理由を返す
+    pub(crate) async fn call<T, F, Fut>(&self, mut f: F) -> Result<T, String>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, error::Error>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match f().await {
+                Ok(value) => return Ok(value),
+                Err(e) if !is_transient(&e) => {
+                    return Err(format!("event api returned an error: {:?}", e));
+                }
+                Err(e) if attempt >= self.max_retries => {
+                    return Err(format!(
+                        "event api failed after {} retries: {:?}",
+                        attempt, e
+                    ));
+                }
+                Err(_) => {
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_retry {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    // skyway-webrtc-gateway-apiが生成するメッセージ
+    const SERVICE_UNAVAILABLE: &str = "recv invalid response: url: http://localhost:8000/peers/peer_id/events?token=pt-9749250e-d157-4f80-9ee2-359ce8524308 code: 503 Service Unavailable";
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(3),
+        }
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = policy();
+        assert_eq!(policy.backoff(0), Duration::from_millis(1));
+        assert_eq!(policy.backoff(1), Duration::from_millis(2));
+        // max_backoffで頭打ちになる
+        assert_eq!(policy.backoff(2), Duration::from_millis(3));
+        assert_eq!(policy.backoff(100), Duration::from_millis(3));
+    }
+
+    #[tokio::test]
+    async fn recover_from_transient_error() {
+        let counter = AtomicU32::new(0);
+        let result = policy()
+            .call(|| async {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    Err(error::Error::create_local_error(SERVICE_UNAVAILABLE))
+                } else {
+                    Ok(())
+                }
+            })
+            .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn give_up() {
+        let counter = AtomicU32::new(0);
+        let result: Result<(), String> = policy()
+            .call(|| async {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Err(error::Error::create_local_error(SERVICE_UNAVAILABLE))
+            })
+            .await;
+        // 最初の1回と、2回の再試行
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            result,
+            Err(format!(
+                "event api failed after 2 retries: LocalError({:?})",
+                SERVICE_UNAVAILABLE
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn permanent_error() {
+        // 404と403はリトライしても結果が変わらない
+        for message in &["recv Not Found", "recv Forbidden"] {
+            let counter = AtomicU32::new(0);
+            let result: Result<(), String> = policy()
+                .call(|| async {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Err(error::Error::create_local_error(message))
+                })
+                .await;
+            // 再試行しない
+            assert_eq!(counter.load(Ordering::SeqCst), 1);
+            assert_eq!(
+                result,
+                Err(format!(
+                    "event api returned an error: LocalError({:?})",
+                    message
+                ))
+            );
+        }
+    }
+}
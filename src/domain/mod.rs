@@ -1,9 +1,18 @@
 // Domain層として機能を定義する
-// 現時点では大きく2つの機能が存在する
+// 現時点では大きく5つの機能が存在する
 // ・アプリケーションの起動状態を示すもの -> state module
 //   (event loopからのexitの際に利用される)
 // ・SkyWay WebRTC Gateway関連のもの -> webrtc module
+// ・ローカルのRTP入力を複数のMediaConnectionへ複製するもの -> fanout module
+// ・このcrateで生成したリソースを記録し、リクエストの事前チェックに利用するもの -> registry module
+// ・起動中のevent監視ループを記録し、重複を防ぐもの -> listener module
 
+/// 1つのRTP入力を複数のMediaConnectionで送信するための機能を定義する
+pub(crate) mod fanout;
+/// 起動中のevent監視ループを記録する
+pub(crate) mod listener;
+/// このcrateで生成したリソースを記録し、CALL, ANSWERの事前チェックを行う
+pub(crate) mod registry;
 /// アプリケーションが継続して実行されるべきかどうかを示す
 pub(crate) mod state;
 /// SkyWay WebRTC Gatewayを利用するための機能を定義する
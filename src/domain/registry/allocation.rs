@@ -0,0 +1,241 @@
+// redirect_paramsのうち"auto"と指定された項目(設定によっては省略された項目も)に、
+// ResourceRegistryから確保したアドレスを埋め込む
+// 確保したアドレスは、Connectionの確立後にreserve_redirectで紐付け、失敗時にはrelease_portsで開放する
+
+use std::net::SocketAddr;
+
+use serde_json::{Map, Value};
+
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::webrtc::common::value_object::{PhantomId, SerializableSocket, SocketInfo};
+use crate::error;
+
+// 自動割当を指示する値
+const AUTO: &str = "auto";
+
+fn is_auto(value: &Value) -> bool {
+    value.as_str() == Some(AUTO)
+}
+
+// portを1つ確保し、SocketInfoのJSONとして返す
+fn allocate_socket(
+    registry: &dyn ResourceRegistry,
+    allocated: &mut Vec<SocketAddr>,
+) -> Result<Value, error::Error> {
+    let address = registry.allocate_port()?;
+    allocated.push(address);
+    let socket =
+        SocketInfo::<PhantomId>::try_create(None, &address.ip().to_string(), address.port())?;
+    serde_json::to_value(socket).map_err(|e| error::Error::SerdeError { error: e })
+}
+
+// 途中で失敗した場合は、それまでに確保したアドレスを開放する
+fn release_on_error<T>(
+    registry: &dyn ResourceRegistry,
+    allocated: &[SocketAddr],
+    result: Result<T, error::Error>,
+) -> Result<T, error::Error> {
+    if result.is_err() {
+        registry.release_ports(allocated);
+    }
+    result
+}
+
+/// Fill "auto" entries of RedirectParameters in CallQuery or AnswerQuery with allocated addresses
+pub(crate) fn allocate_media_redirect(
+    registry: &dyn ResourceRegistry,
+    query: &mut Value,
+) -> Result<Vec<SocketAddr>, error::Error> {
+    // constraintsが省略された場合はvideo, audio共に有効として扱う
+    let video = query
+        .pointer("/constraints/video")
+        .and_then(Value::as_bool)
+        .unwrap_or(true);
+    let audio = query
+        .pointer("/constraints/audio")
+        .and_then(Value::as_bool)
+        .unwrap_or(true);
+    let query = match query.as_object_mut() {
+        Some(query) => query,
+        // objectでない場合のエラーはdeserialize時に返す
+        None => return Ok(vec![]),
+    };
+
+    let entry = query.entry("redirect_params").or_insert(Value::Null);
+    // redirect_params全体に"auto"を与えた場合は、有効なメディアの全ての項目を割り当てる
+    let auto_all = is_auto(entry);
+    let allocate_omitted = auto_all || registry.allocates_omitted();
+    if entry.is_null() || auto_all {
+        if !allocate_omitted {
+            return Ok(vec![]);
+        }
+        *entry = Value::Object(Map::new());
+    }
+    let params = match entry.as_object_mut() {
+        Some(params) => params,
+        None => return Ok(vec![]),
+    };
+
+    let mut allocated = vec![];
+    let keys = [
+        ("video", video),
+        ("video_rtcp", video),
+        ("audio", audio),
+        ("audio_rtcp", audio),
+    ];
+    for (key, enabled) in keys {
+        let slot = params.get(key);
+        let requested = match slot {
+            Some(value) if is_auto(value) => true,
+            None | Some(Value::Null) => enabled && allocate_omitted,
+            _ => false,
+        };
+        if requested {
+            let socket = allocate_socket(registry, &mut allocated);
+            let socket = release_on_error(registry, &allocated, socket)?;
+            params.insert(key.to_string(), socket);
+        }
+    }
+    Ok(allocated)
+}
+
+/// Fill "auto" redirect_params of ConnectQuery or RedirectParams with an allocated address
+pub(crate) fn allocate_data_redirect(
+    registry: &dyn ResourceRegistry,
+    params: &mut Value,
+) -> Result<Vec<SocketAddr>, error::Error> {
+    let params = match params.as_object_mut() {
+        Some(params) => params,
+        None => return Ok(vec![]),
+    };
+    let requested = match params.get("redirect_params") {
+        Some(value) if is_auto(value) => true,
+        None | Some(Value::Null) => registry.allocates_omitted(),
+        _ => false,
+    };
+    if !requested {
+        return Ok(vec![]);
+    }
+
+    let mut allocated = vec![];
+    let socket = allocate_socket(registry, &mut allocated);
+    let socket = release_on_error(registry, &allocated, socket)?;
+    params.insert("redirect_params".to_string(), socket);
+    Ok(allocated)
+}
+
+#[cfg(test)]
+mod test_allocation {
+    use serde_json::json;
+
+    use crate::domain::registry::repository::MockResourceRegistry;
+
+    use super::*;
+
+    // 13000から順にportを割り当てるMockを作成
+    fn registry(allocate_omitted: bool) -> MockResourceRegistry {
+        let mut registry = MockResourceRegistry::default();
+        let mut port = 13000;
+        registry.expect_allocate_port().returning(move || {
+            port += 1;
+            Ok(SocketAddr::new("127.0.0.1".parse().unwrap(), port - 1))
+        });
+        registry
+            .expect_allocates_omitted()
+            .returning(move || allocate_omitted);
+        registry
+    }
+
+    #[test]
+    fn auto_entries() {
+        let registry = registry(false);
+        let mut query = json!({
+            "constraints": { "video": true, "audio": true },
+            "redirect_params": {
+                "video": "auto",
+                "audio": { "ip_v4": "127.0.0.1", "port": 20000 }
+            }
+        });
+
+        let allocated = allocate_media_redirect(&registry, &mut query).unwrap();
+
+        // "auto"と指定された項目のみが割り当てられる
+        assert_eq!(
+            allocated,
+            vec!["127.0.0.1:13000".parse::<SocketAddr>().unwrap()]
+        );
+        assert_eq!(
+            query["redirect_params"]["video"],
+            json!({ "ip_v4": "127.0.0.1", "port": 13000 })
+        );
+        assert_eq!(query["redirect_params"]["audio"]["port"], json!(20000));
+        assert!(query["redirect_params"].get("video_rtcp").is_none());
+    }
+
+    #[test]
+    fn auto_all() {
+        let registry = registry(false);
+        let mut query = json!({
+            "constraints": { "video": true, "audio": false },
+            "redirect_params": "auto"
+        });
+
+        let allocated = allocate_media_redirect(&registry, &mut query).unwrap();
+
+        // 有効なメディアの項目のみが割り当てられる
+        assert_eq!(allocated.len(), 2);
+        assert_eq!(query["redirect_params"]["video"]["port"], json!(13000));
+        assert_eq!(query["redirect_params"]["video_rtcp"]["port"], json!(13001));
+        assert!(query["redirect_params"].get("audio").is_none());
+    }
+
+    #[test]
+    fn omitted() {
+        // 設定で有効にされていない場合は、省略された項目は割り当てない
+        let mut query = json!({});
+        assert!(allocate_media_redirect(&registry(false), &mut query)
+            .unwrap()
+            .is_empty());
+
+        // 設定で有効にされている場合は、省略された項目も割り当てる
+        let mut params = json!({ "data_connection_id": "dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c" });
+        let allocated = allocate_data_redirect(&registry(true), &mut params).unwrap();
+        assert_eq!(allocated.len(), 1);
+        assert_eq!(params["redirect_params"]["port"], json!(13000));
+    }
+
+    #[test]
+    fn release_when_exhausted() {
+        // 2つ目の割当に失敗するMockを作成
+        let mut registry = MockResourceRegistry::default();
+        let mut count = 0;
+        registry.expect_allocate_port().returning(move || {
+            count += 1;
+            if count == 1 {
+                Ok("127.0.0.1:13000".parse().unwrap())
+            } else {
+                Err(error::Error::create_local_error(
+                    "no free port in redirect port range 13000-13000",
+                ))
+            }
+        });
+        registry.expect_allocates_omitted().returning(|| false);
+        // 確保済みのportは開放される
+        registry
+            .expect_release_ports()
+            .times(1)
+            .returning(|addresses| {
+                let expected: SocketAddr = "127.0.0.1:13000".parse().unwrap();
+                assert_eq!(addresses, &[expected]);
+            });
+
+        let mut query = json!({ "redirect_params": { "video": "auto", "audio": "auto" } });
+        let result = allocate_media_redirect(&registry, &mut query);
+
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(message, "no free port in redirect port range 13000-13000");
+        } else {
+            assert!(false);
+        }
+    }
+}
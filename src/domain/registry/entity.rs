@@ -0,0 +1,27 @@
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
+
+/// Kind of a media socket created through this crate
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Video,
+    Audio,
+}
+
+impl MediaKind {
+    pub fn from_is_video(is_video: bool) -> Self {
+        if is_video {
+            MediaKind::Video
+        } else {
+            MediaKind::Audio
+        }
+    }
+}
+
+// 同一ホスト上で同じportを奪い合うかどうか
+// 0.0.0.0などのunspecified addressは全てのアドレスと重なるものとして扱う
+pub(crate) fn addresses_overlap(a: &SocketAddr, b: &SocketAddr) -> bool {
+    a.port() == b.port() && (a.ip() == b.ip() || a.ip().is_unspecified() || b.ip().is_unspecified())
+}
@@ -0,0 +1,4 @@
+pub(crate) mod allocation;
+pub(crate) mod entity;
+pub(crate) mod repository;
+pub(crate) mod validation;
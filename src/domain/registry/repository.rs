@@ -0,0 +1,41 @@
+use std::net::SocketAddr;
+
+use shaku::Interface;
+
+use crate::domain::registry::entity::MediaKind;
+use crate::domain::webrtc::media::value_object::{MediaId, RtcpId};
+use crate::error;
+
+#[cfg(test)]
+use mockall::automock;
+
+/// このcrateを通して生成されたリソースを記録する機能を定義する
+/// CALL, ANSWERの事前チェックと、redirect先のportの自動割当、送信先アドレスの解決に利用される
+#[cfg_attr(test, automock)]
+pub trait ResourceRegistry: Interface {
+    fn register_media(&self, media_id: &MediaId, kind: MediaKind, address: SocketAddr);
+    fn unregister_media(&self, media_id: &MediaId);
+    /// 生成済みで、まだ削除されていないmedia socketの種別を返す
+    fn find_media(&self, media_id: &MediaId) -> Option<MediaKind>;
+    /// 生成済みで、まだ削除されていないmedia socketのアドレスを返す
+    fn media_address(&self, media_id: &MediaId) -> Option<SocketAddr>;
+    fn register_rtcp(&self, rtcp_id: &RtcpId, address: SocketAddr);
+    fn unregister_rtcp(&self, rtcp_id: &RtcpId);
+    fn contains_rtcp(&self, rtcp_id: &RtcpId) -> bool;
+    /// 生成済みで、まだ削除されていないrtcp socketのアドレスを返す
+    fn rtcp_address(&self, rtcp_id: &RtcpId) -> Option<SocketAddr>;
+    /// MediaConnection, DataConnectionのredirect先として利用するアドレスを記録する
+    /// 同じConnectionに対して再度呼ばれた場合は置き換える
+    fn reserve_redirect(&self, connection_id: &str, addresses: Vec<SocketAddr>);
+    /// Connectionの終了時に、redirect先のアドレスを開放する
+    fn release_redirect(&self, connection_id: &str);
+    /// アドレスをredirect先として利用しているConnectionのIDを返す
+    fn redirect_owner(&self, address: &SocketAddr) -> Option<String>;
+    /// 設定された範囲から未使用のUDP portを確保する
+    /// 確保したアドレスは、reserve_redirectかrelease_portsが呼ばれるまで他に割り当てられない
+    fn allocate_port(&self) -> Result<SocketAddr, error::Error>;
+    /// 確保したが利用しなかったアドレスを開放する
+    fn release_ports(&self, addresses: &[SocketAddr]);
+    /// 省略されたredirect_paramsの項目にもportを割り当てるかどうか
+    fn allocates_omitted(&self) -> bool;
+}
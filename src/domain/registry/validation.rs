@@ -0,0 +1,251 @@
+// CALL, ANSWERをSkyWay WebRTC Gatewayに送信する前に、
+// 参照しているリソースがこのcrateで生成され、まだ利用可能であるかをチェックする
+
+use std::net::SocketAddr;
+
+use crate::domain::registry::entity::{addresses_overlap, MediaKind};
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::webrtc::common::value_object::{SerializableId, SerializableSocket};
+use crate::domain::webrtc::media::entity::{Constraints, MediaParams, RedirectParameters};
+use crate::error;
+
+// media_params内のmedia_id, rtcp_idの種別と生存をチェックする
+fn check_media_params(
+    registry: &dyn ResourceRegistry,
+    key: &str,
+    params: &MediaParams,
+    expected: MediaKind,
+    problems: &mut Vec<String>,
+) {
+    let media_id = params.media_id.as_str();
+    match registry.find_media(&params.media_id) {
+        None => problems.push(format!(
+            "{}.media_id {} does not exist or has been deleted",
+            key, media_id
+        )),
+        Some(kind) if kind != expected => problems.push(format!(
+            "{}.media_id {} is not a {:?} socket",
+            key, media_id, expected
+        )),
+        _ => {}
+    }
+    if let Some(ref rtcp_id) = params.rtcp_id {
+        if !registry.contains_rtcp(rtcp_id) {
+            problems.push(format!(
+                "{}.rtcp_id {} does not exist or has been deleted",
+                key,
+                rtcp_id.as_str()
+            ));
+        }
+    }
+}
+
+// redirect_paramsに含まれるアドレスを列挙する
+fn redirect_addresses(redirect_params: &RedirectParameters) -> Vec<(&'static str, SocketAddr)> {
+    let sockets = [
+        ("video", &redirect_params.video),
+        ("video_rtcp", &redirect_params.video_rtcp),
+        ("audio", &redirect_params.audio),
+        ("audio_rtcp", &redirect_params.audio_rtcp),
+    ];
+    sockets
+        .iter()
+        .filter_map(|(key, socket)| {
+            socket
+                .as_ref()
+                .map(|socket| (*key, SocketAddr::new(socket.ip(), socket.port())))
+        })
+        .collect()
+}
+
+// redirect先のアドレスが、他のMediaConnectionや同じquery内の他の項目と重なっていないかチェックする
+fn check_redirect_params(
+    registry: &dyn ResourceRegistry,
+    redirect_params: &RedirectParameters,
+    problems: &mut Vec<String>,
+) {
+    let addresses = redirect_addresses(redirect_params);
+    for (index, (key, address)) in addresses.iter().enumerate() {
+        if let Some(owner) = registry.redirect_owner(address) {
+            problems.push(format!(
+                "redirect_params.{} {} is already used by {}",
+                key, address, owner
+            ));
+        }
+        for (other_key, other) in addresses.iter().skip(index + 1) {
+            if addresses_overlap(address, other) {
+                problems.push(format!(
+                    "redirect_params.{} and redirect_params.{} use the same address {}",
+                    key, other_key, address
+                ));
+            }
+        }
+    }
+}
+
+// 確立したMediaConnectionのredirect先を記録し、以降の事前チェックで重複を検出できるようにする
+pub(crate) fn reserve_redirect_params(
+    registry: &dyn ResourceRegistry,
+    connection_id: &str,
+    redirect_params: Option<&RedirectParameters>,
+) {
+    if let Some(redirect_params) = redirect_params {
+        let addresses = redirect_addresses(redirect_params)
+            .into_iter()
+            .map(|(_, address)| address)
+            .collect();
+        registry.reserve_redirect(connection_id, addresses);
+    }
+}
+
+/// Check that resources referenced by a query exist and are not used by other connections
+pub fn validate_resources(
+    registry: &dyn ResourceRegistry,
+    constraints: Option<&Constraints>,
+    redirect_params: Option<&RedirectParameters>,
+) -> Result<(), error::Error> {
+    let mut problems = vec![];
+    if let Some(constraints) = constraints {
+        if let Some(ref params) = constraints.video_params {
+            check_media_params(
+                registry,
+                "video_params",
+                params,
+                MediaKind::Video,
+                &mut problems,
+            );
+        }
+        if let Some(ref params) = constraints.audio_params {
+            check_media_params(
+                registry,
+                "audio_params",
+                params,
+                MediaKind::Audio,
+                &mut problems,
+            );
+        }
+    }
+    if let Some(redirect_params) = redirect_params {
+        check_redirect_params(registry, redirect_params, &mut problems);
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        let message = format!("invalid resources: {}", problems.join(", "));
+        Err(error::Error::create_local_error(&message))
+    }
+}
+
+#[cfg(test)]
+mod test_validation {
+    use crate::domain::registry::repository::MockResourceRegistry;
+    use crate::domain::webrtc::common::value_object::{PhantomId, SocketInfo};
+    use crate::domain::webrtc::media::value_object::{MediaId, RtcpId};
+
+    use super::*;
+
+    fn media_params(media_id: &str, rtcp_id: Option<&str>) -> MediaParams {
+        MediaParams {
+            band_width: 1500,
+            codec: "H264".to_string(),
+            media_id: MediaId::try_create(media_id).unwrap(),
+            rtcp_id: rtcp_id.map(|rtcp_id| RtcpId::try_create(rtcp_id).unwrap()),
+            payload_type: None,
+            sampling_rate: None,
+        }
+    }
+
+    fn socket(port: u16) -> Option<SocketInfo<PhantomId>> {
+        Some(SocketInfo::<PhantomId>::try_create(None, "127.0.0.1", port).unwrap())
+    }
+
+    #[test]
+    fn valid() {
+        let mut registry = MockResourceRegistry::default();
+        registry
+            .expect_find_media()
+            .returning(|_| Some(MediaKind::Video));
+        registry.expect_contains_rtcp().returning(|_| true);
+        registry.expect_redirect_owner().returning(|_| None);
+
+        let constraints = Constraints {
+            video: true,
+            videoReceiveEnabled: None,
+            audio: false,
+            audioReceiveEnabled: None,
+            video_params: Some(media_params(
+                "vi-4d053831-5dc2-461b-a358-d062d6115216",
+                Some("rc-4d053831-5dc2-461b-a358-d062d6115216"),
+            )),
+            audio_params: None,
+            metadata: None,
+        };
+        let redirect_params = RedirectParameters {
+            video: socket(13000),
+            video_rtcp: socket(13001),
+            audio: None,
+            audio_rtcp: None,
+        };
+
+        assert!(validate_resources(&registry, Some(&constraints), Some(&redirect_params)).is_ok());
+    }
+
+    // 全ての問題が列挙される
+    #[test]
+    fn list_all_problems() {
+        let mut registry = MockResourceRegistry::default();
+        // audioのmedia socketをvideo_paramsで参照しているケース
+        registry
+            .expect_find_media()
+            .returning(|media_id| match media_id.as_str() {
+                "au-4d053831-5dc2-461b-a358-d062d6115216" => Some(MediaKind::Audio),
+                _ => None,
+            });
+        registry.expect_contains_rtcp().returning(|_| false);
+        registry.expect_redirect_owner().returning(|address| {
+            if address.port() == 13000 {
+                Some("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211".to_string())
+            } else {
+                None
+            }
+        });
+
+        let constraints = Constraints {
+            video: true,
+            videoReceiveEnabled: None,
+            audio: true,
+            audioReceiveEnabled: None,
+            video_params: Some(media_params(
+                "au-4d053831-5dc2-461b-a358-d062d6115216",
+                Some("rc-4d053831-5dc2-461b-a358-d062d6115216"),
+            )),
+            audio_params: Some(media_params(
+                "au-00000000-5dc2-461b-a358-d062d6115216",
+                None,
+            )),
+            metadata: None,
+        };
+        let redirect_params = RedirectParameters {
+            video: socket(13000),
+            video_rtcp: socket(13001),
+            audio: socket(13001),
+            audio_rtcp: None,
+        };
+        let result = validate_resources(&registry, Some(&constraints), Some(&redirect_params));
+
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "invalid resources: \
+                 video_params.media_id au-4d053831-5dc2-461b-a358-d062d6115216 is not a Video socket, \
+                 video_params.rtcp_id rc-4d053831-5dc2-461b-a358-d062d6115216 does not exist or has been deleted, \
+                 audio_params.media_id au-00000000-5dc2-461b-a358-d062d6115216 does not exist or has been deleted, \
+                 redirect_params.video 127.0.0.1:13000 is already used by mc-50a32bab-b3d9-4913-8e20-f79c90a6a211, \
+                 redirect_params.video_rtcp and redirect_params.audio use the same address 127.0.0.1:13001"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+}
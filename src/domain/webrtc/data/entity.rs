@@ -30,6 +30,24 @@ pub use skyway_webrtc_gateway_api::data::RedirectDataParams;
 /// Response of PUT /data/connections API
 pub use skyway_webrtc_gateway_api::data::RedirectDataResponse;
 
+// redirect先のportを自動で割り当てた場合、エンドユーザは割り当てられたportを知る必要があるので、
+// DataConnectionIdと合わせて返す
+/// Result of Connect and Redirect
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct DataConnectionResult {
+    pub data_connection_id: DataConnectionId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recv_socket: Option<SocketInfo<PhantomId>>,
+}
+
+// SUBSCRIBE時に存在確認のために取得したstatusを、監視対象のDataConnectionIdと合わせて返す
+/// Result of Subscribe
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct DataSubscribeResult {
+    pub data_connection_id: DataConnectionId,
+    pub status: DataConnectionStatus,
+}
+
 // JSON Parse用の定義
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct RedirectParams {
@@ -1,3 +1,4 @@
 pub(crate) mod entity;
 pub(crate) mod repository;
+pub(crate) mod service;
 pub(crate) mod value_object;
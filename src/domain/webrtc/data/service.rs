@@ -0,0 +1,320 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream::{self, Stream, StreamExt};
+use serde_json::Value;
+
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+use crate::domain::listener::repository::{EventPublisher, ListenerRegistry};
+use crate::domain::listener::retry::RetryPolicy;
+use crate::domain::webrtc::data::entity::DataConnectionEventEnum;
+use crate::domain::webrtc::data::repository::DataRepository;
+use crate::domain::webrtc::data::value_object::DataConnectionId;
+use crate::error;
+
+// wait_open_timeout_msが省略された場合のtimeout
+const DEFAULT_WAIT_OPEN_TIMEOUT_MS: u64 = 10000;
+
+// CONNECTのparamsから"wait_open", "wait_open_timeout_ms"を取り除き、
+// OPENを待つ場合はそのtimeoutを返す
+// これらはConnectQueryの項目ではないため、deserializeの前に取り除く
+pub(crate) fn take_wait_open(params: &mut Value) -> Option<Duration> {
+    let params = params.as_object_mut()?;
+    let wait_open = params.remove("wait_open").and_then(|value| value.as_bool());
+    let timeout = params
+        .remove("wait_open_timeout_ms")
+        .and_then(|value| value.as_u64())
+        .unwrap_or(DEFAULT_WAIT_OPEN_TIMEOUT_MS);
+    match wait_open {
+        Some(true) => Some(Duration::from_millis(timeout)),
+        _ => None,
+    }
+}
+
+// OPEN eventを受信するまで待機し、受信したOPEN eventを返す
+// OPENの前にCLOSE, ERROR eventを受信した場合と、timeoutまでに受信できなかった場合はエラーを返す
+// eventの取得に一時的に失敗した場合は、監視ループと同様にretryに従って再試行する
+// 後から起動する監視ループと重複してeventを取得しないよう、待機中は監視ループとして記録する
+// 既に監視ループが起動している場合はWebRTC Gatewayから取得せず、そのループが配信するeventを待つ
+// この場合はOPENも配信済みなので、Noneを返す
+pub(crate) async fn wait_open(
+    repository: Arc<dyn DataRepository>,
+    listeners: &dyn ListenerRegistry,
+    publisher: &dyn EventPublisher,
+    retry: &RetryPolicy,
+    data_connection_id: &DataConnectionId,
+    timeout: Duration,
+) -> Result<Option<DataConnectionEventEnum>, error::Error> {
+    let info = ListenerInfo::new(ListenerKind::Data, data_connection_id.as_str());
+    let result = match listeners.start(info) {
+        Some(handle) => {
+            let wait = async {
+                // 停止を指示された古いループが残っている場合は、その終了を待ってから取得を始める
+                handle.wait_previous().await;
+                let events = stream::unfold(repository, |repository| async move {
+                    let event = retry
+                        .call(|| repository.event(data_connection_id))
+                        .await
+                        .map_err(|reason| error::Error::create_local_error(&reason));
+                    Some((event, repository))
+                });
+                wait_event(events, data_connection_id).await
+            };
+            let result = tokio::time::timeout(timeout, wait).await;
+            // 取得を終えたので、監視ループを起動できるようにする
+            listeners.finish_wait(&handle);
+            result.map(|result| result.map(Some))
+        }
+        None => {
+            let events = publisher.subscribe_data(data_connection_id).map(Ok);
+            let wait = wait_event(events, data_connection_id);
+            tokio::time::timeout(timeout, wait)
+                .await
+                .map(|result| result.map(|_| None))
+        }
+    };
+
+    match result {
+        Ok(result) => result,
+        Err(_) => {
+            let message = format!(
+                "DataConnection {} did not become OPEN within {} ms",
+                data_connection_id.as_str(),
+                timeout.as_millis()
+            );
+            Err(error::Error::create_local_error(&message))
+        }
+    }
+}
+
+// 与えられたeventを順に確認し、OPEN eventを返す
+async fn wait_event(
+    events: impl Stream<Item = Result<DataConnectionEventEnum, error::Error>>,
+    data_connection_id: &DataConnectionId,
+) -> Result<DataConnectionEventEnum, error::Error> {
+    futures::pin_mut!(events);
+    while let Some(event) = events.next().await {
+        let message = match event? {
+            DataConnectionEventEnum::OPEN(event) => {
+                return Ok(DataConnectionEventEnum::OPEN(event));
+            }
+            // long pollingのTIMEOUTなので、他のtaskに実行を譲ってから再度eventを取得する
+            DataConnectionEventEnum::TIMEOUT => {
+                tokio::task::yield_now().await;
+                continue;
+            }
+            DataConnectionEventEnum::CLOSE(_) => format!(
+                "DataConnection {} was closed before OPEN",
+                data_connection_id.as_str()
+            ),
+            // OPEN前のERROR eventはユーザに通知されないので、内容をエラーメッセージに含める
+            event => format!(
+                "DataConnection {} failed before OPEN: {:?}",
+                data_connection_id.as_str(),
+                event
+            ),
+        };
+        return Err(error::Error::create_local_error(&message));
+    }
+    let message = format!(
+        "event stream was closed before DataConnection {} became OPEN",
+        data_connection_id.as_str()
+    );
+    Err(error::Error::create_local_error(&message))
+}
+
+#[cfg(test)]
+mod test_wait_open {
+    use crate::domain::listener::entity::ListenerHandle;
+    use crate::domain::listener::repository::{MockEventPublisher, MockListenerRegistry};
+    use crate::domain::webrtc::data::entity::DataConnectionIdWrapper;
+
+    use super::super::repository::MockDataRepository;
+    use super::*;
+
+    fn data_connection_id() -> DataConnectionId {
+        DataConnectionId::try_create("dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c").unwrap()
+    }
+
+    fn open_event() -> DataConnectionEventEnum {
+        DataConnectionEventEnum::OPEN(DataConnectionIdWrapper {
+            data_connection_id: data_connection_id(),
+        })
+    }
+
+    // 監視ループが起動していない場合のMock
+    // 待機中は監視ループとして記録し、終了時に記録を削除する
+    fn listeners() -> MockListenerRegistry {
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_start().times(1).returning(|info| {
+            assert_eq!(
+                info,
+                ListenerInfo::new(ListenerKind::Data, data_connection_id().as_str())
+            );
+            Some(ListenerHandle::new(info, 0))
+        });
+        listeners.expect_finish_wait().times(1).return_const(());
+        listeners
+    }
+
+    #[test]
+    fn take_wait_open_options() {
+        let mut params = serde_json::json!({ "wait_open": true, "peer_id": "peer_id" });
+        assert_eq!(
+            take_wait_open(&mut params),
+            Some(Duration::from_millis(DEFAULT_WAIT_OPEN_TIMEOUT_MS))
+        );
+        // queryの項目ではないので取り除かれる
+        assert_eq!(params, serde_json::json!({ "peer_id": "peer_id" }));
+
+        let mut params = serde_json::json!({ "wait_open": true, "wait_open_timeout_ms": 500 });
+        assert_eq!(
+            take_wait_open(&mut params),
+            Some(Duration::from_millis(500))
+        );
+
+        let mut params = serde_json::json!({ "wait_open_timeout_ms": 500 });
+        assert_eq!(take_wait_open(&mut params), None);
+        assert_eq!(params, serde_json::json!({}));
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        // OPENを返さないMockを生成
+        let mut repository = MockDataRepository::default();
+        repository
+            .expect_event()
+            .returning(move |_| Ok(DataConnectionEventEnum::TIMEOUT));
+
+        let result = wait_open(
+            Arc::new(repository),
+            &listeners(),
+            &MockEventPublisher::default(),
+            &RetryPolicy::default(),
+            &data_connection_id(),
+            Duration::from_millis(10),
+        )
+        .await;
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "DataConnection dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c did not become OPEN within 10 ms"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[tokio::test]
+    async fn open() {
+        let mut repository = MockDataRepository::default();
+        let mut events = vec![open_event(), DataConnectionEventEnum::TIMEOUT];
+        repository
+            .expect_event()
+            .times(2)
+            .returning(move |_| Ok(events.pop().unwrap()));
+        // 自身で取得する場合は、既に起動している監視ループのeventを待たない
+        let mut publisher = MockEventPublisher::default();
+        publisher.expect_subscribe_data().times(0);
+
+        let result = wait_open(
+            Arc::new(repository),
+            &listeners(),
+            &publisher,
+            &RetryPolicy::default(),
+            &data_connection_id(),
+            Duration::from_millis(100),
+        )
+        .await;
+        // 取得したOPENは、呼び出し側で配信できるよう返す
+        assert_eq!(result.unwrap(), Some(open_event()));
+    }
+
+    #[tokio::test]
+    async fn already_listening() {
+        // 既に監視ループが起動している場合は、WebRTC Gatewayから取得しない
+        let mut repository = MockDataRepository::default();
+        repository.expect_event().times(0);
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_start().times(1).returning(|_| None);
+        listeners.expect_finish_wait().times(0);
+        // そのループが配信するeventを待つ
+        let mut publisher = MockEventPublisher::default();
+        publisher.expect_subscribe_data().times(1).returning(|_| {
+            stream::iter(vec![DataConnectionEventEnum::TIMEOUT, open_event()]).boxed()
+        });
+
+        let result = wait_open(
+            Arc::new(repository),
+            &listeners,
+            &publisher,
+            &RetryPolicy::default(),
+            &data_connection_id(),
+            Duration::from_millis(100),
+        )
+        .await;
+        // OPENは監視ループが配信済みなので返さない
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn retry_transient_error() {
+        // 1回目はGatewayの503, 2回目はOPENを返すMockを生成
+        let mut repository = MockDataRepository::default();
+        let mut events = vec![
+            Ok(open_event()),
+            Err(error::Error::create_local_error(
+                "recv invalid response: url: http://localhost:8000/data/connections/dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c/events code: 503 Service Unavailable",
+            )),
+        ];
+        repository
+            .expect_event()
+            .times(2)
+            .returning(move |_| events.pop().unwrap());
+
+        let retry = RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        };
+        let result = wait_open(
+            Arc::new(repository),
+            &listeners(),
+            &MockEventPublisher::default(),
+            &retry,
+            &data_connection_id(),
+            Duration::from_millis(100),
+        )
+        .await;
+        // 一時的なエラーでは待機を止めない
+        assert_eq!(result.unwrap(), Some(open_event()));
+    }
+
+    #[tokio::test]
+    async fn error() {
+        // 再試行しないエラーを返すMockを生成
+        let mut repository = MockDataRepository::default();
+        repository
+            .expect_event()
+            .times(1)
+            .returning(|_| Err(error::Error::create_local_error("recv Not Found")));
+
+        let result = wait_open(
+            Arc::new(repository),
+            &listeners(),
+            &MockEventPublisher::default(),
+            &RetryPolicy::default(),
+            &data_connection_id(),
+            Duration::from_millis(100),
+        )
+        .await;
+        // 再試行しないエラーは、そのまま待機を終える
+        match result {
+            Err(error::Error::LocalError(message)) => assert_eq!(
+                message,
+                "event api returned an error: LocalError(\"recv Not Found\")"
+            ),
+            _ => unreachable!(),
+        }
+    }
+}
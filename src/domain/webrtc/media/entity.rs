@@ -47,6 +47,19 @@ pub struct RtcpIdWrapper {
     pub rtcp_id: RtcpId,
 }
 
+// redirect先のportを自動で割り当てた場合、エンドユーザは割り当てられたportを知る必要があるので、
+// MediaConnectionIdと合わせて返す
+// READYを待つ場合は、メディアの送信先となるmedia socket, rtcp socketのアドレスも返す
+/// Result of Call
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct CallResult {
+    pub media_connection_id: MediaConnectionId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_sockets: Option<RedirectParameters>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recv_sockets: Option<RedirectParameters>,
+}
+
 // skyway-webrtc-gateway crateのAnswerで帰ってきたパラメータにはMediaConnectionIdが含まれない。
 // エンドユーザはMediaConnectionIdが含まれていたほうが便利であると考えられるので、含めた形で再定義する
 /// Result of Answer
@@ -58,3 +71,11 @@ pub struct AnswerResult {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub recv_sockets: Option<RedirectParameters>,
 }
+
+// SUBSCRIBE時に存在確認のために取得したstatusを、監視対象のMediaConnectionIdと合わせて返す
+/// Result of Subscribe
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct MediaSubscribeResult {
+    pub media_connection_id: MediaConnectionId,
+    pub status: MediaConnectionStatus,
+}
@@ -1,3 +1,5 @@
 pub(crate) mod entity;
+pub(crate) mod preset;
 pub(crate) mod repository;
+pub(crate) mod service;
 pub(crate) mod value_object;
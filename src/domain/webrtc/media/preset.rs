@@ -0,0 +1,363 @@
+// CallQuery/AnswerQueryに与えるMediaParamsの典型的な設定をpresetとして定義する
+// また、codec, payload_type, sampling_rateの組み合わせの整合性を
+// SkyWay WebRTC Gatewayに送信する前にチェックする
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::domain::webrtc::media::entity::{Constraints, MediaParams};
+use crate::domain::webrtc::media::value_object::{MediaId, RtcpId};
+use crate::error;
+
+// codecごとの制約
+struct CodecSpec {
+    name: &'static str,
+    is_video: bool,
+    clock_rate: u64,
+    // 静的に割り当てられたpayload typeを持つcodecの場合のみSome
+    static_payload_type: Option<u64>,
+}
+
+const CODECS: [CodecSpec; 7] = [
+    CodecSpec {
+        name: "H264",
+        is_video: true,
+        clock_rate: 90000,
+        static_payload_type: None,
+    },
+    CodecSpec {
+        name: "VP8",
+        is_video: true,
+        clock_rate: 90000,
+        static_payload_type: None,
+    },
+    CodecSpec {
+        name: "VP9",
+        is_video: true,
+        clock_rate: 90000,
+        static_payload_type: None,
+    },
+    CodecSpec {
+        name: "OPUS",
+        is_video: false,
+        clock_rate: 48000,
+        static_payload_type: None,
+    },
+    CodecSpec {
+        name: "PCMU",
+        is_video: false,
+        clock_rate: 8000,
+        static_payload_type: Some(0),
+    },
+    CodecSpec {
+        name: "PCMA",
+        is_video: false,
+        clock_rate: 8000,
+        static_payload_type: Some(8),
+    },
+    // RFC3551の規定により、G722のRTP clock rateは8000である
+    CodecSpec {
+        name: "G722",
+        is_video: false,
+        clock_rate: 8000,
+        static_payload_type: Some(9),
+    },
+];
+
+// 動的に割り当てられるpayload typeの範囲
+const DYNAMIC_PAYLOAD_TYPES: std::ops::RangeInclusive<u64> = 96..=127;
+
+fn find_codec(codec: &str) -> Option<&'static CodecSpec> {
+    CODECS
+        .iter()
+        .find(|spec| spec.name.eq_ignore_ascii_case(codec))
+}
+
+/// Named presets of MediaParams
+#[allow(non_camel_case_types)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaPreset {
+    H264_360p,
+    H264_720p,
+    H264_1080p,
+    VP8_low,
+    VP8_high,
+    VP9_720p,
+    OPUS_mono,
+    OPUS_stereo,
+    PCMU,
+    PCMA,
+    G722,
+}
+
+// presetから展開される値
+struct PresetSpec {
+    codec: &'static str,
+    band_width: u64,
+    payload_type: u64,
+    sampling_rate: u64,
+}
+
+impl MediaPreset {
+    fn spec(&self) -> PresetSpec {
+        let (codec, band_width, payload_type, sampling_rate) = match self {
+            MediaPreset::H264_360p => ("H264", 600, 100, 90000),
+            MediaPreset::H264_720p => ("H264", 1500, 100, 90000),
+            MediaPreset::H264_1080p => ("H264", 4000, 100, 90000),
+            MediaPreset::VP8_low => ("VP8", 300, 96, 90000),
+            MediaPreset::VP8_high => ("VP8", 1500, 96, 90000),
+            MediaPreset::VP9_720p => ("VP9", 1500, 98, 90000),
+            MediaPreset::OPUS_mono => ("OPUS", 32, 111, 48000),
+            MediaPreset::OPUS_stereo => ("OPUS", 128, 111, 48000),
+            MediaPreset::PCMU => ("PCMU", 64, 0, 8000),
+            MediaPreset::PCMA => ("PCMA", 64, 8, 8000),
+            MediaPreset::G722 => ("G722", 64, 9, 8000),
+        };
+        PresetSpec {
+            codec,
+            band_width,
+            payload_type,
+            sampling_rate,
+        }
+    }
+
+    /// Returns true if the preset is for video
+    pub fn is_video(&self) -> bool {
+        find_codec(self.spec().codec)
+            .map(|codec| codec.is_video)
+            .unwrap_or(false)
+    }
+
+    /// Create MediaParams from the preset
+    pub fn media_params(&self, media_id: MediaId, rtcp_id: Option<RtcpId>) -> MediaParams {
+        let spec = self.spec();
+        MediaParams {
+            band_width: spec.band_width as _,
+            codec: spec.codec.to_string(),
+            media_id,
+            rtcp_id,
+            payload_type: Some(spec.payload_type as _),
+            sampling_rate: Some(spec.sampling_rate as _),
+        }
+    }
+}
+
+// JSONのconstraints内のvideo_params, audio_paramsに"preset"が含まれている場合、MediaParamsの値として展開する
+// ユーザが明示的に与えた値はpresetの値より優先する
+pub(crate) fn expand_presets(constraints: &mut Value) -> Result<(), error::Error> {
+    for key in ["video_params", "audio_params"] {
+        let params = match constraints.get_mut(key).and_then(Value::as_object_mut) {
+            Some(params) => params,
+            None => continue,
+        };
+        let preset = match params.remove("preset") {
+            Some(preset) => preset,
+            None => continue,
+        };
+        let preset = serde_json::from_value::<MediaPreset>(preset)
+            .map_err(|e| error::Error::SerdeError { error: e })?;
+        let spec = preset.spec();
+        params.entry("codec").or_insert(json!(spec.codec));
+        params.entry("band_width").or_insert(json!(spec.band_width));
+        params
+            .entry("payload_type")
+            .or_insert(json!(spec.payload_type));
+        params
+            .entry("sampling_rate")
+            .or_insert(json!(spec.sampling_rate));
+    }
+    Ok(())
+}
+
+// MediaParamsの値の不整合を列挙する
+fn check_media_params(params: &MediaParams, is_video: bool) -> Vec<String> {
+    let kind = if is_video {
+        "video_params"
+    } else {
+        "audio_params"
+    };
+    let codec = match find_codec(&params.codec) {
+        Some(codec) => codec,
+        // 未知のcodecの判断はSkyWay WebRTC Gatewayに任せる
+        None => return vec![],
+    };
+
+    let mut problems = vec![];
+    if codec.is_video != is_video {
+        problems.push(format!(
+            "{}: {} is not a {} codec",
+            kind,
+            codec.name,
+            if is_video { "video" } else { "audio" }
+        ));
+    }
+    if let Some(payload_type) = params.payload_type {
+        let payload_type = payload_type as u64;
+        match codec.static_payload_type {
+            Some(expected) if payload_type != expected => problems.push(format!(
+                "{}: payload_type of {} must be {}, but {} was given",
+                kind, codec.name, expected, payload_type
+            )),
+            None if !DYNAMIC_PAYLOAD_TYPES.contains(&payload_type) => problems.push(format!(
+                "{}: payload_type of {} must be a dynamic payload type(96-127), but {} was given",
+                kind, codec.name, payload_type
+            )),
+            _ => {}
+        }
+    }
+    if let Some(sampling_rate) = params.sampling_rate {
+        let sampling_rate = sampling_rate as u64;
+        if sampling_rate != codec.clock_rate {
+            problems.push(format!(
+                "{}: sampling_rate of {} must be {}, but {} was given",
+                kind, codec.name, codec.clock_rate, sampling_rate
+            ));
+        }
+    }
+    problems
+}
+
+/// Check consistency of codec, payload_type and sampling_rate in Constraints
+pub fn validate_constraints(constraints: &Constraints) -> Result<(), error::Error> {
+    let mut problems = vec![];
+    if let Some(ref params) = constraints.video_params {
+        problems.append(&mut check_media_params(params, true));
+    }
+    if let Some(ref params) = constraints.audio_params {
+        problems.append(&mut check_media_params(params, false));
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        let message = format!("invalid constraints: {}", problems.join(", "));
+        Err(error::Error::create_local_error(&message))
+    }
+}
+
+#[cfg(test)]
+mod test_preset {
+    use crate::domain::webrtc::common::value_object::SerializableId;
+
+    use super::*;
+
+    fn media_id() -> MediaId {
+        MediaId::try_create("vi-4d053831-5dc2-461b-a358-d062d6115216").unwrap()
+    }
+
+    fn constraints(
+        video_params: Option<MediaParams>,
+        audio_params: Option<MediaParams>,
+    ) -> Constraints {
+        Constraints {
+            video: video_params.is_some(),
+            videoReceiveEnabled: None,
+            audio: audio_params.is_some(),
+            audioReceiveEnabled: None,
+            video_params,
+            audio_params,
+            metadata: None,
+        }
+    }
+
+    // 全てのpresetは自身の検証を通過する
+    #[test]
+    fn presets_are_valid() {
+        let presets = [
+            MediaPreset::H264_360p,
+            MediaPreset::H264_720p,
+            MediaPreset::H264_1080p,
+            MediaPreset::VP8_low,
+            MediaPreset::VP8_high,
+            MediaPreset::VP9_720p,
+            MediaPreset::OPUS_mono,
+            MediaPreset::OPUS_stereo,
+            MediaPreset::PCMU,
+            MediaPreset::PCMA,
+            MediaPreset::G722,
+        ];
+        for preset in presets {
+            let params = preset.media_params(media_id(), None);
+            let constraints = if preset.is_video() {
+                constraints(Some(params), None)
+            } else {
+                constraints(None, Some(params))
+            };
+            assert!(validate_constraints(&constraints).is_ok());
+        }
+    }
+
+    #[test]
+    fn expand_preset_from_json() {
+        let mut value = serde_json::json!({
+            "video": true,
+            "audio": true,
+            "video_params": {
+                "preset": "h264_720p",
+                "media_id": "vi-4d053831-5dc2-461b-a358-d062d6115216",
+                // 明示的に与えられた値が優先される
+                "band_width": 1000
+            },
+            "audio_params": {
+                "preset": "opus_stereo",
+                "media_id": "au-4d053831-5dc2-461b-a358-d062d6115216"
+            }
+        });
+        expand_presets(&mut value).unwrap();
+        let constraints = serde_json::from_value::<Constraints>(value).unwrap();
+
+        let video_params = constraints.video_params.unwrap();
+        assert_eq!(video_params.codec, "H264");
+        assert_eq!(video_params.band_width as u64, 1000);
+        assert_eq!(video_params.payload_type.map(|pt| pt as u64), Some(100));
+        let audio_params = constraints.audio_params.unwrap();
+        assert_eq!(audio_params.codec, "OPUS");
+        assert_eq!(
+            audio_params.sampling_rate.map(|rate| rate as u64),
+            Some(48000)
+        );
+    }
+
+    #[test]
+    fn unknown_preset() {
+        let mut value = serde_json::json!({
+            "video_params": {
+                "preset": "h264_8k",
+            }
+        });
+        if let Err(error::Error::SerdeError { error: _ }) = expand_presets(&mut value) {
+            assert!(true);
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn inconsistent_params() {
+        // OPUSにPCMUのpayload typeとsampling rateを与え、さらにvideo_paramsに入れてしまったケース
+        let mut params = MediaPreset::OPUS_stereo.media_params(media_id(), None);
+        params.payload_type = Some(0);
+        params.sampling_rate = Some(8000);
+        let result = validate_constraints(&constraints(Some(params), None));
+
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "invalid constraints: video_params: OPUS is not a video codec, \
+                 video_params: payload_type of OPUS must be a dynamic payload type(96-127), but 0 was given, \
+                 video_params: sampling_rate of OPUS must be 48000, but 8000 was given"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn unknown_codec_is_not_checked() {
+        let mut params = MediaPreset::H264_720p.media_params(media_id(), None);
+        params.codec = "AV1".to_string();
+        params.payload_type = Some(45);
+        assert!(validate_constraints(&constraints(Some(params), None)).is_ok());
+    }
+}
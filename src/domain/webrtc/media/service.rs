@@ -0,0 +1,329 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream::{self, Stream, StreamExt};
+use serde_json::Value;
+
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+use crate::domain::listener::repository::{EventPublisher, ListenerRegistry};
+use crate::domain::listener::retry::RetryPolicy;
+use crate::domain::webrtc::media::entity::MediaConnectionEventEnum;
+use crate::domain::webrtc::media::repository::MediaRepository;
+use crate::domain::webrtc::media::value_object::MediaConnectionId;
+use crate::error;
+
+// wait_ready_timeout_msが省略された場合のtimeout
+const DEFAULT_WAIT_READY_TIMEOUT_MS: u64 = 10000;
+
+// CALL, ANSWERのparamsから"wait_ready", "wait_ready_timeout_ms"を取り除き、
+// READYを待つ場合はそのtimeoutを返す
+// これらはSkyWay WebRTC Gatewayに渡すqueryの項目ではないため、deserializeの前に取り除く
+pub(crate) fn take_wait_ready(params: &mut Value) -> Option<Duration> {
+    let params = params.as_object_mut()?;
+    let wait_ready = params
+        .remove("wait_ready")
+        .and_then(|value| value.as_bool());
+    let timeout = params
+        .remove("wait_ready_timeout_ms")
+        .and_then(|value| value.as_u64())
+        .unwrap_or(DEFAULT_WAIT_READY_TIMEOUT_MS);
+    match wait_ready {
+        Some(true) => Some(Duration::from_millis(timeout)),
+        _ => None,
+    }
+}
+
+// READYもしくはSTREAM eventを受信し、メディアの送受信が可能になるまで待機する
+// 受信したREADY, STREAM eventを返す
+// CLOSE, ERROR eventを受信した場合と、timeoutまでに受信できなかった場合はエラーを返す
+// eventの取得に一時的に失敗した場合は、監視ループと同様にretryに従って再試行する
+// 後から起動する監視ループと重複してeventを取得しないよう、待機中は監視ループとして記録する
+// 既に監視ループが起動している場合(相手側からのCALLを監視している場合など)はWebRTC Gatewayから取得せず、
+// そのループが配信するeventを待つ. この場合はREADYも配信済みなので、Noneを返す
+pub(crate) async fn wait_ready(
+    repository: Arc<dyn MediaRepository>,
+    listeners: &dyn ListenerRegistry,
+    publisher: &dyn EventPublisher,
+    retry: &RetryPolicy,
+    media_connection_id: &MediaConnectionId,
+    timeout: Duration,
+) -> Result<Option<MediaConnectionEventEnum>, error::Error> {
+    let info = ListenerInfo::new(ListenerKind::Media, media_connection_id.as_str());
+    let result = match listeners.start(info) {
+        Some(handle) => {
+            let wait = async {
+                // 停止を指示された古いループが残っている場合は、その終了を待ってから取得を始める
+                handle.wait_previous().await;
+                let events = stream::unfold(repository, |repository| async move {
+                    let event = retry
+                        .call(|| repository.event(media_connection_id))
+                        .await
+                        .map_err(|reason| error::Error::create_local_error(&reason));
+                    Some((event, repository))
+                });
+                wait_event(events, media_connection_id).await
+            };
+            let result = tokio::time::timeout(timeout, wait).await;
+            // 取得を終えたので、監視ループを起動できるようにする
+            listeners.finish_wait(&handle);
+            result.map(|result| result.map(Some))
+        }
+        None => {
+            let events = publisher.subscribe_media(media_connection_id).map(Ok);
+            let wait = wait_event(events, media_connection_id);
+            tokio::time::timeout(timeout, wait)
+                .await
+                .map(|result| result.map(|_| None))
+        }
+    };
+
+    match result {
+        Ok(result) => result,
+        Err(_) => {
+            let message = format!(
+                "MediaConnection {} did not become READY within {} ms",
+                media_connection_id.as_str(),
+                timeout.as_millis()
+            );
+            Err(error::Error::create_local_error(&message))
+        }
+    }
+}
+
+// 与えられたeventを順に確認し、READYもしくはSTREAM eventを返す
+async fn wait_event(
+    events: impl Stream<Item = Result<MediaConnectionEventEnum, error::Error>>,
+    media_connection_id: &MediaConnectionId,
+) -> Result<MediaConnectionEventEnum, error::Error> {
+    futures::pin_mut!(events);
+    while let Some(event) = events.next().await {
+        let message = match event? {
+            event @ MediaConnectionEventEnum::READY(_)
+            | event @ MediaConnectionEventEnum::STREAM(_) => {
+                return Ok(event);
+            }
+            // long pollingのTIMEOUTなので、他のtaskに実行を譲ってから再度eventを取得する
+            MediaConnectionEventEnum::TIMEOUT => {
+                tokio::task::yield_now().await;
+                continue;
+            }
+            MediaConnectionEventEnum::CLOSE(_) => format!(
+                "MediaConnection {} was closed before READY",
+                media_connection_id.as_str()
+            ),
+            event => format!(
+                "MediaConnection {} failed before READY: {:?}",
+                media_connection_id.as_str(),
+                event
+            ),
+        };
+        return Err(error::Error::create_local_error(&message));
+    }
+    let message = format!(
+        "event stream was closed before MediaConnection {} became READY",
+        media_connection_id.as_str()
+    );
+    Err(error::Error::create_local_error(&message))
+}
+
+#[cfg(test)]
+mod test_wait_ready {
+    use std::sync::Mutex;
+
+    use crate::domain::listener::entity::ListenerHandle;
+    use crate::domain::listener::repository::{MockEventPublisher, MockListenerRegistry};
+
+    use super::super::entity::MediaConnectionIdWrapper;
+    use super::super::repository::MockMediaRepository;
+    use super::*;
+
+    fn media_connection_id() -> MediaConnectionId {
+        MediaConnectionId::try_create("mc-102127d9-30de-413b-93f7-41a33e39d82d").unwrap()
+    }
+
+    fn ready_event() -> MediaConnectionEventEnum {
+        MediaConnectionEventEnum::READY(MediaConnectionIdWrapper {
+            media_connection_id: media_connection_id(),
+        })
+    }
+
+    // 監視ループが起動していない場合のMock
+    // 待機中は監視ループとして記録し、終了時に記録を削除する
+    fn listeners() -> MockListenerRegistry {
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_start().times(1).returning(|info| {
+            assert_eq!(
+                info,
+                ListenerInfo::new(ListenerKind::Media, media_connection_id().as_str())
+            );
+            Some(ListenerHandle::new(info, 0))
+        });
+        listeners.expect_finish_wait().times(1).return_const(());
+        listeners
+    }
+
+    #[test]
+    fn take_wait_ready_options() {
+        let mut params = serde_json::json!({ "wait_ready": true, "peer_id": "peer_id" });
+        assert_eq!(
+            take_wait_ready(&mut params),
+            Some(Duration::from_millis(DEFAULT_WAIT_READY_TIMEOUT_MS))
+        );
+        // queryの項目ではないので取り除かれる
+        assert_eq!(params, serde_json::json!({ "peer_id": "peer_id" }));
+
+        let mut params = serde_json::json!({ "wait_ready": true, "wait_ready_timeout_ms": 500 });
+        assert_eq!(
+            take_wait_ready(&mut params),
+            Some(Duration::from_millis(500))
+        );
+
+        let mut params = serde_json::json!({ "wait_ready_timeout_ms": 500 });
+        assert_eq!(take_wait_ready(&mut params), None);
+        assert_eq!(params, serde_json::json!({}));
+    }
+
+    #[tokio::test]
+    async fn ready_after_timeout_event() {
+        // 1回目はlong pollingのTIMEOUT, 2回目はREADYを返すMockを生成
+        let counter = Mutex::new(0u8);
+        let mut repository = MockMediaRepository::default();
+        repository.expect_event().returning(move |_| {
+            let mut count = counter.lock().unwrap();
+            *count += 1;
+            if *count == 1 {
+                Ok(MediaConnectionEventEnum::TIMEOUT)
+            } else {
+                Ok(ready_event())
+            }
+        });
+
+        let result = wait_ready(
+            Arc::new(repository),
+            &listeners(),
+            &MockEventPublisher::default(),
+            &RetryPolicy::default(),
+            &media_connection_id(),
+            Duration::from_secs(1),
+        )
+        .await;
+        // 取得したREADYは、呼び出し側で配信できるよう返す
+        assert_eq!(result.unwrap(), Some(ready_event()));
+    }
+
+    #[tokio::test]
+    async fn closed() {
+        let mut repository = MockMediaRepository::default();
+        repository.expect_event().returning(move |_| {
+            Ok(MediaConnectionEventEnum::CLOSE(MediaConnectionIdWrapper {
+                media_connection_id: media_connection_id(),
+            }))
+        });
+
+        let result = wait_ready(
+            Arc::new(repository),
+            &listeners(),
+            &MockEventPublisher::default(),
+            &RetryPolicy::default(),
+            &media_connection_id(),
+            Duration::from_secs(1),
+        )
+        .await;
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "MediaConnection mc-102127d9-30de-413b-93f7-41a33e39d82d was closed before READY"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        // READYを返さないMockを生成
+        let mut repository = MockMediaRepository::default();
+        repository
+            .expect_event()
+            .returning(move |_| Ok(MediaConnectionEventEnum::TIMEOUT));
+
+        let result = wait_ready(
+            Arc::new(repository),
+            &listeners(),
+            &MockEventPublisher::default(),
+            &RetryPolicy::default(),
+            &media_connection_id(),
+            Duration::from_millis(10),
+        )
+        .await;
+        if let Err(error::Error::LocalError(message)) = result {
+            assert_eq!(
+                message,
+                "MediaConnection mc-102127d9-30de-413b-93f7-41a33e39d82d did not become READY within 10 ms"
+            );
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[tokio::test]
+    async fn already_listening() {
+        // 相手側からのCALLを既に監視している場合は、WebRTC Gatewayから取得しない
+        let mut repository = MockMediaRepository::default();
+        repository.expect_event().times(0);
+        let mut listeners = MockListenerRegistry::default();
+        listeners.expect_start().times(1).returning(|_| None);
+        listeners.expect_finish_wait().times(0);
+        // そのループが配信するeventを待つ
+        let mut publisher = MockEventPublisher::default();
+        publisher
+            .expect_subscribe_media()
+            .times(1)
+            .returning(|_| stream::iter(vec![ready_event()]).boxed());
+
+        let result = wait_ready(
+            Arc::new(repository),
+            &listeners,
+            &publisher,
+            &RetryPolicy::default(),
+            &media_connection_id(),
+            Duration::from_secs(1),
+        )
+        .await;
+        // READYは監視ループが配信済みなので返さない
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn retry_transient_error() {
+        // 1回目はGatewayの503, 2回目はREADYを返すMockを生成
+        let mut repository = MockMediaRepository::default();
+        let mut events = vec![
+            Ok(ready_event()),
+            Err(error::Error::create_local_error(
+                "recv invalid response: url: http://localhost:8000/media/connections/mc-50a32bab-b3d9-4913-8e20-f79c90a6a211/events code: 503 Service Unavailable",
+            )),
+        ];
+        repository
+            .expect_event()
+            .times(2)
+            .returning(move |_| events.pop().unwrap());
+
+        let retry = RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        };
+        let result = wait_ready(
+            Arc::new(repository),
+            &listeners(),
+            &MockEventPublisher::default(),
+            &retry,
+            &media_connection_id(),
+            Duration::from_millis(100),
+        )
+        .await;
+        // 一時的なエラーでは待機を止めない
+        assert_eq!(result.unwrap(), Some(ready_event()));
+    }
+}
@@ -1 +1,163 @@
 pub use skyway_webrtc_gateway_api::error::Error;
+
+use serde::Deserialize;
+
+/// Category of errors. Front-ends use it to choose their own status codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The request was rejected by this crate or by WebRTC Gateway (400)
+    InvalidRequest,
+    /// WebRTC Gateway returned 403
+    Forbidden,
+    /// The resource does not exist (404)
+    NotFound,
+    /// WebRTC Gateway could not be reached, or returned 5xx or 408. Retrying may succeed
+    Unavailable,
+    /// WebRTC Gateway returned an unexpected response
+    BadGateway,
+}
+
+// session::callでcrateが停止している場合に返すメッセージ
+pub(crate) const STOPPED_MESSAGE: &str = "skyway webrtc gateway caller has stopped";
+
+impl ErrorKind {
+    /// Classify an error returned from WebRTC Gateway or this crate
+    pub fn from_error(error: &Error) -> Self {
+        match error {
+            Error::LocalError(message) => Self::from_local_error(message),
+            Error::ReqwestError(_) => ErrorKind::Unavailable,
+            Error::SerdeError { .. } | Error::AddrParseError(_) => ErrorKind::InvalidRequest,
+            Error::IOError { .. } | Error::Utf8Error { .. } => ErrorKind::BadGateway,
+        }
+    }
+
+    /// Classify the content of `ResponseResult::Error`
+    pub fn from_response(message: &str) -> Self {
+        // UseCaseのエラーは、skyway-webrtc-gateway-apiのErrorをシリアライズしたJSONとして返される
+        #[derive(Deserialize)]
+        struct SerializedError {
+            reason: String,
+            message: String,
+        }
+
+        match serde_json::from_str::<SerializedError>(message) {
+            Ok(error) => match error.reason.as_str() {
+                "InternalError" => Self::from_local_error(&error.message),
+                "NetworkError" => ErrorKind::Unavailable,
+                "JsonError" | "InvalidAddressError" => ErrorKind::InvalidRequest,
+                _ => ErrorKind::BadGateway,
+            },
+            Err(_) if message == STOPPED_MESSAGE => ErrorKind::Unavailable,
+            // それ以外は、与えられたJSONの解析にこのcrateが失敗した場合である
+            Err(_) => ErrorKind::InvalidRequest,
+        }
+    }
+
+    // skyway-webrtc-gateway-apiのcommon/api.rsは、Gatewayのstatus codeを以下のLocalErrorに変換する
+    // 400: "recv message\n{errors}"
+    // 403: "recv Forbidden"
+    // 404: "recv Not Found" (404を想定しないAPIでは、その他のcodeとして扱われる)
+    // 405: "recv Method Not Allowed"
+    // 406: "recv Not Acceptable"
+    // 408: "recv RequestTimeout"
+    // その他: "recv invalid response: url: {url} code: {status}"
+    // "recv "で始まらないLocalErrorは、パラメータの検証に失敗した結果である
+    fn from_local_error(message: &str) -> Self {
+        match message {
+            "recv Forbidden" => ErrorKind::Forbidden,
+            "recv Not Found" => ErrorKind::NotFound,
+            "recv RequestTimeout" => ErrorKind::Unavailable,
+            "recv Method Not Allowed" | "recv Not Acceptable" => ErrorKind::BadGateway,
+            message if message.starts_with("recv message") => ErrorKind::InvalidRequest,
+            message if message.starts_with("recv invalid response") => message
+                .rsplit_once(" code: ")
+                .and_then(|(_, status)| status.split(' ').next())
+                .and_then(|code| code.parse::<u16>().ok())
+                .map(Self::from_status_code)
+                .unwrap_or(ErrorKind::BadGateway),
+            _ => ErrorKind::InvalidRequest,
+        }
+    }
+
+    fn from_status_code(code: u16) -> Self {
+        match code {
+            400 => ErrorKind::InvalidRequest,
+            403 => ErrorKind::Forbidden,
+            404 => ErrorKind::NotFound,
+            408 | 500..=599 => ErrorKind::Unavailable,
+            _ => ErrorKind::BadGateway,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_error_kind {
+    use super::*;
+
+    // skyway-webrtc-gateway-apiが生成するLocalErrorのメッセージ
+    const GATEWAY_ERRORS: &[(&str, ErrorKind)] = &[
+        ("recv message\ninvalid peer_id", ErrorKind::InvalidRequest),
+        ("recv Forbidden", ErrorKind::Forbidden),
+        ("recv Not Found", ErrorKind::NotFound),
+        ("recv Method Not Allowed", ErrorKind::BadGateway),
+        ("recv Not Acceptable", ErrorKind::BadGateway),
+        ("recv RequestTimeout", ErrorKind::Unavailable),
+        (
+            "recv invalid response: url: http://localhost:8000/data/connections/dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c/events code: 503 Service Unavailable",
+            ErrorKind::Unavailable,
+        ),
+        (
+            "recv invalid response: url: http://localhost:8000/peers/peer_id/events?token=pt-9749250e-d157-4f80-9ee2-359ce8524308 code: 500 Internal Server Error",
+            ErrorKind::Unavailable,
+        ),
+        (
+            "recv invalid response: url: http://localhost:8000/data/connections/dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c code: 404 Not Found",
+            ErrorKind::NotFound,
+        ),
+        (
+            "recv invalid response: url: http://localhost:8000/media code: 409 Conflict",
+            ErrorKind::BadGateway,
+        ),
+        ("media socket has no media_id", ErrorKind::InvalidRequest),
+    ];
+
+    #[test]
+    fn local_error() {
+        for (message, kind) in GATEWAY_ERRORS {
+            let error = Error::create_local_error(message);
+            assert_eq!(ErrorKind::from_error(&error), *kind, "{}", message);
+        }
+    }
+
+    #[test]
+    fn serde_error() {
+        let error = serde_json::from_str::<serde_json::Value>("invalid json").unwrap_err();
+        let error = Error::SerdeError { error };
+        assert_eq!(ErrorKind::from_error(&error), ErrorKind::InvalidRequest);
+    }
+
+    #[tokio::test]
+    async fn network_error() {
+        // Gatewayに到達できない場合
+        let error = reqwest::get("http://localhost:0").await.unwrap_err();
+        let error = Error::ReqwestError(error);
+        assert_eq!(ErrorKind::from_error(&error), ErrorKind::Unavailable);
+    }
+
+    #[test]
+    fn response() {
+        for (message, kind) in GATEWAY_ERRORS {
+            // UseCaseが返すResponseResult::Errorの中身
+            let error = serde_json::to_string(&Error::create_local_error(message)).unwrap();
+            assert_eq!(ErrorKind::from_response(&error), *kind, "{}", message);
+        }
+
+        let error = serde_json::from_str::<serde_json::Value>("invalid json").unwrap_err();
+        let error = serde_json::to_string(&Error::SerdeError { error }).unwrap();
+        assert_eq!(ErrorKind::from_response(&error), ErrorKind::InvalidRequest);
+        assert_eq!(
+            ErrorKind::from_response(STOPPED_MESSAGE),
+            ErrorKind::Unavailable
+        );
+    }
+}
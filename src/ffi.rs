@@ -0,0 +1,324 @@
+// C ABIを通じて、Rust以外のプログラムからこのcrateを利用するためのインターフェース
+// 宣言はinclude/skyway_caller.hにある
+//
+// handleは自身のtokio runtimeを保持し、crateの処理は全てそのruntime上で行う。
+// 呼び出し側はruntimeを意識せず、同期的な関数とcallbackのみで操作できる。
+//
+// メモリの所有権は以下の通り
+// - 引数として与えられる文字列は呼び出し側が所有する。この関数群は関数から戻った後に参照しない
+// - callbackに与えられる文字列はこのライブラリが所有し、callbackの実行中のみ有効である
+// - poll_eventに与えられるbufferは呼び出し側が所有する。このライブラリはlenの範囲内に書き込むのみである
+// - handleはskyway_caller_startが確保し、skyway_caller_stopが解放する
+// - user_dataは呼び出し側が所有する。このライブラリはcallbackにそのまま渡すのみで、参照しない
+//
+// panicがC側に伝播すると未定義動作となるので、各関数はpanicを捕捉してエラーの戻り値に変換する
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TryRecvError;
+
+use crate::session::{self, CommandSender};
+
+/// Callback receiving the result of `skyway_caller_send` and the `user_data` given to it.
+/// The string is owned by this library and is valid only while the callback runs.
+/// It is invoked on a worker thread of the runtime owned by the handle.
+pub type SkywayCallerCallback =
+    Option<unsafe extern "C" fn(response: *const c_char, user_data: *mut c_void)>;
+
+/// Returned from `skyway_caller_poll_event` when the handle is invalid, no more events will arrive,
+/// or the library failed internally.
+/// It is outside the range of the negated buffer size returned when the buffer is too small.
+pub const SKYWAY_CALLER_CLOSED: i64 = i64::MIN;
+
+/// Returned from `skyway_caller_send` when the library failed internally
+pub const SKYWAY_CALLER_INTERNAL_ERROR: i32 = -2;
+
+// skyway_caller_stop時に、実行中のtaskの終了を待つ時間
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Handle of a running instance. It is opaque for C programs
+pub struct SkywayCaller {
+    runtime: Runtime,
+    message_tx: CommandSender,
+    events: Mutex<Events>,
+}
+
+struct Events {
+    receiver: mpsc::Receiver<String>,
+    // bufferが小さく渡せなかったevent. 次のpollで改めて渡す
+    pending: Option<CString>,
+}
+
+// callbackを実行するworker threadへ渡すためのwrapper
+// ポインタの指す先の扱いは呼び出し側の責任であり、このライブラリは参照しない
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+// 処理中にpanicした場合は、on_panicを返す
+fn catch_panic<T>(on_panic: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+// serde_jsonはNULをエスケープするので、JSONメッセージの変換に失敗することはない
+fn to_c_string(message: String) -> CString {
+    CString::new(message).unwrap_or_default()
+}
+
+/// Start WebRTC Gateway operation with its own tokio runtime.
+/// It returns NULL when `base_url` is NULL or not UTF-8, or the runtime cannot be created.
+///
+/// # Safety
+/// `base_url` must be NULL or a pointer to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn skyway_caller_start(base_url: *const c_char) -> *mut SkywayCaller {
+    catch_panic(ptr::null_mut(), || {
+        if base_url.is_null() {
+            return ptr::null_mut();
+        }
+        let base_url = match CStr::from_ptr(base_url).to_str() {
+            Ok(base_url) => base_url,
+            Err(_) => return ptr::null_mut(),
+        };
+        let runtime = match Runtime::new() {
+            Ok(runtime) => runtime,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        let (message_tx, receiver) = runtime.block_on(crate::run(base_url));
+        let caller = SkywayCaller {
+            runtime,
+            message_tx,
+            events: Mutex::new(Events {
+                receiver,
+                pending: None,
+            }),
+        };
+        Box::into_raw(Box::new(caller))
+    })
+}
+
+/// Give a JSON message for operation. It returns immediately with 0, or -1 when the arguments are invalid.
+/// The result is passed to `callback` later together with `user_data`. `callback` may be NULL to ignore the result.
+/// It returns `SKYWAY_CALLER_INTERNAL_ERROR` when the library failed internally.
+///
+/// # Safety
+/// `handle` must be NULL or a pointer returned from `skyway_caller_start` and not yet stopped.
+/// `json` must be NULL or a pointer to a NUL-terminated string.
+/// `user_data` must be valid until `callback` is invoked, since it may be used on another thread.
+#[no_mangle]
+pub unsafe extern "C" fn skyway_caller_send(
+    handle: *const SkywayCaller,
+    json: *const c_char,
+    callback: SkywayCallerCallback,
+    user_data: *mut c_void,
+) -> i32 {
+    catch_panic(SKYWAY_CALLER_INTERNAL_ERROR, || {
+        let caller = match handle.as_ref() {
+            Some(caller) => caller,
+            None => return -1,
+        };
+        if json.is_null() {
+            return -1;
+        }
+        // 呼び出し側のbufferは関数から戻った後に参照できないので、ここでコピーする
+        let message = match CStr::from_ptr(json).to_str() {
+            Ok(message) => message.to_string(),
+            Err(_) => return -1,
+        };
+
+        let message_tx = caller.message_tx.clone();
+        let user_data = UserData(user_data);
+        caller.runtime.spawn(async move {
+            let response = to_c_string(session::call(&message_tx, message).await);
+            if let Some(callback) = callback {
+                callback(response.as_ptr(), user_data.0);
+            }
+        });
+        0
+    })
+}
+
+/// Take an event without blocking and copy it into `buf` as a NUL-terminated string.
+/// It returns the length of the event excluding NUL, or 0 when there is no event.
+/// When `buf` is too small, it returns the negated buffer size required including NUL and keeps the event for the next call.
+/// It returns `SKYWAY_CALLER_CLOSED` when the handle is NULL, no more events will arrive, or the library failed internally.
+///
+/// # Safety
+/// `handle` must be NULL or a pointer returned from `skyway_caller_start` and not yet stopped.
+/// `buf` must be NULL or writable for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn skyway_caller_poll_event(
+    handle: *const SkywayCaller,
+    buf: *mut c_char,
+    len: usize,
+) -> i64 {
+    catch_panic(SKYWAY_CALLER_CLOSED, || {
+        let caller = match handle.as_ref() {
+            Some(caller) => caller,
+            None => return SKYWAY_CALLER_CLOSED,
+        };
+        let mut events = caller.events.lock().unwrap();
+        let event = match events.pending.take() {
+            Some(event) => event,
+            None => match events.receiver.try_recv() {
+                Ok(event) => to_c_string(event),
+                Err(TryRecvError::Empty) => return 0,
+                Err(TryRecvError::Disconnected) => return SKYWAY_CALLER_CLOSED,
+            },
+        };
+
+        let bytes = event.as_bytes_with_nul();
+        if buf.is_null() || bytes.len() > len {
+            let required = bytes.len() as i64;
+            events.pending = Some(event);
+            return -required;
+        }
+        ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, buf, bytes.len());
+        (bytes.len() - 1) as i64
+    })
+}
+
+/// Stop the operation and release the handle. Callbacks of unfinished `skyway_caller_send` may not be invoked.
+///
+/// # Safety
+/// `handle` must be NULL or a pointer returned from `skyway_caller_start`, and must not be used after this call.
+/// It must not be called inside a callback.
+#[no_mangle]
+pub unsafe extern "C" fn skyway_caller_stop(handle: *mut SkywayCaller) {
+    if handle.is_null() {
+        return;
+    }
+    let caller = *Box::from_raw(handle);
+    catch_panic((), move || {
+        let SkywayCaller {
+            runtime,
+            message_tx,
+            events,
+        } = caller;
+        // Senderを破棄するとcrateの処理ループが終了する
+        drop(message_tx);
+        drop(events);
+        runtime.shutdown_timeout(SHUTDOWN_TIMEOUT);
+    })
+}
+
+#[cfg(test)]
+mod test_ffi {
+    use super::*;
+    use crate::application::dto::response_message::ResponseResult;
+
+    unsafe extern "C" fn callback(response: *const c_char, user_data: *mut c_void) {
+        let response = CStr::from_ptr(response).to_string_lossy().into_owned();
+        let responses = &*(user_data as *const Mutex<Vec<String>>);
+        responses.lock().unwrap().push(response);
+    }
+
+    #[test]
+    fn send_and_poll() {
+        unsafe {
+            let handle = skyway_caller_start(b"http://localhost:8000\0".as_ptr() as *const c_char);
+            assert!(!handle.is_null());
+
+            // 不正なJSONに対しては、callbackでエラーが返される
+            // 結果はuser_dataとして与えたVecに格納される
+            let responses = Mutex::new(Vec::<String>::new());
+            let user_data = &responses as *const Mutex<Vec<String>> as *mut c_void;
+            let json = b"invalid\0".as_ptr() as *const c_char;
+            assert_eq!(
+                skyway_caller_send(handle, json, Some(callback), user_data),
+                0
+            );
+            for _ in 0..100 {
+                if !responses.lock().unwrap().is_empty() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            let response = responses.lock().unwrap().pop().unwrap();
+            assert!(matches!(
+                ResponseResult::from_str(&response),
+                Ok(ResponseResult::Error(_))
+            ));
+
+            // eventはまだ発生していない
+            let mut buf = [0 as c_char; 16];
+            assert_eq!(
+                skyway_caller_poll_event(handle, buf.as_mut_ptr(), buf.len()),
+                0
+            );
+
+            // 不正な引数
+            assert_eq!(
+                skyway_caller_send(handle, ptr::null(), None, ptr::null_mut()),
+                -1
+            );
+            assert_eq!(
+                skyway_caller_poll_event(ptr::null(), buf.as_mut_ptr(), buf.len()),
+                SKYWAY_CALLER_CLOSED
+            );
+            assert!(skyway_caller_start(ptr::null()).is_null());
+
+            skyway_caller_stop(handle);
+        }
+    }
+
+    #[test]
+    fn small_buffer() {
+        unsafe {
+            // eventを1つ保持したhandleを生成する
+            let (message_tx, _message_rx) = mpsc::channel(1);
+            let (event_tx, receiver) = mpsc::channel(1);
+            event_tx.try_send(r#"{"seq":1}"#.to_string()).unwrap();
+            let caller = SkywayCaller {
+                runtime: Runtime::new().unwrap(),
+                message_tx,
+                events: Mutex::new(Events {
+                    receiver,
+                    pending: None,
+                }),
+            };
+            let handle = Box::into_raw(Box::new(caller));
+
+            // bufferが小さい場合は、NULを含む必要なサイズを負の値で返す. SKYWAY_CALLER_CLOSEDとは区別できる
+            let mut small = [0 as c_char; 1];
+            let required = skyway_caller_poll_event(handle, small.as_mut_ptr(), small.len());
+            assert_eq!(required, -10);
+            assert_ne!(required, SKYWAY_CALLER_CLOSED);
+
+            // eventは次のpollで渡される
+            let mut buf = [0 as c_char; 16];
+            assert_eq!(
+                skyway_caller_poll_event(handle, buf.as_mut_ptr(), buf.len()),
+                9
+            );
+            assert_eq!(
+                CStr::from_ptr(buf.as_ptr()).to_str().unwrap(),
+                r#"{"seq":1}"#
+            );
+
+            // これ以上eventが届かない場合
+            drop(event_tx);
+            assert_eq!(
+                skyway_caller_poll_event(handle, buf.as_mut_ptr(), buf.len()),
+                SKYWAY_CALLER_CLOSED
+            );
+            skyway_caller_stop(handle);
+        }
+    }
+
+    #[test]
+    fn panic_to_error() {
+        // panicはC側に伝播させず、エラーの戻り値に変換する
+        let result = catch_panic(SKYWAY_CALLER_INTERNAL_ERROR, || panic!("unexpected"));
+        assert_eq!(result, SKYWAY_CALLER_INTERNAL_ERROR);
+        assert_eq!(catch_panic(SKYWAY_CALLER_INTERNAL_ERROR, || 0), 0);
+    }
+}
@@ -0,0 +1,891 @@
+// gRPCでこのcrateを利用するためのinterface
+// 定義はproto/skyway_caller.protoにあり、build.rsでコードを生成する
+//
+// 各RPCは、typedなrequestをJSONメッセージのparamsに変換してcrateに与え、
+// 得られたResponseMessageの各entityをtypedなresponseに変換して返す。
+// 期待と異なる種類のResponseMessageが返された場合は、INTERNALとする。
+
+// tonic::StatusはRPCの戻り値の型そのものなので、変換の途中でもboxingせずに返す
+#![allow(clippy::result_large_err)]
+
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tonic::{Request, Response, Status};
+
+use crate::application::dto::response_message::{
+    DataResponse, EventEnvelope, MediaResponse, PeerResponse, ResponseMessage, ResponseResult,
+};
+use crate::domain::listener::entity::ListenerKind;
+use crate::domain::webrtc::common::value_object::{
+    PeerInfo, SerializableId, SerializableSocket, SocketInfo,
+};
+use crate::domain::webrtc::data::entity::{DataConnectionResult, DataConnectionStatus};
+use crate::domain::webrtc::media::entity::{MediaConnectionStatus, RedirectParameters};
+use crate::domain::webrtc::media::preset::MediaPreset;
+use crate::error::ErrorKind;
+use crate::hub::{event_name, EventFilter, EventHub, LagPolicy, ReplayFrom};
+use crate::presentation::serialize_event;
+use crate::session::{self, CommandSender};
+
+/// Code generated from proto/skyway_caller.proto
+pub mod proto {
+    tonic::include_proto!("skyway_caller");
+}
+
+use proto::skyway_caller_server::{SkywayCaller, SkywayCallerServer};
+
+/// Implementation of the SkywayCaller gRPC service
+#[derive(Clone)]
+pub struct GrpcService {
+    message_tx: CommandSender,
+    hub: EventHub,
+}
+
+impl GrpcService {
+    /// Create the service from the values returned from `run_with_event_hub`
+    pub fn new(message_tx: CommandSender, hub: EventHub) -> Self {
+        GrpcService { message_tx, hub }
+    }
+
+    /// Wrap into a server given to `tonic::transport::Server`
+    pub fn into_server(self) -> SkywayCallerServer<Self> {
+        SkywayCallerServer::new(self)
+    }
+
+    async fn execute(
+        &self,
+        r#type: &str,
+        command: &str,
+        params: Value,
+    ) -> Result<ResponseMessage, Status> {
+        let message = json!({ "type": r#type, "command": command, "params": params });
+        let response = session::call(&self.message_tx, message.to_string()).await;
+        match ResponseResult::from_str(&response) {
+            Ok(ResponseResult::Success(message)) => Ok(message),
+            Ok(ResponseResult::Error(message)) => Err(error_status(message)),
+            Err(e) => Err(Status::internal(format!("{:?}", e))),
+        }
+    }
+}
+
+// ResponseResult::Errorの内容から、status codeを決める
+// REST APIのフロントエンドと同じErrorKindで分類する
+fn error_status(message: String) -> Status {
+    match ErrorKind::from_response(&message) {
+        ErrorKind::InvalidRequest => Status::invalid_argument(message),
+        ErrorKind::Forbidden => Status::permission_denied(message),
+        ErrorKind::NotFound => Status::not_found(message),
+        ErrorKind::Unavailable => Status::unavailable(message),
+        ErrorKind::BadGateway => Status::unknown(message),
+    }
+}
+
+fn unexpected(message: ResponseMessage) -> Status {
+    Status::internal(format!("unexpected response {:?}", message))
+}
+
+fn reply<T>(message: T) -> Result<Response<T>, Status> {
+    Ok(Response::new(message))
+}
+
+fn to_value<T: Serialize>(object: &T) -> Value {
+    serde_json::to_value(object).unwrap_or(Value::Null)
+}
+
+// Noneの項目はparamsに含めず、WebRTC Gatewayのデフォルト値に任せる
+fn insert_some<T: Serialize>(map: &mut Map<String, Value>, key: &str, value: &Option<T>) {
+    if let Some(value) = value {
+        map.insert(key.to_string(), to_value(value));
+    }
+}
+
+fn socket<T: SerializableId>(socket: &SocketInfo<T>) -> proto::Socket {
+    proto::Socket {
+        id: socket
+            .get_id()
+            .map(|id| id.as_str().to_string())
+            .unwrap_or_default(),
+        ip: socket.ip().to_string(),
+        port: socket.port() as u32,
+    }
+}
+
+fn media_sockets(sockets: &RedirectParameters) -> proto::MediaSockets {
+    proto::MediaSockets {
+        video: sockets.video.as_ref().map(socket),
+        video_rtcp: sockets.video_rtcp.as_ref().map(socket),
+        audio: sockets.audio.as_ref().map(socket),
+        audio_rtcp: sockets.audio_rtcp.as_ref().map(socket),
+    }
+}
+
+// 宛先のアドレスは、idを持たないsocketとしてparamsに与える
+// e.g. {"ip_v4": "127.0.0.1", "port": 10000}
+fn address_json(address: &proto::Address) -> Value {
+    let mut map = Map::new();
+    let ip_key = if address.ip.contains(':') {
+        "ip_v6"
+    } else {
+        "ip_v4"
+    };
+    map.insert(ip_key.into(), json!(address.ip));
+    map.insert("port".into(), json!(address.port));
+    Value::Object(map)
+}
+
+// autoが指定された場合は、このcrateがredirect用のportを割り当てる
+fn redirect_json(address: &proto::Address) -> Value {
+    if address.auto {
+        return json!("auto");
+    }
+    address_json(address)
+}
+
+fn redirect_params_json(params: &proto::RedirectParams) -> Value {
+    if params.auto {
+        return json!("auto");
+    }
+    let mut map = Map::new();
+    let entries = [
+        ("video", &params.video),
+        ("video_rtcp", &params.video_rtcp),
+        ("audio", &params.audio),
+        ("audio_rtcp", &params.audio_rtcp),
+    ];
+    for (key, address) in entries.iter() {
+        if let Some(address) = address {
+            map.insert(key.to_string(), redirect_json(address));
+        }
+    }
+    Value::Object(map)
+}
+
+fn media_preset(preset: i32) -> Result<Option<MediaPreset>, Status> {
+    use proto::MediaPreset as Preset;
+
+    let preset = match Preset::from_i32(preset) {
+        Some(Preset::Unspecified) => return Ok(None),
+        Some(Preset::H264360p) => MediaPreset::H264_360p,
+        Some(Preset::H264720p) => MediaPreset::H264_720p,
+        Some(Preset::H2641080p) => MediaPreset::H264_1080p,
+        Some(Preset::Vp8Low) => MediaPreset::VP8_low,
+        Some(Preset::Vp8High) => MediaPreset::VP8_high,
+        Some(Preset::Vp9720p) => MediaPreset::VP9_720p,
+        Some(Preset::OpusMono) => MediaPreset::OPUS_mono,
+        Some(Preset::OpusStereo) => MediaPreset::OPUS_stereo,
+        Some(Preset::Pcmu) => MediaPreset::PCMU,
+        Some(Preset::Pcma) => MediaPreset::PCMA,
+        Some(Preset::G722) => MediaPreset::G722,
+        None => {
+            let message = format!("unknown preset {}", preset);
+            return Err(Status::invalid_argument(message));
+        }
+    };
+    Ok(Some(preset))
+}
+
+// presetはそのままparamsに与え、各usecaseでMediaParamsの値として展開する
+fn media_params_json(params: &proto::MediaParams) -> Result<Value, Status> {
+    let mut map = Map::new();
+    insert_some(&mut map, "preset", &media_preset(params.preset)?);
+    insert_some(&mut map, "codec", &params.codec);
+    insert_some(&mut map, "band_width", &params.band_width);
+    insert_some(&mut map, "payload_type", &params.payload_type);
+    insert_some(&mut map, "sampling_rate", &params.sampling_rate);
+    // FANOUT_CALLでは、media_idはfan-out側で埋め込まれる
+    if !params.media_id.is_empty() {
+        map.insert("media_id".into(), json!(params.media_id));
+    }
+    insert_some(&mut map, "rtcp_id", &params.rtcp_id);
+    Ok(Value::Object(map))
+}
+
+fn constraints_json(constraints: &proto::Constraints) -> Result<Value, Status> {
+    let mut map = Map::new();
+    map.insert("video".into(), json!(constraints.video));
+    insert_some(
+        &mut map,
+        "videoReceiveEnabled",
+        &constraints.video_receive_enabled,
+    );
+    map.insert("audio".into(), json!(constraints.audio));
+    insert_some(
+        &mut map,
+        "audioReceiveEnabled",
+        &constraints.audio_receive_enabled,
+    );
+    if let Some(ref params) = constraints.video_params {
+        map.insert("video_params".into(), media_params_json(params)?);
+    }
+    if let Some(ref params) = constraints.audio_params {
+        map.insert("audio_params".into(), media_params_json(params)?);
+    }
+    insert_some(&mut map, "metadata", &constraints.metadata);
+    Ok(Value::Object(map))
+}
+
+// ConnectQueryOptionの形式に変換する
+// dcInitの各項目は、RTCDataChannelInitと同じくcamelCaseで与える
+fn connect_options_json(options: &proto::ConnectOptions) -> Value {
+    let mut map = Map::new();
+    insert_some(&mut map, "metadata", &options.metadata);
+    insert_some(&mut map, "serialization", &options.serialization);
+    if let Some(ref dc_init) = options.dc_init {
+        let mut init = Map::new();
+        insert_some(&mut init, "ordered", &dc_init.ordered);
+        insert_some(
+            &mut init,
+            "maxPacketLifeTime",
+            &dc_init.max_packet_life_time,
+        );
+        insert_some(&mut init, "maxRetransmits", &dc_init.max_retransmits);
+        insert_some(&mut init, "protocol", &dc_init.protocol);
+        insert_some(&mut init, "negotiated", &dc_init.negotiated);
+        insert_some(&mut init, "id", &dc_init.id);
+        insert_some(&mut init, "priority", &dc_init.priority);
+        map.insert("dcInit".into(), Value::Object(init));
+    }
+    Value::Object(map)
+}
+
+fn peer_info_json(peer_info: &Option<proto::PeerInfo>) -> Result<Value, Status> {
+    match peer_info {
+        Some(peer_info) => Ok(json!({ "peer_id": peer_info.peer_id, "token": peer_info.token })),
+        None => Err(Status::invalid_argument("peer is required")),
+    }
+}
+
+fn peer_info(peer_info: &PeerInfo) -> proto::PeerInfo {
+    proto::PeerInfo {
+        peer_id: peer_info.peer_id().as_str().to_string(),
+        token: peer_info.token().as_str().to_string(),
+    }
+}
+
+fn data_connection_result(result: &DataConnectionResult) -> proto::DataConnectionResult {
+    proto::DataConnectionResult {
+        data_connection_id: result.data_connection_id.as_str().to_string(),
+        recv_socket: result.recv_socket.as_ref().map(socket),
+    }
+}
+
+fn data_connection_status(status: &DataConnectionStatus) -> proto::DataConnectionStatus {
+    proto::DataConnectionStatus {
+        remote_id: status.remote_id.clone(),
+        buffersize: status.buffersize as u64,
+        label: status.label.clone(),
+        metadata: status.metadata.clone(),
+        open: status.open,
+        reliable: status.reliable,
+        serialization: status.serialization.clone(),
+        r#type: status.r#type.clone(),
+    }
+}
+
+fn media_connection_status(status: &MediaConnectionStatus) -> proto::MediaConnectionStatus {
+    let ssrc = status
+        .ssrc
+        .iter()
+        .flatten()
+        .map(|pair| proto::SsrcPair {
+            media_id: pair.media_id.as_str().to_string(),
+            ssrc: pair.ssrc as u32,
+        })
+        .collect();
+    proto::MediaConnectionStatus {
+        metadata: status.metadata.clone(),
+        open: status.open,
+        remote_id: status.remote_id.as_str().to_string(),
+        ssrc,
+    }
+}
+
+fn event_message(envelope: &EventEnvelope) -> proto::Event {
+    proto::Event {
+        seq: envelope.seq,
+        timestamp: envelope.timestamp,
+        kind: to_value(&envelope.source.kind)
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        resource_id: envelope.source.resource_id.clone(),
+        peer_id: envelope.peer_id.clone().unwrap_or_default(),
+        event: event_name(envelope).unwrap_or_default(),
+        json: serialize_event(envelope),
+    }
+}
+
+#[tonic::async_trait]
+impl SkywayCaller for GrpcService {
+    async fn create_peer(
+        &self,
+        request: Request<proto::CreatePeerRequest>,
+    ) -> Result<Response<proto::PeerInfo>, Status> {
+        let request = request.into_inner();
+        let params = json!({
+            "key": request.key,
+            "domain": request.domain,
+            "peer_id": request.peer_id,
+            "turn": request.turn
+        });
+        match self.execute("PEER", "CREATE", params).await? {
+            ResponseMessage::Peer(PeerResponse::Create(peer)) => reply(peer_info(&peer)),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn peer_status(
+        &self,
+        request: Request<proto::PeerInfo>,
+    ) -> Result<Response<proto::PeerStatusResponse>, Status> {
+        let params = peer_info_json(&Some(request.into_inner()))?;
+        match self.execute("PEER", "STATUS", params).await? {
+            ResponseMessage::Peer(PeerResponse::Status(status)) => {
+                reply(proto::PeerStatusResponse {
+                    peer_id: status.peer_id.as_str().to_string(),
+                    disconnected: status.disconnected,
+                })
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn delete_peer(
+        &self,
+        request: Request<proto::PeerInfo>,
+    ) -> Result<Response<proto::PeerInfo>, Status> {
+        let params = peer_info_json(&Some(request.into_inner()))?;
+        match self.execute("PEER", "DELETE", params).await? {
+            ResponseMessage::Peer(PeerResponse::Delete(peer)) => reply(peer_info(&peer)),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn subscribe_peer(
+        &self,
+        request: Request<proto::PeerInfo>,
+    ) -> Result<Response<proto::PeerInfo>, Status> {
+        let params = peer_info_json(&Some(request.into_inner()))?;
+        match self.execute("PEER", "SUBSCRIBE", params).await? {
+            ResponseMessage::Peer(PeerResponse::Subscribe(peer)) => reply(peer_info(&peer)),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn create_data(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Socket>, Status> {
+        match self.execute("DATA", "CREATE", json!({})).await? {
+            ResponseMessage::Data(DataResponse::Create(data)) => reply(socket(&data)),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn delete_data(
+        &self,
+        request: Request<proto::DataIdRequest>,
+    ) -> Result<Response<proto::DataIdRequest>, Status> {
+        let params = json!({ "data_id": request.into_inner().data_id });
+        match self.execute("DATA", "DELETE", params).await? {
+            ResponseMessage::Data(DataResponse::Delete(wrapper)) => reply(proto::DataIdRequest {
+                data_id: wrapper.data_id.as_str().to_string(),
+            }),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn connect(
+        &self,
+        request: Request<proto::ConnectRequest>,
+    ) -> Result<Response<proto::DataConnectionResult>, Status> {
+        let request = request.into_inner();
+        let mut params = peer_info_json(&request.peer)?;
+        params["target_id"] = json!(request.target_id);
+        if let Some(data_id) = request.data_id {
+            params["params"] = json!({ "data_id": data_id });
+        }
+        if let Some(ref redirect) = request.redirect {
+            params["redirect_params"] = redirect_json(redirect);
+        }
+        if let Some(ref options) = request.options {
+            params["options"] = connect_options_json(options);
+        }
+        if request.wait_open {
+            params["wait_open"] = json!(true);
+        }
+        if let Some(timeout) = request.wait_open_timeout_ms {
+            params["wait_open_timeout_ms"] = json!(timeout);
+        }
+        match self.execute("DATA", "CONNECT", params).await? {
+            ResponseMessage::Data(DataResponse::Connect(result)) => {
+                reply(data_connection_result(&result))
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn redirect(
+        &self,
+        request: Request<proto::RedirectRequest>,
+    ) -> Result<Response<proto::DataConnectionResult>, Status> {
+        let request = request.into_inner();
+        let params = json!({
+            "data_connection_id": request.data_connection_id,
+            "feed_params": request.data_id.map(|data_id| json!({ "data_id": data_id })),
+            "redirect_params": request.redirect.as_ref().map(redirect_json),
+        });
+        match self.execute("DATA", "REDIRECT", params).await? {
+            ResponseMessage::Data(DataResponse::Redirect(result)) => {
+                reply(data_connection_result(&result))
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn disconnect_data(
+        &self,
+        request: Request<proto::DataConnectionIdRequest>,
+    ) -> Result<Response<proto::DataConnectionIdRequest>, Status> {
+        let params = json!({ "data_connection_id": request.into_inner().data_connection_id });
+        match self.execute("DATA", "DISCONNECT", params).await? {
+            ResponseMessage::Data(DataResponse::Disconnect(wrapper)) => {
+                reply(proto::DataConnectionIdRequest {
+                    data_connection_id: wrapper.data_connection_id.as_str().to_string(),
+                })
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn data_status(
+        &self,
+        request: Request<proto::DataConnectionIdRequest>,
+    ) -> Result<Response<proto::DataConnectionStatus>, Status> {
+        let params = json!({ "data_connection_id": request.into_inner().data_connection_id });
+        match self.execute("DATA", "STATUS", params).await? {
+            ResponseMessage::Data(DataResponse::Status(status)) => {
+                reply(data_connection_status(&status))
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn subscribe_data(
+        &self,
+        request: Request<proto::DataConnectionIdRequest>,
+    ) -> Result<Response<proto::DataSubscribeResult>, Status> {
+        let params = json!({ "data_connection_id": request.into_inner().data_connection_id });
+        match self.execute("DATA", "SUBSCRIBE", params).await? {
+            ResponseMessage::Data(DataResponse::Subscribe(result)) => {
+                reply(proto::DataSubscribeResult {
+                    data_connection_id: result.data_connection_id.as_str().to_string(),
+                    status: Some(data_connection_status(&result.status)),
+                })
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn create_content(
+        &self,
+        request: Request<proto::CreateContentRequest>,
+    ) -> Result<Response<proto::Socket>, Status> {
+        let params = json!({ "is_video": request.into_inner().is_video });
+        match self.execute("MEDIA", "CONTENT_CREATE", params).await? {
+            ResponseMessage::Media(MediaResponse::ContentCreate(media)) => reply(socket(&media)),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn delete_content(
+        &self,
+        request: Request<proto::MediaIdRequest>,
+    ) -> Result<Response<proto::MediaIdRequest>, Status> {
+        let params = json!({ "media_id": request.into_inner().media_id });
+        match self.execute("MEDIA", "CONTENT_DELETE", params).await? {
+            ResponseMessage::Media(MediaResponse::ContentDelete(wrapper)) => {
+                reply(proto::MediaIdRequest {
+                    media_id: wrapper.media_id.as_str().to_string(),
+                })
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn create_rtcp(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Socket>, Status> {
+        match self.execute("MEDIA", "RTCP_CREATE", json!({})).await? {
+            ResponseMessage::Media(MediaResponse::RtcpCreate(rtcp)) => reply(socket(&rtcp)),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn delete_rtcp(
+        &self,
+        request: Request<proto::RtcpIdRequest>,
+    ) -> Result<Response<proto::RtcpIdRequest>, Status> {
+        let params = json!({ "rtcp_id": request.into_inner().rtcp_id });
+        match self.execute("MEDIA", "RTCP_DELETE", params).await? {
+            ResponseMessage::Media(MediaResponse::RtcpDelete(wrapper)) => {
+                reply(proto::RtcpIdRequest {
+                    rtcp_id: wrapper.rtcp_id.as_str().to_string(),
+                })
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn call(
+        &self,
+        request: Request<proto::CallRequest>,
+    ) -> Result<Response<proto::CallResult>, Status> {
+        let request = request.into_inner();
+        let mut params = peer_info_json(&request.peer)?;
+        params["target_id"] = json!(request.target_id);
+        if let Some(ref constraints) = request.constraints {
+            params["constraints"] = constraints_json(constraints)?;
+        }
+        if let Some(ref redirect_params) = request.redirect_params {
+            params["redirect_params"] = redirect_params_json(redirect_params);
+        }
+        if request.wait_ready {
+            params["wait_ready"] = json!(true);
+        }
+        if let Some(timeout) = request.wait_ready_timeout_ms {
+            params["wait_ready_timeout_ms"] = json!(timeout);
+        }
+        match self.execute("MEDIA", "CALL", params).await? {
+            ResponseMessage::Media(MediaResponse::Call(result)) => reply(proto::CallResult {
+                media_connection_id: result.media_connection_id.as_str().to_string(),
+                send_sockets: result.send_sockets.as_ref().map(media_sockets),
+                recv_sockets: result.recv_sockets.as_ref().map(media_sockets),
+            }),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn answer(
+        &self,
+        request: Request<proto::AnswerRequest>,
+    ) -> Result<Response<proto::AnswerResult>, Status> {
+        let request = request.into_inner();
+        let constraints = match request.constraints {
+            Some(ref constraints) => constraints_json(constraints)?,
+            None => return Err(Status::invalid_argument("constraints is required")),
+        };
+        let mut answer_query = json!({ "constraints": constraints });
+        if let Some(ref redirect_params) = request.redirect_params {
+            answer_query["redirect_params"] = redirect_params_json(redirect_params);
+        }
+        let mut params = json!({
+            "media_connection_id": request.media_connection_id,
+            "answer_query": answer_query
+        });
+        if request.wait_ready {
+            params["wait_ready"] = json!(true);
+        }
+        if let Some(timeout) = request.wait_ready_timeout_ms {
+            params["wait_ready_timeout_ms"] = json!(timeout);
+        }
+        match self.execute("MEDIA", "ANSWER", params).await? {
+            ResponseMessage::Media(MediaResponse::Answer(result)) => {
+                let send_sockets = result.send_sockets.as_ref();
+                reply(proto::AnswerResult {
+                    media_connection_id: result.media_connection_id.as_str().to_string(),
+                    send_video_id: send_sockets
+                        .and_then(|sockets| sockets.video_id.as_ref())
+                        .map(|media_id| media_id.as_str().to_string()),
+                    send_audio_id: send_sockets
+                        .and_then(|sockets| sockets.audio_id.as_ref())
+                        .map(|media_id| media_id.as_str().to_string()),
+                    recv_sockets: result.recv_sockets.as_ref().map(media_sockets),
+                })
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn disconnect_media(
+        &self,
+        request: Request<proto::MediaConnectionIdRequest>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let params = json!({ "media_connection_id": request.into_inner().media_connection_id });
+        match self.execute("MEDIA", "DISCONNECT", params).await? {
+            ResponseMessage::Media(MediaResponse::Disconnect(_)) => reply(proto::Empty {}),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn media_status(
+        &self,
+        request: Request<proto::MediaConnectionIdRequest>,
+    ) -> Result<Response<proto::MediaConnectionStatus>, Status> {
+        let params = json!({ "media_connection_id": request.into_inner().media_connection_id });
+        match self.execute("MEDIA", "STATUS", params).await? {
+            ResponseMessage::Media(MediaResponse::Status(status)) => {
+                reply(media_connection_status(&status))
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn create_fanout(
+        &self,
+        request: Request<proto::CreateFanoutRequest>,
+    ) -> Result<Response<proto::FanoutInfo>, Status> {
+        let request = request.into_inner();
+        let input = match request.input {
+            Some(ref input) => address_json(input),
+            None => return Err(Status::invalid_argument("input is required")),
+        };
+        let params = json!({ "is_video": request.is_video, "input": input });
+        match self.execute("MEDIA", "FANOUT_CREATE", params).await? {
+            ResponseMessage::Media(MediaResponse::FanoutCreate(info)) => reply(proto::FanoutInfo {
+                fanout_id: info.fanout_id.as_str().to_string(),
+                is_video: info.is_video,
+                input: Some(socket(&info.input)),
+            }),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn fanout_call(
+        &self,
+        request: Request<proto::FanoutCallRequest>,
+    ) -> Result<Response<proto::FanoutCallResult>, Status> {
+        let request = request.into_inner();
+        let mut call_query = peer_info_json(&request.peer)?;
+        call_query["target_id"] = json!(request.target_id);
+        if let Some(ref constraints) = request.constraints {
+            call_query["constraints"] = constraints_json(constraints)?;
+        }
+        if let Some(ref redirect_params) = request.redirect_params {
+            call_query["redirect_params"] = redirect_params_json(redirect_params);
+        }
+        let params = json!({ "fanout_id": request.fanout_id, "call_query": call_query });
+        match self.execute("MEDIA", "FANOUT_CALL", params).await? {
+            ResponseMessage::Media(MediaResponse::FanoutCall(result)) => {
+                reply(proto::FanoutCallResult {
+                    fanout_id: result.fanout_id.as_str().to_string(),
+                    media_connection_id: result.media_connection_id.as_str().to_string(),
+                    send_socket: Some(socket(&result.send_socket)),
+                })
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn delete_fanout(
+        &self,
+        request: Request<proto::FanoutIdRequest>,
+    ) -> Result<Response<proto::FanoutIdRequest>, Status> {
+        let params = json!({ "fanout_id": request.into_inner().fanout_id });
+        match self.execute("MEDIA", "FANOUT_DELETE", params).await? {
+            ResponseMessage::Media(MediaResponse::FanoutDelete(wrapper)) => {
+                reply(proto::FanoutIdRequest {
+                    fanout_id: wrapper.fanout_id.as_str().to_string(),
+                })
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    async fn subscribe_media(
+        &self,
+        request: Request<proto::MediaConnectionIdRequest>,
+    ) -> Result<Response<proto::MediaSubscribeResult>, Status> {
+        let params = json!({ "media_connection_id": request.into_inner().media_connection_id });
+        match self.execute("MEDIA", "SUBSCRIBE", params).await? {
+            ResponseMessage::Media(MediaResponse::Subscribe(result)) => {
+                reply(proto::MediaSubscribeResult {
+                    media_connection_id: result.media_connection_id.as_str().to_string(),
+                    status: Some(media_connection_status(&result.status)),
+                })
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+
+    type EventsStream = BoxStream<'static, Result<proto::Event, Status>>;
+
+    async fn events(
+        &self,
+        request: Request<proto::EventsRequest>,
+    ) -> Result<Response<Self::EventsStream>, Status> {
+        let request = request.into_inner();
+        let kind = match request.kind {
+            Some(kind) => Some(
+                serde_json::from_value::<ListenerKind>(Value::String(kind))
+                    .map_err(|e| Status::invalid_argument(format!("{:?}", e)))?,
+            ),
+            None => None,
+        };
+        let filter = EventFilter {
+            kind,
+            event: request.event,
+            resource_id: request.resource_id,
+        };
+        // 遅いclientは他のclientへの配信を妨げないよう、溢れたeventを読み飛ばす
+        let subscription = match request.from_seq {
+            Some(seq) => self
+                .hub
+                .subscribe_from(filter, LagPolicy::Skip, ReplayFrom::Seq(seq)),
+            None => self.hub.subscribe(filter, LagPolicy::Skip),
+        };
+        let stream = stream::unfold(subscription, |mut subscription| async move {
+            let envelope = subscription.recv_envelope().await.ok()?;
+            Some((Ok(event_message(&envelope)), subscription))
+        })
+        .boxed();
+        Ok(Response::new(stream))
+    }
+}
+
+#[cfg(test)]
+mod test_grpc {
+    use super::*;
+    use crate::domain::webrtc::common::value_object::PhantomId;
+    use crate::domain::webrtc::media::value_object::MediaId;
+
+    #[test]
+    fn socket_conversion() {
+        let media = SocketInfo::<MediaId>::try_create(
+            Some("vi-4d053831-5dc2-461b-a358-d062d6115216".into()),
+            "127.0.0.1",
+            10000,
+        )
+        .unwrap();
+        let converted = socket(&media);
+        assert_eq!(converted.id, "vi-4d053831-5dc2-461b-a358-d062d6115216");
+        assert_eq!(converted.ip, "127.0.0.1");
+        assert_eq!(converted.port, 10000);
+
+        // idを持たないsocketはidを空とする
+        let redirect = SocketInfo::<PhantomId>::try_create(None, "127.0.0.1", 20000).unwrap();
+        let sockets = RedirectParameters {
+            video: Some(redirect),
+            video_rtcp: None,
+            audio: None,
+            audio_rtcp: None,
+        };
+        let converted = media_sockets(&sockets);
+        assert_eq!(converted.video.unwrap().id, "");
+        assert_eq!(converted.audio, None);
+
+        // redirect先などの宛先は、idを持たないsocketとしてparamsに与える
+        let address = proto::Address {
+            ip: "127.0.0.1".into(),
+            port: 10000,
+            ..Default::default()
+        };
+        assert_eq!(
+            address_json(&address),
+            json!({ "ip_v4": "127.0.0.1", "port": 10000 })
+        );
+        let params = proto::RedirectParams {
+            video: Some(address),
+            ..Default::default()
+        };
+        assert_eq!(
+            redirect_params_json(&params),
+            json!({ "video": { "ip_v4": "127.0.0.1", "port": 10000 } })
+        );
+
+        // autoはportの自動割当として与える
+        let params = proto::RedirectParams {
+            audio: Some(proto::Address {
+                auto: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(redirect_params_json(&params), json!({ "audio": "auto" }));
+        let params = proto::RedirectParams {
+            auto: true,
+            ..Default::default()
+        };
+        assert_eq!(redirect_params_json(&params), json!("auto"));
+    }
+
+    #[test]
+    fn constraints_conversion() {
+        let constraints = proto::Constraints {
+            video: true,
+            video_params: Some(proto::MediaParams {
+                preset: proto::MediaPreset::Vp8Low as i32,
+                band_width: Some(500),
+                media_id: "vi-4d053831-5dc2-461b-a358-d062d6115216".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        // presetはusecaseで展開されるJSONの形式で与え、設定されていない項目は省略する
+        assert_eq!(
+            constraints_json(&constraints).unwrap(),
+            json!({
+                "video": true,
+                "audio": false,
+                "video_params": {
+                    "preset": "vp8_low",
+                    "band_width": 500,
+                    "media_id": "vi-4d053831-5dc2-461b-a358-d062d6115216"
+                }
+            })
+        );
+
+        // 定義されていないpresetはエラーとする
+        let params = proto::MediaParams {
+            preset: 100,
+            ..Default::default()
+        };
+        let status = media_params_json(&params).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn connect_options_conversion() {
+        let options = proto::ConnectOptions {
+            serialization: Some("BINARY".into()),
+            dc_init: Some(proto::DataChannelInit {
+                ordered: Some(true),
+                max_retransmits: Some(3),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            connect_options_json(&options),
+            json!({
+                "serialization": "BINARY",
+                "dcInit": { "ordered": true, "maxRetransmits": 3 }
+            })
+        );
+    }
+
+    #[test]
+    fn status_code() {
+        use crate::error::Error;
+
+        // skyway-webrtc-gateway-apiが生成するエラーと、対応するstatus code
+        let table = [
+            ("recv message\ninvalid peer_id", tonic::Code::InvalidArgument),
+            ("recv Forbidden", tonic::Code::PermissionDenied),
+            ("recv Not Found", tonic::Code::NotFound),
+            (
+                "recv invalid response: url: http://localhost:8000/media/connections code: 503 Service Unavailable",
+                tonic::Code::Unavailable,
+            ),
+            ("recv Method Not Allowed", tonic::Code::Unknown),
+            // このcrateでのパラメータの検証に失敗した場合
+            ("media socket has no media_id", tonic::Code::InvalidArgument),
+        ];
+        for (message, code) in table.iter() {
+            let error = serde_json::to_string(&Error::create_local_error(message)).unwrap();
+            assert_eq!(error_status(error).code(), *code, "{}", message);
+        }
+    }
+}
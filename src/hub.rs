@@ -0,0 +1,905 @@
+// run_with_event_hubが返すReceiver以外にも、eventを受け取るReceiverを追加するための機能
+// lib.rs内でeventに番号を振った後、購読者ごとのqueueを介して全ての購読者に配信する
+// 購読者ごとにfilterを適用し、処理が遅れてqueueから溢れたeventはOverflowPolicyに従って破棄し、その数を報告する
+// JSONで受け取る購読者には、次に配信するeventのdroppedとして報告する
+// 購読前に発生したeventを再送できるよう、直近のeventは履歴として保持する
+// 特定のeventを待つwait_forとAWAITも、一時的な購読者として他の購読者に影響を与えずに実装する
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::Notify;
+
+use crate::application::dto::response_message::{EventEnvelope, GatewayEvent};
+use crate::domain::listener::entity::ListenerKind;
+use crate::presentation::serialize_event;
+
+/// Condition to select events delivered to a subscriber.
+/// Each field is ignored when it is None.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct EventFilter {
+    /// Kind of the listener which observed the event
+    pub kind: Option<ListenerKind>,
+    /// Event name such as "CONNECTION" or "CLOSE". "LISTENER_STOPPED" is also matched
+    pub event: Option<String>,
+    /// peer_id, data_connection_id or media_connection_id of the source listener
+    pub resource_id: Option<String>,
+}
+
+impl EventFilter {
+    fn matches(&self, envelope: &EventEnvelope) -> bool {
+        if let Some(kind) = self.kind {
+            if envelope.source.kind != kind {
+                return false;
+            }
+        }
+        if let Some(ref resource_id) = self.resource_id {
+            if &envelope.source.resource_id != resource_id {
+                return false;
+            }
+        }
+        if let Some(ref event) = self.event {
+            if event_name(envelope).as_deref() != Some(event.as_str()) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// EVENTの場合はevent名を、LISTENER_STOPPEDのようにevent名を持たない場合はcommandを返す
+pub(crate) fn event_name(envelope: &EventEnvelope) -> Option<String> {
+    let value = serde_json::to_value(&envelope.event).ok()?;
+    let result = value.get("result")?;
+    result
+        .get("event")
+        .or_else(|| result.get("command"))
+        .and_then(Value::as_str)
+        .map(|name| name.to_string())
+}
+
+/// Bounds of the history of recent events kept for late subscribers.
+/// An event is discarded when either bound is exceeded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryLimit {
+    /// Maximum number of events. 0 disables the history
+    pub max_events: usize,
+    /// Maximum age of events
+    pub max_age: Duration,
+}
+
+impl Default for HistoryLimit {
+    fn default() -> Self {
+        HistoryLimit {
+            max_events: 1000,
+            max_age: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Position in the history from which a subscriber starts receiving events.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayFrom {
+    /// Events whose seq is equal to or greater than this
+    Seq(u64),
+    /// Events whose timestamp is equal to or later than this, in milliseconds since the UNIX epoch
+    Timestamp(u64),
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+struct History {
+    limit: HistoryLimit,
+    events: VecDeque<Arc<EventEnvelope>>,
+}
+
+impl History {
+    fn evict(&mut self, now: u64) {
+        let max_age = self.limit.max_age.as_millis() as u64;
+        while let Some(event) = self.events.front() {
+            let expired = now.saturating_sub(event.timestamp) > max_age;
+            if self.events.len() <= self.limit.max_events && !expired {
+                break;
+            }
+            self.events.pop_front();
+        }
+    }
+
+    fn push(&mut self, event: Arc<EventEnvelope>, now: u64) {
+        self.events.push_back(event);
+        self.evict(now);
+    }
+
+    fn replay(&self, from: ReplayFrom) -> VecDeque<Arc<EventEnvelope>> {
+        self.events
+            .iter()
+            .filter(|event| match from {
+                ReplayFrom::Seq(seq) => event.seq >= seq,
+                ReplayFrom::Timestamp(timestamp) => event.timestamp >= timestamp,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Behavior when the queue of a subscriber is full.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverflowPolicy {
+    /// Wait until the subscriber receives an event.
+    /// It also delays the other subscribers and, eventually, the long polling of every listener
+    Block,
+    /// Drop the oldest event in the queue
+    DropOldest,
+    /// Drop the new event
+    DropNewest,
+    /// Replace a queued event of the same name from the same listener, treating it as an outdated status.
+    /// Drop the oldest event when there is no such event
+    Coalesce,
+}
+
+/// Size and overflow policy of the queue of a subscriber.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueConfig {
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        QueueConfig {
+            capacity: 1000,
+            overflow: OverflowPolicy::DropOldest,
+        }
+    }
+}
+
+/// Capacities and overflow policies of the event pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventChannelConfig {
+    /// Capacity of the channel which collects events from all listeners.
+    /// Listeners wait when it is full
+    pub listener_capacity: usize,
+    /// Queue of the Receiver returned from `run`
+    pub receiver_queue: QueueConfig,
+    /// Queue of subscribers added via `EventHub::subscribe`
+    pub subscriber_queue: QueueConfig,
+}
+
+impl Default for EventChannelConfig {
+    fn default() -> Self {
+        EventChannelConfig {
+            listener_capacity: 10,
+            // 従来通り、End-Userが受け取るまで待ちeventを破棄しない
+            receiver_queue: QueueConfig {
+                capacity: 1000,
+                overflow: OverflowPolicy::Block,
+            },
+            subscriber_queue: QueueConfig::default(),
+        }
+    }
+}
+
+/// Behavior when a subscriber falls behind and misses events.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LagPolicy {
+    /// Skip the missed events. The number is set to `dropped` of the next event returned from `recv`,
+    /// and counted in `EventSubscription::missed`
+    Skip,
+    /// Return `SubscriptionError::Lagged` once and keep receiving
+    Report,
+    /// Return `SubscriptionError::Lagged` and close the subscription
+    Close,
+}
+
+/// Error returned from `EventHub::wait_for`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaitError {
+    /// No matching event arrived before the timeout
+    Timeout,
+    /// No more events will be delivered
+    Closed,
+}
+
+// AWAIT commandのparams
+// filterに一致するeventを、timeoutミリ秒まで待つ
+// from_seqを指定した場合は、履歴に残っているevent(seqがfrom_seq以上)も対象にする
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct AwaitParams {
+    #[serde(flatten)]
+    pub(crate) filter: EventFilter,
+    pub(crate) timeout: u64,
+    #[serde(default)]
+    pub(crate) from_seq: Option<u64>,
+}
+
+/// Error returned from `EventSubscription::recv`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionError {
+    /// Number of events the subscriber missed since the last receive
+    Lagged(u64),
+    /// No more events will be delivered
+    Closed,
+}
+
+#[derive(Default)]
+struct Queue {
+    events: VecDeque<Arc<EventEnvelope>>,
+    // 購読者にまだ報告していない、破棄したeventの数
+    unreported: u64,
+    closed: bool,
+}
+
+// 購読者ごとのqueue
+// hubがeventを追加し、EventSubscriptionが取り出す
+struct Outbox {
+    filter: EventFilter,
+    config: QueueConfig,
+    queue: Mutex<Queue>,
+    // 破棄したeventの総数
+    // run()が返すReceiverの分は、EventHubからも参照できるよう共有する
+    dropped: Arc<AtomicU64>,
+    // eventの追加を購読者に通知する
+    readable: Notify,
+    // eventの取り出しを、Blockで待機中のhubに通知する
+    writable: Notify,
+}
+
+impl Outbox {
+    // queueに空きがなく、Blockのため追加できなかった場合はfalseを返す
+    fn offer(&self, envelope: &Arc<EventEnvelope>) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.closed {
+            return true;
+        }
+        if queue.events.len() >= self.config.capacity {
+            match self.config.overflow {
+                OverflowPolicy::Block => return false,
+                OverflowPolicy::DropNewest => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    queue.unreported += 1;
+                    return true;
+                }
+                OverflowPolicy::DropOldest => {
+                    queue.events.pop_front();
+                }
+                OverflowPolicy::Coalesce => {
+                    let name = event_name(envelope);
+                    let index = queue
+                        .events
+                        .iter()
+                        .position(|queued| {
+                            queued.source == envelope.source && event_name(queued) == name
+                        })
+                        .unwrap_or(0);
+                    queue.events.remove(index);
+                }
+            }
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            queue.unreported += 1;
+        }
+        queue.events.push_back(envelope.clone());
+        self.readable.notify_one();
+        true
+    }
+
+    async fn push(&self, envelope: &Arc<EventEnvelope>) {
+        while !self.offer(envelope) {
+            self.writable.notified().await;
+        }
+    }
+
+    fn close(&self) {
+        self.queue.lock().unwrap().closed = true;
+        self.readable.notify_one();
+    }
+}
+
+struct Subscribers {
+    history: History,
+    outboxes: Vec<Weak<Outbox>>,
+}
+
+/// Handle to add subscribers of events.
+#[derive(Clone)]
+pub struct EventHub {
+    // 履歴の取得と購読の開始の間にeventが配信されると、取りこぼしや重複が生じるので
+    // 配信先の決定と購読の開始はこのlockを取得して行う
+    subscribers: Arc<Mutex<Subscribers>>,
+    default_queue: QueueConfig,
+    receiver_dropped: Arc<AtomicU64>,
+}
+
+impl EventHub {
+    pub(crate) fn new(default_queue: QueueConfig, limit: HistoryLimit) -> Self {
+        let subscribers = Subscribers {
+            history: History {
+                limit,
+                events: VecDeque::new(),
+            },
+            outboxes: vec![],
+        };
+        EventHub {
+            subscribers: Arc::new(Mutex::new(subscribers)),
+            default_queue,
+            receiver_dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    // run()が返すReceiverへ中継するための購読
+    // 破棄したeventの数はEventHub::receiver_droppedで参照できる
+    pub(crate) fn subscribe_receiver(&self, queue: QueueConfig) -> EventSubscription {
+        self.create_subscription(
+            EventFilter::default(),
+            LagPolicy::Skip,
+            queue,
+            None,
+            self.receiver_dropped.clone(),
+        )
+    }
+
+    // filterに一致する購読者のqueueに追加する
+    // Blockの購読者のqueueに空きがない場合は、空くまで待機する
+    pub(crate) async fn publish(&self, envelope: EventEnvelope) {
+        let envelope = Arc::new(envelope);
+        let outboxes: Vec<Arc<Outbox>> = {
+            let mut subscribers = self.subscribers.lock().unwrap();
+            subscribers.history.push(envelope.clone(), now());
+            // 破棄された購読者はここで取り除く
+            subscribers
+                .outboxes
+                .retain(|outbox| outbox.strong_count() > 0);
+            subscribers
+                .outboxes
+                .iter()
+                .filter_map(Weak::upgrade)
+                .filter(|outbox| outbox.filter.matches(&envelope))
+                .collect()
+        };
+        for outbox in outboxes {
+            outbox.push(&envelope).await;
+        }
+    }
+
+    // 全ての購読者に、これ以上eventが配信されないことを通知する
+    pub(crate) fn close(&self) {
+        let subscribers = self.subscribers.lock().unwrap();
+        for outbox in subscribers.outboxes.iter().filter_map(Weak::upgrade) {
+            outbox.close();
+        }
+    }
+
+    /// Create a new independent receiver of events.
+    /// Events published before the subscription are not delivered.
+    pub fn subscribe(&self, filter: EventFilter, lag_policy: LagPolicy) -> EventSubscription {
+        self.subscribe_with(filter, lag_policy, self.default_queue.clone(), None)
+    }
+
+    /// Create a new independent receiver which first replays events kept in the history,
+    /// then switches to live delivery without gaps or duplicates.
+    pub fn subscribe_from(
+        &self,
+        filter: EventFilter,
+        lag_policy: LagPolicy,
+        from: ReplayFrom,
+    ) -> EventSubscription {
+        self.subscribe_with(filter, lag_policy, self.default_queue.clone(), Some(from))
+    }
+
+    /// Create a new independent receiver with its own queue size and overflow policy.
+    pub fn subscribe_with(
+        &self,
+        filter: EventFilter,
+        lag_policy: LagPolicy,
+        queue: QueueConfig,
+        from: Option<ReplayFrom>,
+    ) -> EventSubscription {
+        let dropped = Arc::new(AtomicU64::new(0));
+        self.create_subscription(filter, lag_policy, queue, from, dropped)
+    }
+
+    fn create_subscription(
+        &self,
+        filter: EventFilter,
+        lag_policy: LagPolicy,
+        queue: QueueConfig,
+        from: Option<ReplayFrom>,
+        dropped: Arc<AtomicU64>,
+    ) -> EventSubscription {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let pending = match from {
+            Some(from) => {
+                subscribers.history.evict(now());
+                subscribers
+                    .history
+                    .replay(from)
+                    .into_iter()
+                    .filter(|event| filter.matches(event))
+                    .collect()
+            }
+            None => VecDeque::new(),
+        };
+        let outbox = Arc::new(Outbox {
+            filter,
+            config: queue,
+            queue: Mutex::new(Queue::default()),
+            dropped,
+            readable: Notify::new(),
+            writable: Notify::new(),
+        });
+        subscribers.outboxes.push(Arc::downgrade(&outbox));
+        EventSubscription {
+            outbox,
+            pending,
+            lag_policy,
+            skipped: 0,
+            closed: false,
+        }
+    }
+
+    /// Wait for the first event which satisfies the predicate.
+    /// Events are observed from the time this function is called, not when the future is polled,
+    /// so that an event triggered right after the call is not missed.
+    /// Other subscribers still receive the event.
+    pub fn wait_for<F>(
+        &self,
+        predicate: F,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<GatewayEvent, WaitError>>
+    where
+        F: Fn(&GatewayEvent) -> bool,
+    {
+        // 待機中に溢れても、対象のeventでなければ問題ないので、取りこぼしは無視する
+        let mut subscription = self.subscribe_with(
+            EventFilter::default(),
+            LagPolicy::Skip,
+            self.default_queue.clone(),
+            None,
+        );
+        async move {
+            let wait = async {
+                loop {
+                    let envelope = subscription
+                        .recv_envelope()
+                        .await
+                        .map_err(|_| WaitError::Closed)?;
+                    if let Some(event) = GatewayEvent::from_response(envelope.event.clone()) {
+                        if predicate(&event) {
+                            return Ok(event);
+                        }
+                    }
+                }
+            };
+            tokio::time::timeout(timeout, wait)
+                .await
+                .unwrap_or(Err(WaitError::Timeout))
+        }
+    }
+
+    // AWAIT commandの処理
+    // 一致するeventはJSONのまま返す。ResponseResult::Errorの場合はタイムアウトなどの理由を示す
+    pub(crate) fn await_event(
+        &self,
+        params: AwaitParams,
+    ) -> impl Future<Output = Result<Arc<EventEnvelope>, String>> {
+        let timeout = params.timeout;
+        let mut subscription = self.subscribe_with(
+            params.filter,
+            LagPolicy::Skip,
+            self.default_queue.clone(),
+            params.from_seq.map(ReplayFrom::Seq),
+        );
+        async move {
+            match tokio::time::timeout(Duration::from_millis(timeout), subscription.recv_envelope())
+                .await
+            {
+                Ok(Ok(envelope)) => Ok(envelope),
+                Ok(Err(_)) => Err("event stream was closed before a matching event".to_string()),
+                Err(_) => Err(format!("no matching event arrived within {} ms", timeout)),
+            }
+        }
+    }
+
+    /// Number of active subscriptions
+    pub fn subscriber_count(&self) -> usize {
+        let subscribers = self.subscribers.lock().unwrap();
+        subscribers
+            .outboxes
+            .iter()
+            .filter(|outbox| outbox.strong_count() > 0)
+            .count()
+    }
+
+    /// Total number of events dropped because the Receiver returned from `run` fell behind
+    pub fn receiver_dropped(&self) -> u64 {
+        self.receiver_dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Receiver of events in the same JSON format as the Receiver returned from `run`.
+pub struct EventSubscription {
+    outbox: Arc<Outbox>,
+    // 購読開始前に発生し、再送を待っているevent
+    pending: VecDeque<Arc<EventEnvelope>>,
+    lag_policy: LagPolicy,
+    // Skipで読み飛ばし、まだdroppedとして通知していないeventの数
+    skipped: u64,
+    closed: bool,
+}
+
+impl EventSubscription {
+    /// Receive the next event which matches the filter.
+    /// When events were skipped before it, their number is set to `dropped` of the event.
+    pub async fn recv(&mut self) -> Result<String, SubscriptionError> {
+        let envelope = self.recv_envelope().await?;
+        if self.skipped == 0 {
+            return Ok(serialize_event(&envelope));
+        }
+        // envelopeは他の購読者と共有しているので、この購読者の分だけ複製して設定する
+        let envelope = EventEnvelope {
+            dropped: Some(std::mem::take(&mut self.skipped)),
+            ..(*envelope).clone()
+        };
+        Ok(serialize_event(&envelope))
+    }
+
+    // JSONに変換せず、Objectのまま受け取る
+    pub(crate) async fn recv_envelope(&mut self) -> Result<Arc<EventEnvelope>, SubscriptionError> {
+        if let Some(envelope) = self.pending.pop_front() {
+            return Ok(envelope);
+        }
+        loop {
+            if self.closed {
+                return Err(SubscriptionError::Closed);
+            }
+            {
+                let mut queue = self.outbox.queue.lock().unwrap();
+                if queue.unreported > 0 {
+                    let count = queue.unreported;
+                    queue.unreported = 0;
+                    match self.lag_policy {
+                        LagPolicy::Skip => self.skipped += count,
+                        LagPolicy::Report => return Err(SubscriptionError::Lagged(count)),
+                        LagPolicy::Close => {
+                            self.closed = true;
+                            queue.closed = true;
+                            return Err(SubscriptionError::Lagged(count));
+                        }
+                    }
+                }
+                if let Some(envelope) = queue.events.pop_front() {
+                    self.outbox.writable.notify_one();
+                    return Ok(envelope);
+                }
+                if queue.closed {
+                    self.closed = true;
+                    continue;
+                }
+            }
+            self.outbox.readable.notified().await;
+        }
+    }
+
+    /// Total number of events dropped because this subscriber fell behind
+    pub fn missed(&self) -> u64 {
+        self.outbox.dropped.load(Ordering::Relaxed)
+    }
+}
+
+// Blockで待機中のhubが、破棄された購読者を待ち続けないようにする
+impl Drop for EventSubscription {
+    fn drop(&mut self) {
+        self.outbox.queue.lock().unwrap().closed = true;
+        self.outbox.writable.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod test_event_hub {
+    use crate::application::dto::response_message::{EventEnvelope, PeerResponse, SourcedEvent};
+    use crate::domain::listener::entity::ListenerInfo;
+    use crate::domain::webrtc::peer::entity::{PeerCloseEvent, PeerEventEnum};
+    use crate::domain::webrtc::peer::value_object::PeerInfo;
+
+    use super::*;
+
+    fn peer_info() -> PeerInfo {
+        PeerInfo::try_create("peer_id", "pt-9749250e-d157-4f80-9ee2-359ce8524308").unwrap()
+    }
+
+    fn envelope(seq: u64, kind: ListenerKind, resource_id: &str) -> EventEnvelope {
+        let event = PeerResponse::Event(PeerEventEnum::CLOSE(PeerCloseEvent {
+            params: peer_info(),
+        }))
+        .create_response_message();
+        EventEnvelope::new(
+            seq,
+            SourcedEvent {
+                source: ListenerInfo::new(kind, resource_id),
+                peer_id: Some("peer_id".into()),
+                event,
+            },
+        )
+    }
+
+    fn queue(capacity: usize, overflow: OverflowPolicy) -> QueueConfig {
+        QueueConfig { capacity, overflow }
+    }
+
+    fn seq(message: &str) -> u64 {
+        message.parse::<EventEnvelope>().unwrap().seq
+    }
+
+    #[tokio::test]
+    async fn broadcast_to_subscribers() {
+        let hub = EventHub::new(
+            queue(10, OverflowPolicy::DropOldest),
+            HistoryLimit::default(),
+        );
+        let mut all = hub.subscribe(EventFilter::default(), LagPolicy::Report);
+        let mut peer = hub.subscribe(
+            EventFilter {
+                kind: Some(ListenerKind::Peer),
+                ..Default::default()
+            },
+            LagPolicy::Report,
+        );
+        assert_eq!(hub.subscriber_count(), 2);
+
+        hub.publish(envelope(1, ListenerKind::Data, "dc-id")).await;
+        hub.publish(envelope(2, ListenerKind::Peer, "peer_id"))
+            .await;
+
+        // filterのない購読者は全て受け取る
+        assert_eq!(seq(&all.recv().await.unwrap()), 1);
+        assert_eq!(seq(&all.recv().await.unwrap()), 2);
+        // filterに一致するeventのみ受け取る
+        assert_eq!(seq(&peer.recv().await.unwrap()), 2);
+    }
+
+    #[test]
+    fn filter() {
+        let event = envelope(1, ListenerKind::Peer, "peer_id");
+        let filter = |event: Option<&str>, resource_id: Option<&str>| EventFilter {
+            kind: None,
+            event: event.map(|e| e.to_string()),
+            resource_id: resource_id.map(|r| r.to_string()),
+        };
+
+        assert!(filter(Some("CLOSE"), None).matches(&event));
+        assert!(!filter(Some("CONNECTION"), None).matches(&event));
+        assert!(filter(None, Some("peer_id")).matches(&event));
+        assert!(!filter(None, Some("other")).matches(&event));
+        assert!(filter(Some("CLOSE"), Some("peer_id")).matches(&event));
+    }
+
+    #[tokio::test]
+    async fn lag() {
+        let hub = EventHub::new(
+            queue(2, OverflowPolicy::DropOldest),
+            HistoryLimit::default(),
+        );
+        let mut skip = hub.subscribe(EventFilter::default(), LagPolicy::Skip);
+        let mut report = hub.subscribe(EventFilter::default(), LagPolicy::Report);
+        let mut close = hub.subscribe(EventFilter::default(), LagPolicy::Close);
+
+        // 容量を超えたので、最初の2つは取りこぼす
+        for i in 1..=4 {
+            hub.publish(envelope(i, ListenerKind::Peer, "peer_id"))
+                .await;
+        }
+
+        // 取りこぼした数は、次のeventのdroppedとして通知される
+        let event = skip.recv().await.unwrap().parse::<EventEnvelope>().unwrap();
+        assert_eq!((event.seq, event.dropped), (3, Some(2)));
+        let event = skip.recv().await.unwrap().parse::<EventEnvelope>().unwrap();
+        assert_eq!((event.seq, event.dropped), (4, None));
+        assert_eq!(skip.missed(), 2);
+
+        assert_eq!(report.recv().await, Err(SubscriptionError::Lagged(2)));
+        assert_eq!(seq(&report.recv().await.unwrap()), 3);
+        assert_eq!(report.missed(), 2);
+
+        assert_eq!(close.recv().await, Err(SubscriptionError::Lagged(2)));
+        assert_eq!(close.recv().await, Err(SubscriptionError::Closed));
+    }
+
+    #[tokio::test]
+    async fn replay() {
+        let hub = EventHub::new(
+            queue(10, OverflowPolicy::DropOldest),
+            HistoryLimit::default(),
+        );
+        for i in 1..=3 {
+            hub.publish(envelope(i, ListenerKind::Peer, "peer_id"))
+                .await;
+        }
+
+        // 指定した番号以降の履歴を受け取った後、購読開始後のeventを受け取る
+        let mut subscription = hub.subscribe_from(
+            EventFilter::default(),
+            LagPolicy::Report,
+            ReplayFrom::Seq(2),
+        );
+        hub.publish(envelope(4, ListenerKind::Peer, "peer_id"))
+            .await;
+        assert_eq!(seq(&subscription.recv().await.unwrap()), 2);
+        assert_eq!(seq(&subscription.recv().await.unwrap()), 3);
+        assert_eq!(seq(&subscription.recv().await.unwrap()), 4);
+
+        // 時刻でも指定できる
+        let mut subscription = hub.subscribe_from(
+            EventFilter::default(),
+            LagPolicy::Report,
+            ReplayFrom::Timestamp(0),
+        );
+        assert_eq!(seq(&subscription.recv().await.unwrap()), 1);
+    }
+
+    #[tokio::test]
+    async fn history_limit() {
+        let hub = EventHub::new(
+            queue(10, OverflowPolicy::DropOldest),
+            HistoryLimit {
+                max_events: 2,
+                max_age: Duration::from_secs(60),
+            },
+        );
+        // 古すぎるeventは保持しない
+        let mut expired = envelope(1, ListenerKind::Peer, "peer_id");
+        expired.timestamp = 0;
+        hub.publish(expired).await;
+        // 件数を超えた分は古いものから破棄する
+        for i in 2..=4 {
+            hub.publish(envelope(i, ListenerKind::Peer, "peer_id"))
+                .await;
+        }
+
+        let subscribers = hub.subscribers.lock().unwrap();
+        let seqs: Vec<u64> = subscribers
+            .history
+            .replay(ReplayFrom::Seq(0))
+            .iter()
+            .map(|e| e.seq)
+            .collect();
+        assert_eq!(seqs, vec![3, 4]);
+    }
+
+    #[tokio::test]
+    async fn drop_newest() {
+        let hub = EventHub::new(
+            queue(10, OverflowPolicy::DropOldest),
+            HistoryLimit::default(),
+        );
+        let mut subscription = hub.subscribe_with(
+            EventFilter::default(),
+            LagPolicy::Skip,
+            queue(2, OverflowPolicy::DropNewest),
+            None,
+        );
+        for i in 1..=4 {
+            hub.publish(envelope(i, ListenerKind::Peer, "peer_id"))
+                .await;
+        }
+
+        // 溢れた新しいeventを破棄する
+        assert_eq!(seq(&subscription.recv().await.unwrap()), 1);
+        assert_eq!(seq(&subscription.recv().await.unwrap()), 2);
+        assert_eq!(subscription.missed(), 2);
+    }
+
+    #[tokio::test]
+    async fn coalesce() {
+        let hub = EventHub::new(
+            queue(10, OverflowPolicy::DropOldest),
+            HistoryLimit::default(),
+        );
+        let mut subscription = hub.subscribe_with(
+            EventFilter::default(),
+            LagPolicy::Skip,
+            queue(2, OverflowPolicy::Coalesce),
+            None,
+        );
+        hub.publish(envelope(1, ListenerKind::Data, "dc-id")).await;
+        hub.publish(envelope(2, ListenerKind::Peer, "peer_id"))
+            .await;
+        hub.publish(envelope(3, ListenerKind::Peer, "peer_id"))
+            .await;
+
+        // 同じ監視ループからの同じeventは、新しいもので置き換える
+        assert_eq!(seq(&subscription.recv().await.unwrap()), 1);
+        assert_eq!(seq(&subscription.recv().await.unwrap()), 3);
+        assert_eq!(subscription.missed(), 1);
+    }
+
+    #[tokio::test]
+    async fn block() {
+        let hub = EventHub::new(
+            queue(10, OverflowPolicy::DropOldest),
+            HistoryLimit::default(),
+        );
+        let mut subscription = hub.subscribe_receiver(queue(1, OverflowPolicy::Block));
+        hub.publish(envelope(1, ListenerKind::Peer, "peer_id"))
+            .await;
+
+        // queueが空くまで配信を待機する
+        let publisher = hub.clone();
+        let mut task = tokio::spawn(async move {
+            publisher
+                .publish(envelope(2, ListenerKind::Peer, "peer_id"))
+                .await;
+            publisher.close();
+        });
+        let waiting = tokio::time::timeout(Duration::from_millis(10), &mut task).await;
+        assert!(waiting.is_err());
+
+        assert_eq!(seq(&subscription.recv().await.unwrap()), 1);
+        task.await.unwrap();
+        assert_eq!(seq(&subscription.recv().await.unwrap()), 2);
+        assert_eq!(subscription.recv().await, Err(SubscriptionError::Closed));
+        assert_eq!(hub.receiver_dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn wait_for() {
+        let hub = EventHub::new(
+            queue(10, OverflowPolicy::DropOldest),
+            HistoryLimit::default(),
+        );
+        let mut other = hub.subscribe(EventFilter::default(), LagPolicy::Report);
+
+        // 呼び出した時点から監視を開始する
+        let wait = hub.wait_for(
+            |event| matches!(event, GatewayEvent::PeerClose(_)),
+            Duration::from_secs(1),
+        );
+        hub.publish(envelope(1, ListenerKind::Peer, "peer_id"))
+            .await;
+        assert!(matches!(wait.await, Ok(GatewayEvent::PeerClose(_))));
+        // 他の購読者も同じeventを受け取る
+        assert_eq!(seq(&other.recv().await.unwrap()), 1);
+
+        let wait = hub.wait_for(
+            |event| matches!(event, GatewayEvent::PeerOpen(_)),
+            Duration::from_millis(10),
+        );
+        hub.publish(envelope(2, ListenerKind::Peer, "peer_id"))
+            .await;
+        assert_eq!(wait.await, Err(WaitError::Timeout));
+    }
+
+    #[tokio::test]
+    async fn await_event() {
+        let hub = EventHub::new(
+            queue(10, OverflowPolicy::DropOldest),
+            HistoryLimit::default(),
+        );
+        hub.publish(envelope(1, ListenerKind::Data, "dc-id")).await;
+        hub.publish(envelope(2, ListenerKind::Peer, "peer_id"))
+            .await;
+
+        // 履歴に残っているeventも対象にできる
+        let params: AwaitParams = serde_json::from_str(
+            r#"{"kind": "PEER", "event": "CLOSE", "timeout": 1000, "from_seq": 1}"#,
+        )
+        .unwrap();
+        assert_eq!(hub.await_event(params).await.unwrap().seq, 2);
+
+        let params: AwaitParams =
+            serde_json::from_str(r#"{"resource_id": "dc-id", "timeout": 10}"#).unwrap();
+        assert_eq!(
+            hub.await_event(params).await,
+            Err("no matching event arrived within 10 ms".to_string())
+        );
+    }
+}
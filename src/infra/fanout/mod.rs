@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use shaku::*;
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+
+use crate::domain::fanout::entity::FanoutInfo;
+use crate::domain::fanout::repository::FanoutRepository;
+use crate::domain::fanout::value_object::FanoutId;
+use crate::domain::webrtc::common::value_object::{PhantomId, SerializableSocket, SocketInfo};
+use crate::domain::webrtc::media::value_object::{MediaConnectionId, MediaId};
+use crate::error;
+
+// UDPパケットの最大長
+const MAX_PACKET_SIZE: usize = 65535;
+
+// 複製先のmedia socket
+// MediaConnectionIdをkeyとして保持する
+type Recipients = Arc<Mutex<HashMap<String, SocketInfo<MediaId>>>>;
+
+pub(crate) struct Relay {
+    info: FanoutInfo,
+    recipients: Recipients,
+    handle: JoinHandle<()>,
+}
+
+// Serviceの具象Struct
+// 中継中のfan-outを、入力portで受信し続けるtaskと共に保持する
+#[derive(Component, Clone, Default)]
+#[shaku(interface = FanoutRepository)]
+pub(crate) struct FanoutRepositoryImpl {
+    #[shaku(default)]
+    relays: Arc<Mutex<HashMap<FanoutId, Relay>>>,
+    // fan-outを削除した後も、CLOSE時にmedia socketを開放できるよう、Relayとは別に保持する
+    // MediaConnectionIdをkeyとする
+    #[shaku(default)]
+    sockets: Arc<Mutex<HashMap<String, SocketInfo<MediaId>>>>,
+    #[shaku(default)]
+    counter: Arc<AtomicU64>,
+}
+
+// 入力portで受信したパケットを、その時点の全複製先へそのまま送信し続ける
+async fn relay(socket: UdpSocket, recipients: Recipients) {
+    let mut buf = vec![0u8; MAX_PACKET_SIZE];
+    loop {
+        let len = match socket.recv_from(&mut buf).await {
+            Ok((len, _)) => len,
+            Err(_) => break,
+        };
+        // lockを保持したままawaitしないよう、送信先を取り出してから送信する
+        let targets: Vec<SocketAddr> = recipients
+            .lock()
+            .unwrap()
+            .values()
+            .map(|socket| SocketAddr::new(socket.ip(), socket.port()))
+            .collect();
+        for target in targets {
+            // 1つの複製先への送信失敗で他の複製先への送信を止めない
+            let _ = socket.send_to(&buf[..len], target).await;
+        }
+    }
+}
+
+fn not_found(fanout_id: &FanoutId) -> error::Error {
+    let message = format!("fan-out {} does not exist", fanout_id.as_str());
+    error::Error::create_local_error(&message)
+}
+
+#[async_trait]
+impl FanoutRepository for FanoutRepositoryImpl {
+    async fn create(
+        &self,
+        is_video: bool,
+        input: SocketInfo<PhantomId>,
+    ) -> Result<FanoutInfo, error::Error> {
+        let addr = SocketAddr::new(input.ip(), input.port());
+        let socket = UdpSocket::bind(addr).await.map_err(|e| {
+            let message = format!("failed to bind fan-out input {}: {:?}", addr, e);
+            error::Error::create_local_error(&message)
+        })?;
+        // port 0が指定された場合もあるので、実際にbindされたアドレスを返す
+        let local_addr = socket.local_addr().map_err(|e| {
+            let message = format!("failed to get fan-out input address {:?}", e);
+            error::Error::create_local_error(&message)
+        })?;
+        let input = SocketInfo::<PhantomId>::try_create(
+            None,
+            &local_addr.ip().to_string(),
+            local_addr.port(),
+        )?;
+
+        let fanout_id = FanoutId(format!(
+            "fo-{}",
+            self.counter.fetch_add(1, Ordering::SeqCst)
+        ));
+        let info = FanoutInfo {
+            fanout_id: fanout_id.clone(),
+            is_video,
+            input,
+        };
+        let recipients: Recipients = Arc::new(Mutex::new(HashMap::new()));
+        let handle = tokio::spawn(relay(socket, recipients.clone()));
+        self.relays.lock().unwrap().insert(
+            fanout_id,
+            Relay {
+                info: info.clone(),
+                recipients,
+                handle,
+            },
+        );
+        Ok(info)
+    }
+
+    fn delete(&self, fanout_id: &FanoutId) -> Result<FanoutInfo, error::Error> {
+        let relay = self
+            .relays
+            .lock()
+            .unwrap()
+            .remove(fanout_id)
+            .ok_or_else(|| not_found(fanout_id))?;
+        // taskを止めるとUdpSocketもdropされ、入力portが開放される
+        relay.handle.abort();
+        Ok(relay.info)
+    }
+
+    fn find(&self, fanout_id: &FanoutId) -> Option<FanoutInfo> {
+        self.relays
+            .lock()
+            .unwrap()
+            .get(fanout_id)
+            .map(|relay| relay.info.clone())
+    }
+
+    fn add_recipient(
+        &self,
+        fanout_id: &FanoutId,
+        media_connection_id: &MediaConnectionId,
+        media_socket: SocketInfo<MediaId>,
+    ) -> Result<(), error::Error> {
+        let relays = self.relays.lock().unwrap();
+        let relay = relays.get(fanout_id).ok_or_else(|| not_found(fanout_id))?;
+        relay.recipients.lock().unwrap().insert(
+            media_connection_id.as_str().to_string(),
+            media_socket.clone(),
+        );
+        self.sockets
+            .lock()
+            .unwrap()
+            .insert(media_connection_id.as_str().to_string(), media_socket);
+        Ok(())
+    }
+
+    fn remove_recipient(
+        &self,
+        media_connection_id: &MediaConnectionId,
+    ) -> Option<SocketInfo<MediaId>> {
+        for relay in self.relays.lock().unwrap().values() {
+            relay
+                .recipients
+                .lock()
+                .unwrap()
+                .remove(media_connection_id.as_str());
+        }
+        self.sockets
+            .lock()
+            .unwrap()
+            .remove(media_connection_id.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test_fanout {
+    use std::time::Duration;
+
+    use super::*;
+
+    // 入力portに送ったパケットが、全ての複製先に届くことを確認する
+    #[tokio::test]
+    async fn duplicate_packets() {
+        let repository = FanoutRepositoryImpl::default();
+        let input = SocketInfo::<PhantomId>::try_create(None, "127.0.0.1", 0).unwrap();
+        let info = repository.create(true, input).await.unwrap();
+
+        // 複製先として2つのsocketを用意する
+        let recv_1 = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let recv_2 = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        for (recv, id) in [
+            (&recv_1, "mc-50a32bab-b3d9-4913-8e20-f79c90a6a211"),
+            (&recv_2, "mc-4995f372-fb6a-4196-b30a-ce11e5c7f56c"),
+        ] {
+            let addr = recv.local_addr().unwrap();
+            let socket = SocketInfo::<MediaId>::try_create(
+                Some("vi-4d053831-5dc2-461b-a358-d062d6115216".into()),
+                &addr.ip().to_string(),
+                addr.port(),
+            )
+            .unwrap();
+            let media_connection_id = MediaConnectionId::try_create(id).unwrap();
+            repository
+                .add_recipient(&info.fanout_id, &media_connection_id, socket)
+                .unwrap();
+        }
+
+        // 入力portへ送信
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let input_addr = SocketAddr::new(info.input.ip(), info.input.port());
+        sender.send_to(b"rtp", input_addr).await.unwrap();
+
+        // 両方の複製先で受信できる
+        for recv in [&recv_1, &recv_2] {
+            let mut buf = [0u8; 16];
+            let len = tokio::time::timeout(Duration::from_secs(1), recv.recv(&mut buf))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(&buf[..len], b"rtp");
+        }
+
+        // 削除後は存在しない
+        let _ = repository.delete(&info.fanout_id).unwrap();
+        assert!(repository.find(&info.fanout_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn remove_recipient() {
+        let repository = FanoutRepositoryImpl::default();
+        let input = SocketInfo::<PhantomId>::try_create(None, "127.0.0.1", 0).unwrap();
+        let info = repository.create(false, input).await.unwrap();
+
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+        let socket = SocketInfo::<MediaId>::try_create(
+            Some("au-4d053831-5dc2-461b-a358-d062d6115216".into()),
+            "127.0.0.1",
+            10000,
+        )
+        .unwrap();
+        repository
+            .add_recipient(&info.fanout_id, &media_connection_id, socket.clone())
+            .unwrap();
+
+        // 1回目は登録したsocketが返り、2回目は既に外れている
+        assert_eq!(
+            repository.remove_recipient(&media_connection_id),
+            Some(socket)
+        );
+        assert_eq!(repository.remove_recipient(&media_connection_id), None);
+    }
+
+    // fan-outを削除した後でも、複製先だったmedia socketは取り出せる
+    #[tokio::test]
+    async fn remove_recipient_after_delete() {
+        let repository = FanoutRepositoryImpl::default();
+        let input = SocketInfo::<PhantomId>::try_create(None, "127.0.0.1", 0).unwrap();
+        let info = repository.create(true, input).await.unwrap();
+
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+        let socket = SocketInfo::<MediaId>::try_create(
+            Some("vi-4d053831-5dc2-461b-a358-d062d6115216".into()),
+            "127.0.0.1",
+            10000,
+        )
+        .unwrap();
+        repository
+            .add_recipient(&info.fanout_id, &media_connection_id, socket.clone())
+            .unwrap();
+        let _ = repository.delete(&info.fanout_id).unwrap();
+
+        assert_eq!(
+            repository.remove_recipient(&media_connection_id),
+            Some(socket)
+        );
+        assert_eq!(repository.remove_recipient(&media_connection_id), None);
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use shaku::*;
+
+use crate::domain::listener::entity::{ListenerHandle, ListenerInfo};
+use crate::domain::listener::repository::ListenerRegistry;
+
+// 監視ループを介さずに取得したeventを配信する
+pub(crate) mod publisher;
+
+#[derive(Default)]
+pub(crate) struct Listeners {
+    active: HashMap<ListenerInfo, ListenerHandle>,
+    // 停止を指示したが、まだ終了していないループ
+    stopping: HashMap<ListenerInfo, ListenerHandle>,
+    // DataConnection, MediaConnectionと、それを所有するPeerのpeer_id
+    owners: HashMap<ListenerInfo, String>,
+    next_generation: u64,
+}
+
+impl Listeners {
+    // 終了したループの記録を削除する。起動中のループとして記録されていた場合はtrueを返す
+    fn remove(&mut self, handle: &ListenerHandle) -> bool {
+        handle.finish();
+        let is_stopping = self
+            .stopping
+            .get(&handle.info)
+            .map(|stopping| stopping.generation == handle.generation)
+            .unwrap_or(false);
+        if is_stopping {
+            self.stopping.remove(&handle.info);
+        }
+        // 停止後に再度起動されたループの記録は削除しない
+        let is_same = self
+            .active
+            .get(&handle.info)
+            .map(|active| active.generation == handle.generation)
+            .unwrap_or(false);
+        if is_same {
+            self.active.remove(&handle.info);
+        }
+        is_same
+    }
+}
+
+// Serviceの具象Struct
+// 起動中と停止中の監視ループ、Connectionの所有者を1つのlockで記録する
+#[derive(Component, Clone, Default)]
+#[shaku(interface = ListenerRegistry)]
+pub(crate) struct ListenerRegistryImpl {
+    #[shaku(default)]
+    listeners: Arc<Mutex<Listeners>>,
+}
+
+impl ListenerRegistry for ListenerRegistryImpl {
+    fn start(&self, info: ListenerInfo) -> Option<ListenerHandle> {
+        let mut listeners = self.listeners.lock().unwrap();
+        if listeners.active.contains_key(&info) {
+            return None;
+        }
+        let mut handle = ListenerHandle::new(info.clone(), listeners.next_generation);
+        listeners.next_generation += 1;
+        // 停止中のループと同時にeventを取得すると、eventが重複・欠落するので、その終了を待たせる
+        if let Some(stopping) = listeners.stopping.get(&info) {
+            handle = handle.after(stopping);
+        }
+        listeners.active.insert(info, handle.clone());
+        Some(handle)
+    }
+
+    fn stop(&self, info: &ListenerInfo) -> bool {
+        let mut listeners = self.listeners.lock().unwrap();
+        match listeners.active.remove(info) {
+            Some(handle) => {
+                handle.stop();
+                listeners.stopping.insert(info.clone(), handle);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn finish(&self, handle: &ListenerHandle) {
+        let mut listeners = self.listeners.lock().unwrap();
+        if listeners.remove(handle) {
+            // 監視を終えたConnectionは既に閉じているので、所有者の記録も不要になる
+            listeners.owners.remove(&handle.info);
+        }
+    }
+
+    fn finish_wait(&self, handle: &ListenerHandle) {
+        let _ = self.listeners.lock().unwrap().remove(handle);
+    }
+
+    fn is_active(&self, info: &ListenerInfo) -> bool {
+        let listeners = self.listeners.lock().unwrap();
+        listeners.active.contains_key(info)
+    }
+
+    fn list(&self) -> Vec<ListenerInfo> {
+        let listeners = self.listeners.lock().unwrap();
+        let mut list: Vec<ListenerInfo> = listeners.active.keys().cloned().collect();
+        list.sort_by(|a, b| a.resource_id.cmp(&b.resource_id));
+        list
+    }
+
+    fn set_owner(&self, peer_id: &str, info: ListenerInfo) {
+        let mut listeners = self.listeners.lock().unwrap();
+        listeners.owners.insert(info, peer_id.to_string());
+    }
+
+    fn owner(&self, info: &ListenerInfo) -> Option<String> {
+        let listeners = self.listeners.lock().unwrap();
+        listeners.owners.get(info).cloned()
+    }
+
+    fn close_owned(&self, peer_id: &str) -> Vec<ListenerInfo> {
+        let mut listeners = self.listeners.lock().unwrap();
+        let owned: Vec<ListenerInfo> = listeners
+            .owners
+            .iter()
+            .filter(|(_, owner)| owner.as_str() == peer_id)
+            .map(|(info, _)| info.clone())
+            .collect();
+
+        let mut closed = vec![];
+        for info in owned {
+            listeners.owners.remove(&info);
+            if let Some(handle) = listeners.active.remove(&info) {
+                handle.close();
+                listeners.stopping.insert(info.clone(), handle);
+                closed.push(info);
+            }
+        }
+        closed.sort_by(|a, b| a.resource_id.cmp(&b.resource_id));
+        closed
+    }
+}
+
+#[cfg(test)]
+mod test_listener_registry {
+    use std::time::Duration;
+
+    use crate::domain::listener::entity::ListenerKind;
+
+    use super::*;
+
+    fn info() -> ListenerInfo {
+        ListenerInfo::new(
+            ListenerKind::Data,
+            "dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c",
+        )
+    }
+
+    #[test]
+    fn deduplicate() {
+        let registry = ListenerRegistryImpl::default();
+        let handle = registry.start(info()).unwrap();
+        // 同じリソースの監視ループは重複して起動しない
+        assert!(registry.clone().start(info()).is_none());
+        assert_eq!(registry.list(), vec![info()]);
+
+        // ループが終了すると再度起動できる
+        registry.finish(&handle);
+        assert!(registry.list().is_empty());
+        assert!(registry.start(info()).is_some());
+    }
+
+    #[tokio::test]
+    async fn stop() {
+        let registry = ListenerRegistryImpl::default();
+        let old = registry.start(info()).unwrap();
+
+        // 停止を指示するとループに通知される
+        assert!(registry.stop(&info()));
+        assert!(!old.is_running());
+        assert!(!registry.stop(&info()));
+
+        // 停止後に再度起動したループは、古いループが終了するまでeventの取得を待つ
+        let new = registry.start(info()).unwrap();
+        let mut wait = Box::pin(new.wait_previous());
+        let pending = tokio::time::timeout(Duration::from_millis(10), &mut wait).await;
+        assert!(pending.is_err());
+
+        // 停止後に再度起動したループの記録は、古いループの終了では消えない
+        registry.finish(&old);
+        wait.await;
+        assert!(registry.is_active(&info()));
+        registry.finish(&new);
+        assert!(!registry.is_active(&info()));
+    }
+
+    #[test]
+    fn close_owned() {
+        let registry = ListenerRegistryImpl::default();
+        let media = ListenerInfo::new(
+            ListenerKind::Media,
+            "mc-50a32bab-b3d9-4913-8e20-f79c90a6a211",
+        );
+        let other = ListenerInfo::new(
+            ListenerKind::Data,
+            "dc-102127d9-30de-413b-93f7-41a33e39d82b",
+        );
+
+        // 監視ループの起動前後どちらでも所有者を記録できる
+        registry.set_owner("peer_id", info());
+        let data_handle = registry.start(info()).unwrap();
+        let media_handle = registry.start(media.clone()).unwrap();
+        registry.set_owner("peer_id", media.clone());
+        // 監視していないリソースは停止対象にならない
+        registry.set_owner("peer_id", ListenerInfo::new(ListenerKind::Data, "dc-none"));
+        // 他のPeerのリソースは停止しない
+        let other_handle = registry.start(other.clone()).unwrap();
+        registry.set_owner("other_peer", other.clone());
+        assert_eq!(registry.owner(&media), Some("peer_id".to_string()));
+
+        let closed = registry.close_owned("peer_id");
+        assert_eq!(closed, vec![info(), media.clone()]);
+        assert!(data_handle.is_closed() && !data_handle.is_running());
+        assert!(media_handle.is_closed() && !media_handle.is_running());
+        assert!(other_handle.is_running());
+        assert_eq!(registry.list(), vec![other]);
+
+        // 所有者の記録も削除されている
+        assert!(registry.close_owned("peer_id").is_empty());
+        assert_eq!(registry.owner(&media), None);
+    }
+}
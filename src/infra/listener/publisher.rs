@@ -0,0 +1,263 @@
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+use shaku::*;
+use tokio::sync::mpsc;
+
+use crate::application::dto::response_message::{
+    DataResponse, MediaResponse, ResponseMessage, ResponseResult, SourcedEvent,
+};
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+use crate::domain::listener::repository::{EventPublisher, ListenerRegistry};
+use crate::domain::webrtc::data::entity::DataConnectionEventEnum;
+use crate::domain::webrtc::data::value_object::DataConnectionId;
+use crate::domain::webrtc::media::entity::MediaConnectionEventEnum;
+use crate::domain::webrtc::media::value_object::MediaConnectionId;
+use crate::hub::{EventFilter, EventHub, LagPolicy, ReplayFrom};
+use crate::infra::listener::ListenerRegistryImpl;
+
+// 監視ループが通知したeventを集約するchannelと、番号を振ったeventを配信するhub
+#[derive(Clone)]
+pub(crate) struct EventChannel {
+    event_tx: mpsc::Sender<SourcedEvent>,
+    hub: EventHub,
+    listeners: ListenerRegistryImpl,
+}
+
+// Serviceの具象Struct
+// 監視ループと同じchannelに送信することで、通知順の番号を振らせる
+// channelを持たないdefaultの状態では、eventを破棄し、購読しても何も受け取らない
+#[derive(Component, Clone, Default)]
+#[shaku(interface = EventPublisher)]
+pub(crate) struct EventPublisherImpl {
+    #[shaku(default)]
+    channel: Option<EventChannel>,
+}
+
+impl EventPublisherImpl {
+    pub(crate) fn new(
+        event_tx: mpsc::Sender<SourcedEvent>,
+        hub: EventHub,
+        listeners: ListenerRegistryImpl,
+    ) -> Self {
+        EventPublisherImpl {
+            channel: Some(EventChannel {
+                event_tx,
+                hub,
+                listeners,
+            }),
+        }
+    }
+
+    async fn publish(&self, source: ListenerInfo, event: ResponseResult) {
+        if let Some(ref channel) = self.channel {
+            // 監視ループと同様に、リソースを所有するPeerを添える
+            let peer_id = channel.listeners.owner(&source);
+            let event = SourcedEvent {
+                source,
+                peer_id,
+                event,
+            };
+            let _ = channel.event_tx.send(event).await;
+        }
+    }
+
+    fn subscribe(&self, source: ListenerInfo) -> BoxStream<'static, ResponseResult> {
+        let channel = match self.channel {
+            Some(ref channel) => channel,
+            None => return stream::empty().boxed(),
+        };
+        let filter = EventFilter {
+            kind: Some(source.kind),
+            event: None,
+            resource_id: Some(source.resource_id),
+        };
+        // 購読前に配信されたeventも受け取れるよう、履歴から再送させる
+        // 待機中に溢れても、対象のeventでなければ問題ないので、取りこぼしは無視する
+        let subscription = channel
+            .hub
+            .subscribe_from(filter, LagPolicy::Skip, ReplayFrom::Seq(0));
+        stream::unfold(subscription, |mut subscription| async move {
+            let envelope = subscription.recv_envelope().await.ok()?;
+            Some((envelope.event.clone(), subscription))
+        })
+        .boxed()
+    }
+}
+
+#[async_trait]
+impl EventPublisher for EventPublisherImpl {
+    async fn publish_data(
+        &self,
+        data_connection_id: &DataConnectionId,
+        event: DataConnectionEventEnum,
+    ) {
+        let source = ListenerInfo::new(ListenerKind::Data, data_connection_id.as_str());
+        let event = DataResponse::Event(event).create_response_message();
+        self.publish(source, event).await;
+    }
+
+    fn subscribe_data(
+        &self,
+        data_connection_id: &DataConnectionId,
+    ) -> BoxStream<'static, DataConnectionEventEnum> {
+        let source = ListenerInfo::new(ListenerKind::Data, data_connection_id.as_str());
+        self.subscribe(source)
+            .filter_map(|event| async move {
+                match event {
+                    ResponseResult::Success(ResponseMessage::Data(DataResponse::Event(event))) => {
+                        Some(event)
+                    }
+                    _ => None,
+                }
+            })
+            .boxed()
+    }
+
+    async fn publish_media(
+        &self,
+        media_connection_id: &MediaConnectionId,
+        event: MediaConnectionEventEnum,
+    ) {
+        let source = ListenerInfo::new(ListenerKind::Media, media_connection_id.as_str());
+        let event = MediaResponse::Event(event).create_response_message();
+        self.publish(source, event).await;
+    }
+
+    fn subscribe_media(
+        &self,
+        media_connection_id: &MediaConnectionId,
+    ) -> BoxStream<'static, MediaConnectionEventEnum> {
+        let source = ListenerInfo::new(ListenerKind::Media, media_connection_id.as_str());
+        self.subscribe(source)
+            .filter_map(|event| async move {
+                match event {
+                    ResponseResult::Success(ResponseMessage::Media(MediaResponse::Event(
+                        event,
+                    ))) => Some(event),
+                    _ => None,
+                }
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test_event_publisher {
+    use crate::application::dto::response_message::EventEnvelope;
+    use crate::domain::webrtc::data::entity::DataConnectionIdWrapper;
+    use crate::domain::webrtc::media::entity::MediaConnectionIdWrapper;
+    use crate::hub::{HistoryLimit, QueueConfig};
+
+    use super::*;
+
+    fn data_connection_id() -> DataConnectionId {
+        DataConnectionId::try_create("dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c").unwrap()
+    }
+
+    fn open_event() -> DataConnectionEventEnum {
+        DataConnectionEventEnum::OPEN(DataConnectionIdWrapper {
+            data_connection_id: data_connection_id(),
+        })
+    }
+
+    #[tokio::test]
+    async fn publish_with_owner() {
+        let (event_tx, mut event_rx) = mpsc::channel(10);
+        let hub = EventHub::new(QueueConfig::default(), HistoryLimit::default());
+        let listeners = ListenerRegistryImpl::default();
+        let source = ListenerInfo::new(ListenerKind::Data, data_connection_id().as_str());
+        listeners.set_owner("peer_id", source.clone());
+        let publisher = EventPublisherImpl::new(event_tx, hub, listeners);
+
+        publisher
+            .publish_data(&data_connection_id(), open_event())
+            .await;
+
+        // 監視ループが通知したeventと同じ形で送信される
+        let expected = SourcedEvent {
+            source,
+            peer_id: Some("peer_id".into()),
+            event: DataResponse::Event(open_event()).create_response_message(),
+        };
+        assert_eq!(event_rx.recv().await, Some(expected));
+    }
+
+    #[tokio::test]
+    async fn subscribe_from_history() {
+        let (event_tx, _event_rx) = mpsc::channel(10);
+        let hub = EventHub::new(QueueConfig::default(), HistoryLimit::default());
+        let publisher =
+            EventPublisherImpl::new(event_tx, hub.clone(), ListenerRegistryImpl::default());
+        let source = ListenerInfo::new(ListenerKind::Data, data_connection_id().as_str());
+        let other = ListenerInfo::new(
+            ListenerKind::Data,
+            "dc-102127d9-30de-413b-93f7-41a33e39d82b",
+        );
+
+        // 購読前に配信されたeventも受け取れる
+        let close = DataConnectionEventEnum::CLOSE(DataConnectionIdWrapper {
+            data_connection_id: data_connection_id(),
+        });
+        let events = vec![
+            (other, open_event()),
+            (source.clone(), open_event()),
+            (source, close.clone()),
+        ];
+        for (seq, (source, event)) in events.into_iter().enumerate() {
+            let event = SourcedEvent {
+                source,
+                peer_id: None,
+                event: DataResponse::Event(event).create_response_message(),
+            };
+            hub.publish(EventEnvelope::new(seq as u64 + 1, event)).await;
+        }
+
+        // 他のDataConnectionのeventは受け取らない
+        let events = publisher.subscribe_data(&data_connection_id());
+        let events: Vec<_> = events.take(2).collect().await;
+        assert_eq!(events, vec![open_event(), close]);
+    }
+
+    #[tokio::test]
+    async fn media_events() {
+        let (event_tx, mut event_rx) = mpsc::channel(10);
+        let hub = EventHub::new(QueueConfig::default(), HistoryLimit::default());
+        let publisher =
+            EventPublisherImpl::new(event_tx, hub.clone(), ListenerRegistryImpl::default());
+        let media_connection_id =
+            MediaConnectionId::try_create("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211").unwrap();
+        let ready = MediaConnectionEventEnum::READY(MediaConnectionIdWrapper {
+            media_connection_id: media_connection_id.clone(),
+        });
+
+        // MediaConnectionのeventとして送信される
+        publisher
+            .publish_media(&media_connection_id, ready.clone())
+            .await;
+        let event = event_rx.recv().await.unwrap();
+        assert_eq!(
+            event.source,
+            ListenerInfo::new(ListenerKind::Media, media_connection_id.as_str())
+        );
+
+        // hubが配信したeventをMediaConnectionのeventとして受け取る
+        let events = publisher.subscribe_media(&media_connection_id);
+        hub.publish(EventEnvelope::new(1, event)).await;
+        let events: Vec<_> = events.take(1).collect().await;
+        assert_eq!(events, vec![ready]);
+    }
+
+    #[tokio::test]
+    async fn not_injected() {
+        // 注入されていない場合は、何も受け取らない
+        let publisher = EventPublisherImpl::default();
+        publisher
+            .publish_data(&data_connection_id(), open_event())
+            .await;
+        let events: Vec<_> = publisher
+            .subscribe_data(&data_connection_id())
+            .collect()
+            .await;
+        assert!(events.is_empty());
+    }
+}
@@ -1,13 +1,29 @@
 // Domain層で定義されている機能を実装する
-// 現状このレイヤで実装されている機能は以下の2つである
+// 現状このレイヤで実装されている機能は以下の5つである
 // ・アプリケーションが実行中であるかどうかを提示するStruct
 // ・SkyWay WebRTC GatewayのAPIを叩くためのStruct
+// ・ローカルのRTP入力を複製するStruct
+// ・このcrateで生成したリソースを記録するStruct
+// ・起動中のevent監視ループを記録するStruct
 
-// 前者は、event loop内でのexit判定に利用される
+// 1つめは、event loop内でのexit判定に利用される
 // stateモジュールとして実装される
 //
-// 後者はskyway-webrtc-gateway-api crateの機能をDomain層の定義と合わせるための薄いラッパーである
+// 2つめはskyway-webrtc-gateway-api crateの機能をDomain層の定義と合わせるための薄いラッパーである
 // webrtcモジュールとして実装される
+//
+// 3つめはtokioのUdpSocketを利用してRTPパケットを中継する
+// fanoutモジュールとして実装される
+//
+// 4つめはlib.rs内のfoldが保持し、UseCase間で共有されるインメモリの記録である
+// registryモジュールとして実装される
+//
+// 5つめも同様にlib.rs内のfoldが保持し、event監視ループの重複防止と停止に利用される
+// 監視ループを介さずに取得したeventを、監視ループと同じ経路で配信するStructも合わせて実装する
+// listenerモジュールとして実装される
 
+pub(crate) mod fanout;
+pub(crate) mod listener;
+pub(crate) mod registry;
 pub(crate) mod state;
 pub(crate) mod webrtc;
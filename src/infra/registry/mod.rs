@@ -0,0 +1,250 @@
+use std::collections::{HashMap, HashSet};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+
+use shaku::*;
+
+use crate::config::Config;
+use crate::domain::registry::entity::{addresses_overlap, MediaKind};
+use crate::domain::registry::repository::ResourceRegistry;
+use crate::domain::webrtc::common::value_object::SerializableId;
+use crate::domain::webrtc::media::value_object::{MediaId, RtcpId};
+use crate::error;
+
+#[derive(Default)]
+pub(crate) struct Resources {
+    // media_idをkeyとする
+    media: HashMap<String, (MediaKind, SocketAddr)>,
+    // rtcp_idをkeyとする
+    rtcp: HashMap<String, SocketAddr>,
+    // media_connection_id, data_connection_idをkeyとする
+    redirects: HashMap<String, Vec<SocketAddr>>,
+    // allocate_portで確保し、まだConnectionに紐付いていないアドレス
+    pending: HashSet<SocketAddr>,
+    // 次に割り当てを試みるport
+    // 開放直後のportがすぐに再利用されないよう、範囲内を順に巡回する
+    next_port: Option<u16>,
+}
+
+impl Resources {
+    fn is_used(&self, address: &SocketAddr) -> bool {
+        self.pending.contains(address)
+            || self
+                .redirects
+                .values()
+                .flatten()
+                .any(|a| addresses_overlap(a, address))
+    }
+}
+
+// Serviceの具象Struct
+// redirect先の自動割り当てには、Configのaddressとportの範囲を利用する
+// newを介さずに生成した場合は、Config::default()の設定を利用する
+#[derive(Component, Clone, Default)]
+#[shaku(interface = ResourceRegistry)]
+pub(crate) struct ResourceRegistryImpl {
+    #[shaku(default)]
+    resources: Arc<Mutex<Resources>>,
+    #[shaku(default)]
+    config: Arc<Config>,
+}
+
+impl ResourceRegistryImpl {
+    pub(crate) fn new(config: &Config) -> Self {
+        ResourceRegistryImpl {
+            resources: Arc::new(Mutex::new(Resources::default())),
+            config: Arc::new(config.clone()),
+        }
+    }
+}
+
+impl ResourceRegistry for ResourceRegistryImpl {
+    fn register_media(&self, media_id: &MediaId, kind: MediaKind, address: SocketAddr) {
+        let mut resources = self.resources.lock().unwrap();
+        resources
+            .media
+            .insert(media_id.as_str().to_string(), (kind, address));
+    }
+
+    fn unregister_media(&self, media_id: &MediaId) {
+        let mut resources = self.resources.lock().unwrap();
+        resources.media.remove(media_id.as_str());
+    }
+
+    fn find_media(&self, media_id: &MediaId) -> Option<MediaKind> {
+        let resources = self.resources.lock().unwrap();
+        resources
+            .media
+            .get(media_id.as_str())
+            .map(|(kind, _)| *kind)
+    }
+
+    fn media_address(&self, media_id: &MediaId) -> Option<SocketAddr> {
+        let resources = self.resources.lock().unwrap();
+        resources
+            .media
+            .get(media_id.as_str())
+            .map(|(_, address)| *address)
+    }
+
+    fn register_rtcp(&self, rtcp_id: &RtcpId, address: SocketAddr) {
+        let mut resources = self.resources.lock().unwrap();
+        resources.rtcp.insert(rtcp_id.as_str().to_string(), address);
+    }
+
+    fn unregister_rtcp(&self, rtcp_id: &RtcpId) {
+        let mut resources = self.resources.lock().unwrap();
+        resources.rtcp.remove(rtcp_id.as_str());
+    }
+
+    fn contains_rtcp(&self, rtcp_id: &RtcpId) -> bool {
+        let resources = self.resources.lock().unwrap();
+        resources.rtcp.contains_key(rtcp_id.as_str())
+    }
+
+    fn rtcp_address(&self, rtcp_id: &RtcpId) -> Option<SocketAddr> {
+        let resources = self.resources.lock().unwrap();
+        resources.rtcp.get(rtcp_id.as_str()).copied()
+    }
+
+    fn reserve_redirect(&self, connection_id: &str, addresses: Vec<SocketAddr>) {
+        let mut resources = self.resources.lock().unwrap();
+        for address in addresses.iter() {
+            resources.pending.remove(address);
+        }
+        if addresses.is_empty() {
+            resources.redirects.remove(connection_id);
+        } else {
+            resources
+                .redirects
+                .insert(connection_id.to_string(), addresses);
+        }
+    }
+
+    fn release_redirect(&self, connection_id: &str) {
+        let mut resources = self.resources.lock().unwrap();
+        resources.redirects.remove(connection_id);
+    }
+
+    fn redirect_owner(&self, address: &SocketAddr) -> Option<String> {
+        let resources = self.resources.lock().unwrap();
+        resources
+            .redirects
+            .iter()
+            .find(|(_, addresses)| addresses.iter().any(|a| addresses_overlap(a, address)))
+            .map(|(connection_id, _)| connection_id.clone())
+    }
+
+    fn allocate_port(&self) -> Result<SocketAddr, error::Error> {
+        let start = *self.config.redirect_ports.start();
+        let end = *self.config.redirect_ports.end();
+        let mut resources = self.resources.lock().unwrap();
+        let first = resources
+            .next_port
+            .filter(|port| self.config.redirect_ports.contains(port))
+            .unwrap_or(start);
+        for port in (first..=end).chain(start..first) {
+            let address = SocketAddr::new(self.config.redirect_ip, port);
+            if resources.is_used(&address) {
+                continue;
+            }
+            // 他のプロセスが利用中のportは割り当てない
+            if UdpSocket::bind(address).is_err() {
+                continue;
+            }
+            resources.pending.insert(address);
+            resources.next_port = port.checked_add(1);
+            return Ok(address);
+        }
+
+        let message = format!("no free port in redirect port range {}-{}", start, end);
+        Err(error::Error::create_local_error(&message))
+    }
+
+    fn release_ports(&self, addresses: &[SocketAddr]) {
+        let mut resources = self.resources.lock().unwrap();
+        for address in addresses {
+            resources.pending.remove(address);
+        }
+    }
+
+    fn allocates_omitted(&self) -> bool {
+        self.config.allocate_omitted_redirects
+    }
+}
+
+#[cfg(test)]
+mod test_registry {
+    use super::*;
+
+    #[test]
+    fn media() {
+        let registry = ResourceRegistryImpl::default();
+        let media_id = MediaId::try_create("vi-4d053831-5dc2-461b-a358-d062d6115216").unwrap();
+
+        let address: SocketAddr = "127.0.0.1:10000".parse().unwrap();
+
+        registry.register_media(&media_id, MediaKind::Video, address);
+        // cloneしたインスタンス間で記録が共有される
+        let cloned = registry.clone();
+        assert_eq!(cloned.find_media(&media_id), Some(MediaKind::Video));
+        assert_eq!(cloned.media_address(&media_id), Some(address));
+
+        cloned.unregister_media(&media_id);
+        assert_eq!(registry.find_media(&media_id), None);
+    }
+
+    #[test]
+    fn redirect() {
+        let registry = ResourceRegistryImpl::default();
+        let media_connection_id = "mc-50a32bab-b3d9-4913-8e20-f79c90a6a211";
+        let address: SocketAddr = "127.0.0.1:13000".parse().unwrap();
+
+        registry.reserve_redirect(media_connection_id, vec![address]);
+        assert_eq!(
+            registry.redirect_owner(&address),
+            Some(media_connection_id.to_string())
+        );
+        // 0.0.0.0でbindするアドレスとも重なる
+        let any: SocketAddr = "0.0.0.0:13000".parse().unwrap();
+        assert!(registry.redirect_owner(&any).is_some());
+        // 別のportとは重ならない
+        let other: SocketAddr = "127.0.0.1:13001".parse().unwrap();
+        assert!(registry.redirect_owner(&other).is_none());
+
+        registry.release_redirect(media_connection_id);
+        assert!(registry.redirect_owner(&address).is_none());
+    }
+
+    #[test]
+    fn allocate_port() {
+        let config = Config {
+            redirect_ports: 43000..=43002,
+            ..Config::default()
+        };
+        let registry = ResourceRegistryImpl::new(&config);
+        // 他のプロセスが利用中のportを用意する
+        let _occupied = UdpSocket::bind("127.0.0.1:43001");
+        // 他のConnectionが利用中のportを用意する
+        registry.reserve_redirect(
+            "dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c",
+            vec!["127.0.0.1:43000".parse().unwrap()],
+        );
+
+        // 空いているportのみが割り当てられる
+        let address = registry.allocate_port().unwrap();
+        assert_eq!(address, "127.0.0.1:43002".parse::<SocketAddr>().unwrap());
+        // 割り当て済みのportは、Connectionに紐付く前でも再度割り当てられない
+        assert!(registry.allocate_port().is_err());
+
+        // 開放したportは再度割り当てられる
+        registry.release_ports(&[address]);
+        assert_eq!(registry.allocate_port().unwrap(), address);
+
+        // Connectionの終了時に開放される
+        registry.reserve_redirect("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211", vec![address]);
+        assert!(registry.allocate_port().is_err());
+        registry.release_redirect("mc-50a32bab-b3d9-4913-8e20-f79c90a6a211");
+        assert_eq!(registry.allocate_port().unwrap(), address);
+    }
+}
@@ -1,5 +1,6 @@
 use shaku::*;
 
+use crate::domain::listener::entity::ListenerHandle;
 use crate::domain::state::ApplicationState;
 
 // Serviceの具象Struct
@@ -25,3 +26,21 @@ impl ApplicationState for ApplicationStateAlwaysFalseImpl {
         false
     }
 }
+
+// 監視ループごとに生成されるStateの実装
+// UNSUBSCRIBEで停止を指示されるとfalseを返す
+pub(crate) struct ApplicationStateListenerImpl {
+    handle: ListenerHandle,
+}
+
+impl ApplicationStateListenerImpl {
+    pub(crate) fn new(handle: ListenerHandle) -> Self {
+        ApplicationStateListenerImpl { handle }
+    }
+}
+
+impl ApplicationState for ApplicationStateListenerImpl {
+    fn is_running(&self) -> bool {
+        self.handle.is_running()
+    }
+}
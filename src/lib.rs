@@ -75,22 +75,44 @@
 // Domain層はDomain ObjectをInfra層の関数に与え、
 // Infra層はskyway-webrtc-gateway-api crateのAPIから返される戻り値をDomain Objectに変換して返す。
 
-use futures::stream::StreamExt;
+use futures::stream::{self, BoxStream, StreamExt};
 use tokio::sync::{mpsc, oneshot};
 use tokio_stream::wrappers::ReceiverStream;
 
-use crate::application::dto::response_message::ResponseResult;
+use crate::application::dto::request_message::ServiceParams;
+use crate::application::dto::response_message::{
+    EventEnvelope, GatewayEvent, ResponseResult, SourcedEvent,
+};
+use crate::config::Config;
+use crate::di::SharedState;
+use crate::domain::listener::entity::{ListenerInfo, ListenerKind};
+use crate::domain::listener::repository::ListenerRegistry;
+use crate::hub::EventHub;
 use crate::presentation::serialize_service_params;
 
 pub(crate) mod application;
+/// Synchronous API for programs not using tokio.
+pub mod blocking;
+/// Configuration given when starting this crate.
+pub mod config;
 pub(crate) mod di;
 pub(crate) mod domain;
 /// Error definition in this crate.
 pub mod error;
+/// C interface for programs written in other languages. See `include/skyway_caller.h`.
+#[cfg(feature = "ffi")]
+pub mod ffi;
+/// gRPC service defined in `proto/skyway_caller.proto`.
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub(crate) mod hub;
 pub(crate) mod infra;
 /// A "prelude" for crates using this crate.
 pub mod prelude;
 pub(crate) mod presentation;
+#[cfg(feature = "python")]
+pub(crate) mod python;
+pub(crate) mod session;
 
 // Presentation層としてchannelを生成し、Application層以降のパイプラインを組み上げる関数。
 // 外部から直接的に呼ばれるのはこの関数のみである。
@@ -104,6 +126,32 @@ pub async fn run(
 ) -> (
     mpsc::Sender<(oneshot::Sender<String>, String)>,
     mpsc::Receiver<String>,
+) {
+    run_with_config(base_url, Config::default()).await
+}
+
+/// Start WebRTC Gateway operation with the given configuration.
+pub async fn run_with_config(
+    base_url: &str,
+    config: Config,
+) -> (
+    mpsc::Sender<(oneshot::Sender<String>, String)>,
+    mpsc::Receiver<String>,
+) {
+    let (message_tx, event_rx, _) = run_with_event_hub(base_url, config).await;
+    (message_tx, event_rx)
+}
+
+/// Start WebRTC Gateway operation with the given configuration.
+/// In addition to the Receiver, it provides an EventHub to add more receivers of events.
+/// Recent events are kept in the EventHub so that a late receiver can replay them.
+pub async fn run_with_event_hub(
+    base_url: &str,
+    config: Config,
+) -> (
+    mpsc::Sender<(oneshot::Sender<String>, String)>,
+    mpsc::Receiver<String>,
+    EventHub,
 ) {
     // skyway-webrtc-gateway crateにbase_urlを与え、初期化する
     skyway_webrtc_gateway_api::initialize(base_url);
@@ -116,25 +164,80 @@ pub async fn run(
     // UseCaseでの処理の結果が`一次的な結果`に留まらず、副作用としてイベント監視の必要性が生じた場合は、
     // このReceiverを介してイベントをEnd-Userに返す。
     // TODO: タイムアウトの仕様を検討する
-    let (event_tx, event_rx) = mpsc::channel::<ResponseResult>(10);
+    // 監視ループはこのchannelが空くまで待機するので、容量はConfigで調整できる
+    let (event_tx, event_rx) =
+        mpsc::channel::<SourcedEvent>(config.event_channel.listener_capacity);
+    // 全ての購読者には、同じ番号を振ったeventを配信する
+    // 購読前に発生したeventも再送できるよう、hubは直近のeventを履歴として保持する
+    let channel_config = config.event_channel.clone();
+    let hub = EventHub::new(
+        channel_config.subscriber_queue,
+        config.event_history.clone(),
+    );
 
     // Senderの監視を開始する。
     // 副作用としてイベントを返すケースのため、event_txも渡す
     // (例: peer objectを生成したらpeer eventの監視を合わせて開始する)
-    tokio::spawn(skyway_control_service_observe(message_rx, event_tx));
+    // AWAIT commandはhubの購読者として処理するので、hubも渡す
+    tokio::spawn(skyway_control_service_observe(
+        message_rx,
+        event_tx,
+        hub.clone(),
+        config,
+    ));
 
     // Presentation層の責務として、ObjectをJSONメッセージに変換して返す
-    let mut event_rx =
-        ReceiverStream::new(event_rx).map(|params| presentation::serialize_service_params(&params));
-    let (tx, rx) = mpsc::channel::<String>(1000);
+    // 全ての監視ループのeventはこのchannelに集約されるので、ここで通知順に番号を振る
+    let mut event_rx = ReceiverStream::new(event_rx)
+        .enumerate()
+        .map(|(index, event)| EventEnvelope::new(index as u64 + 1, event));
+    // End-Userに渡すReceiverも購読者の1つとして扱い、queueが溢れた場合はOverflowPolicyに従う
+    // queueがバッファの役割を果たすので、channel自体の容量は1で良い
+    let mut subscription = hub.subscribe_receiver(channel_config.receiver_queue);
+    let publisher = hub.clone();
+    tokio::spawn(async move {
+        while let Some(envelope) = event_rx.next().await {
+            publisher.publish(envelope).await;
+        }
+        publisher.close();
+    });
+
+    let (tx, rx) = mpsc::channel::<String>(1);
     tokio::spawn(async move {
-        while let Some(item) = event_rx.next().await {
+        while let Ok(item) = subscription.recv().await {
+            // End-UserがReceiverを破棄した場合は購読を終了し、他の購読者への配信を妨げないようにする
             if tx.send(item).await.is_err() {
                 break;
             }
         }
     });
-    (message_tx, rx)
+    (message_tx, rx, hub)
+}
+
+/// Start WebRTC Gateway operation with the given configuration.
+/// Instead of JSON messages, events are provided as a stream of typed GatewayEvent.
+pub async fn run_with_event_stream(
+    base_url: &str,
+    config: Config,
+) -> (
+    mpsc::Sender<(oneshot::Sender<String>, String)>,
+    BoxStream<'static, GatewayEvent>,
+) {
+    let receiver_queue = config.event_channel.receiver_queue.clone();
+    // JSONのReceiverは利用しないので破棄する。中継タスクは最初のeventの中継に失敗した時点で終了する
+    let (message_tx, _, hub) = run_with_event_hub(base_url, config).await;
+    let subscription = hub.subscribe_receiver(receiver_queue);
+    // JSONを経由せず、Objectから直接GatewayEventに変換する
+    let stream = stream::unfold(subscription, |mut subscription| async move {
+        loop {
+            let envelope = subscription.recv_envelope().await.ok()?;
+            if let Some(event) = GatewayEvent::from_response(envelope.event.clone()) {
+                return Some((event, subscription));
+            }
+        }
+    })
+    .boxed();
+    (message_tx, stream)
 }
 
 // End-Userからのメッセージ(ServiceParams)を監視し続ける
@@ -144,15 +247,44 @@ pub async fn run(
 // なお、Unit Testは行わずIntegration Testでのみテストを行う
 async fn skyway_control_service_observe(
     receiver: mpsc::Receiver<(oneshot::Sender<String>, String)>,
-    event_tx: mpsc::Sender<ResponseResult>,
+    event_tx: mpsc::Sender<SourcedEvent>,
+    hub: EventHub,
+    config: Config,
 ) {
     // FIXME
     // jsonをどんどん受け取る
     let receiver = ReceiverStream::new(receiver);
+    // UseCase間で共有する状態はこのfoldのみが保持する
+    let state = SharedState::new(&config, event_tx.clone(), hub.clone());
     receiver
         .fold(
-            event_tx,
-            |event_tx, (message_response_tx, message)| async move {
+            (event_tx, state, hub),
+            |(event_tx, state, hub), (message_response_tx, message)| async move {
+                // AWAITはUseCaseを実行せず、一致するeventを待って返す
+                // 待機中も他のメッセージを処理できるよう、待機は別タスクで行う
+                if let Some(params) = presentation::format_await_json(&message) {
+                    match params {
+                        Ok(params) => {
+                            // 購読はこの時点で開始する
+                            let wait = hub.await_event(params);
+                            tokio::spawn(async move {
+                                let message = match wait.await {
+                                    Ok(envelope) => presentation::serialize_event(&envelope),
+                                    Err(reason) => {
+                                        serialize_service_params(&ResponseResult::Error(reason))
+                                    }
+                                };
+                                let _ = message_response_tx.send(message);
+                            });
+                        }
+                        Err(e) => {
+                            let message = ResponseResult::Error(format!("{:?}", e));
+                            let _ = message_response_tx.send(serialize_service_params(&message));
+                        }
+                    }
+                    return (event_tx, state, hub);
+                }
+
                 // JSONをパースし、アプリケーション層に渡す
                 // このJSONは呼び出されるべきサービスの情報を含んでおり、アプリケーション層で適切に呼び出す
                 let result = presentation::format_input_json(&message).await;
@@ -165,33 +297,118 @@ async fn skyway_control_service_observe(
                         e
                     ));
                     let _ = message_response_tx.send(serialize_service_params(&message));
-                    return event_tx;
+                    return (event_tx, state, hub);
                 }
 
                 let message = result.unwrap();
-                let result = application::run(message).await;
-
-                // oneshot channelを介してサービス実行によって得られた `一次的な結果` を返す。
-                // サービスの実行結果がエラーの場合でも、エラーを示すJSONメッセージが返される(ResponseMessage::ERROR)のでそのままPresentation層へ渡す
-                let _ = message_response_tx.send(serialize_service_params(&result));
-
-                // イベントを監視する必要が生じた場合は、イベントの監視を開始する
-                // まずイベント監視する必要があるのは、サービス実行に成功したケースのみである
-                if let ResponseResult::Success(message) = result {
-                    // event factoryに渡し、監視サービスが生成された場合
-                    if let Some((value, service)) =
-                        application::usecase::factory::event_factory(message)
-                    {
-                        // event_txをイベント監視スレッドにmoveし、監視を開始する
-                        let tx = event_tx.clone();
-                        tokio::spawn(async move {
-                            service.execute(tx, value).await;
-                        });
-                    }
+                let wait = message.waits_for_event();
+                let task = execute(
+                    message,
+                    message_response_tx,
+                    event_tx.clone(),
+                    state.clone(),
+                );
+                if wait {
+                    // eventを待つコマンドは、待機中も他のメッセージを処理できるよう別タスクで実行する
+                    tokio::spawn(task);
+                } else {
+                    task.await;
                 }
 
-                event_tx
+                (event_tx, state, hub)
             },
         )
         .await;
 }
+
+// UseCaseを実行し、結果をoneshot channelで返す
+async fn execute(
+    message: ServiceParams,
+    message_response_tx: oneshot::Sender<String>,
+    event_tx: mpsc::Sender<SourcedEvent>,
+    state: SharedState,
+) {
+    let result = application::run(message, &state).await;
+
+    // oneshot channelを介してサービス実行によって得られた `一次的な結果` を返す。
+    // サービスの実行結果がエラーの場合でも、エラーを示すJSONメッセージが返される(ResponseMessage::ERROR)のでそのままPresentation層へ渡す
+    let _ = message_response_tx.send(serialize_service_params(&result));
+
+    // イベントを監視する必要が生じた場合は、イベントの監視を開始する
+    // まずイベント監視する必要があるのは、サービス実行に成功したケースのみである
+    if let ResponseResult::Success(message) = result {
+        // event factoryに渡し、監視サービスが生成された場合
+        // 同じリソースを監視中のループが既にある場合は生成されない
+        if let Some(task) = application::usecase::factory::event_factory(message, &state) {
+            spawn_listener(task, event_tx, state);
+        }
+    }
+}
+
+// event監視ループを起動する
+// ループが通知するeventは中継してEnd-Userに返し、
+// その中に新たな監視が必要なもの(CONNECTION, CALL等)があれば、その監視ループも起動する
+fn spawn_listener(
+    task: application::usecase::factory::EventTask,
+    event_tx: mpsc::Sender<SourcedEvent>,
+    state: SharedState,
+) {
+    let (value, service, handle) = task;
+    tokio::spawn(async move {
+        let (tx, mut rx) = mpsc::channel::<ResponseResult>(10);
+        let relay = {
+            let event_tx = event_tx.clone();
+            let state = state.clone();
+            let source = handle.info.clone();
+            tokio::spawn(async move {
+                // 所有者はCONNECTION, CALLの通知後に記録されることもあるので、eventごとに確認する
+                // 終了時には記録が削除されているので、最後に確認できた所有者を返す
+                let mut peer_id = None;
+                while let Some(message) = rx.recv().await {
+                    peer_id = owner(&state, &source).or(peer_id);
+                    let event = SourcedEvent {
+                        source: source.clone(),
+                        peer_id: peer_id.clone(),
+                        event: message.clone(),
+                    };
+                    let _ = event_tx.send(event).await;
+                    if let ResponseResult::Success(message) = message {
+                        if let Some(task) =
+                            application::usecase::factory::event_factory(message, &state)
+                        {
+                            spawn_listener(task, event_tx.clone(), state.clone());
+                        }
+                    }
+                }
+                peer_id
+            })
+        };
+
+        // 停止を指示された古いループが残っている場合は、その終了を待ってから監視を始める
+        handle.wait_previous().await;
+        let result = service.execute(tx, value).await;
+        // 中継し終えてから、後続の処理を行う
+        let peer_id = relay.await.ok().flatten();
+        let peer_id = owner(&state, &handle.info).or(peer_id);
+        // 監視が終了したので、記録を削除する
+        state.listeners.finish(&handle);
+        // 所有するPeerのCLOSEによって停止した場合は、CLOSE eventを補完する
+        if let Some(message) = application::usecase::factory::close_event(&handle, &result) {
+            let event = SourcedEvent {
+                source: handle.info.clone(),
+                peer_id,
+                event: message,
+            };
+            let _ = event_tx.send(event).await;
+        }
+    });
+}
+
+// eventの発生元のリソースを所有するPeerのpeer_id
+// Peer自身の監視ループの場合は、そのPeerを返す
+fn owner(state: &SharedState, source: &ListenerInfo) -> Option<String> {
+    match source.kind {
+        ListenerKind::Peer => Some(source.resource_id.clone()),
+        _ => state.listeners.owner(source),
+    }
+}
@@ -4,6 +4,32 @@ pub mod response_parser {
     pub use crate::application::dto::response_message::*;
 }
 
+/// Provide objects to build JSON messages for operation as Rust objects
+pub mod request_message {
+    pub use crate::application::dto::request_message::{
+        DataServiceParams, ListenerServiceParams, MediaServiceParams, Parameter, PeerServiceParams,
+        ServiceParams,
+    };
+}
+
+/// Configuration given to `run_with_config`
+pub use crate::config::Config;
+
+/// Provide objects to add receivers of events via `run_with_event_hub`
+pub mod hub {
+    pub use crate::hub::{
+        EventChannelConfig, EventFilter, EventHub, EventSubscription, HistoryLimit, LagPolicy,
+        OverflowPolicy, QueueConfig, ReplayFrom, SubscriptionError, WaitError,
+    };
+}
+
+/// Provide helpers for front-ends shared by multiple clients
+pub mod session {
+    pub use crate::session::{
+        call, cleanup, event_frame, response_frame, send, split_id, ClientResources, CommandSender,
+    };
+}
+
 /// Provide objects referenced by some categories
 pub mod common {
     pub use crate::domain::webrtc::common::value_object::*;
@@ -15,9 +41,24 @@ pub mod data {
     pub use crate::domain::webrtc::data::value_object::*;
 }
 
+/// Provide objects related to fan-out of a local media source
+pub mod fanout {
+    pub use crate::domain::fanout::entity::*;
+    pub use crate::domain::fanout::value_object::*;
+}
+
+/// Provide objects related to event listeners
+pub mod listener {
+    pub use crate::domain::listener::entity::{
+        ListenerInfo, ListenerKind, ListenerList, ListenerStopped, ListenerTarget,
+    };
+    pub use crate::domain::listener::retry::RetryPolicy;
+}
+
 /// Provide objects related to Data-based APIs
 pub mod media {
     pub use crate::domain::webrtc::media::entity::*;
+    pub use crate::domain::webrtc::media::preset::{validate_constraints, MediaPreset};
     pub use crate::domain::webrtc::media::value_object::*;
 }
 
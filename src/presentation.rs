@@ -1,6 +1,32 @@
+use serde::Deserialize;
+
 use crate::application::dto::request_message::ServiceParams;
+use crate::application::dto::response_message::EventEnvelope;
+use crate::hub::AwaitParams;
 use crate::{error, ResponseResult};
 
+// AWAITはUseCaseではなく、eventを配信するlib.rs内で処理するので、ServiceParamsとは別にparseする
+// AWAIT commandでない場合はNoneを返す
+pub(crate) fn format_await_json(json_str: &str) -> Option<Result<AwaitParams, error::Error>> {
+    #[derive(Deserialize)]
+    struct Command {
+        r#type: String,
+        command: String,
+        #[serde(default)]
+        params: serde_json::Value,
+    }
+
+    let command = serde_json::from_str::<Command>(json_str).ok()?;
+    if command.r#type != "LISTENER" || command.command != "AWAIT" {
+        return None;
+    }
+    let params = serde_json::from_value::<AwaitParams>(command.params).map_err(|e| {
+        let message = format!("Presentation layer received invalid json {:?}", e);
+        error::Error::create_local_error(&message)
+    });
+    Some(params)
+}
+
 pub async fn format_input_json(json_str: &str) -> Result<ServiceParams, error::Error> {
     serde_json::from_str::<ServiceParams>(json_str).map_err(|e| {
         let message = format!("Presentation layer received invalid json {:?}", e);
@@ -43,6 +69,47 @@ mod format_input_json_test {
     }
 }
 
+#[cfg(test)]
+mod format_await_json_test {
+    use super::*;
+
+    #[test]
+    fn format_await() {
+        let json = r#"{
+        "type": "LISTENER",
+        "command": "AWAIT",
+        "params": {
+            "kind": "DATA",
+            "event": "OPEN",
+            "resource_id": "dc-4995f372-fb6a-4196-b30a-ce11e5c7f56c",
+            "timeout": 5000
+        }
+    }"#;
+
+        let params = format_await_json(json).unwrap().unwrap();
+        assert_eq!(params.timeout, 5000);
+        assert_eq!(params.from_seq, None);
+        assert_eq!(params.filter.event, Some("OPEN".to_string()));
+    }
+
+    #[test]
+    fn format_other_command() {
+        // AWAIT以外はServiceParamsとしてparseする
+        let json = r#"{"type": "LISTENER", "command": "LIST"}"#;
+        assert!(format_await_json(json).is_none());
+    }
+
+    #[test]
+    fn format_invalid_await() {
+        let json = r#"{"type": "LISTENER", "command": "AWAIT", "params": {}}"#;
+        assert!(format_await_json(json).unwrap().is_err());
+    }
+}
+
 pub fn serialize_service_params(params: &ResponseResult) -> String {
     serde_json::to_string(params).unwrap()
 }
+
+pub fn serialize_event(event: &EventEnvelope) -> String {
+    serde_json::to_string(event).unwrap()
+}
@@ -0,0 +1,221 @@
+// PythonからこのcrateのロジックでWebRTC Gatewayを操作するためのmodule
+// maturinでビルドし、`import skyway_webrtc_gateway_caller`として利用する
+//
+// dictとJSONメッセージの相互変換はPythonのjson moduleで行い、
+// crateに対してはrun()が返すSender, Receiverと同じJSONメッセージをやり取りする。
+// 非同期処理はpyo3-asyncioが保持するtokio runtime上で行う。
+
+use std::sync::Arc;
+
+use pyo3::exceptions::PyStopAsyncIteration;
+use pyo3::prelude::*;
+use pyo3_asyncio::tokio::{future_into_py, get_runtime};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::session::{self, CommandSender};
+
+/// Caller operating WebRTC Gateway, constructed with the base URL of WebRTC Gateway
+#[pyclass(name = "Caller")]
+pub struct PyCaller {
+    message_tx: CommandSender,
+    events: Arc<Mutex<mpsc::Receiver<String>>>,
+}
+
+#[pymethods]
+impl PyCaller {
+    #[new]
+    fn new(py: Python, base_url: &str) -> Self {
+        let (message_tx, receiver) =
+            py.allow_threads(|| get_runtime().block_on(crate::run(base_url)));
+        PyCaller {
+            message_tx,
+            events: Arc::new(Mutex::new(receiver)),
+        }
+    }
+
+    /// Give a command dict and await the result dict
+    fn send<'p>(&self, py: Python<'p>, command: &PyAny) -> PyResult<&'p PyAny> {
+        let message = dumps(py, command)?;
+        let message_tx = self.message_tx.clone();
+        future_into_py(py, async move {
+            let response = session::call(&message_tx, message).await;
+            Python::with_gil(|py| loads(py, &response))
+        })
+    }
+
+    /// Give a command dict and block until the result dict is returned
+    fn send_blocking(&self, py: Python, command: &PyAny) -> PyResult<PyObject> {
+        let message = dumps(py, command)?;
+        let message_tx = self.message_tx.clone();
+        // 結果を待つ間、他のPython threadが動けるようGILを解放する
+        let response =
+            py.allow_threads(|| get_runtime().block_on(session::call(&message_tx, message)));
+        loads(py, &response)
+    }
+
+    /// Async iterator of event dicts
+    fn events(&self) -> PyEventIterator {
+        PyEventIterator {
+            events: self.events.clone(),
+        }
+    }
+}
+
+/// Async iterator returned from `Caller.events()`. It finishes when the crate stops
+#[pyclass(name = "EventIterator")]
+pub struct PyEventIterator {
+    events: Arc<Mutex<mpsc::Receiver<String>>>,
+}
+
+#[pymethods]
+impl PyEventIterator {
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __anext__(&self, py: Python) -> PyResult<Option<PyObject>> {
+        let events = self.events.clone();
+        let event = future_into_py(py, async move {
+            // 複数のiteratorから同時に読まれた場合も、eventは一方にのみ渡される
+            let event = events.lock().await.recv().await;
+            match event {
+                Some(event) => Python::with_gil(|py| loads(py, &event)),
+                None => Err(PyStopAsyncIteration::new_err("event stream was closed")),
+            }
+        })?;
+        Ok(Some(event.into()))
+    }
+}
+
+fn dumps(py: Python, command: &PyAny) -> PyResult<String> {
+    py.import("json")?
+        .call_method1("dumps", (command,))?
+        .extract()
+}
+
+fn loads(py: Python, message: &str) -> PyResult<PyObject> {
+    Ok(py.import("json")?.call_method1("loads", (message,))?.into())
+}
+
+/// Python module exporting `Caller`
+#[pymodule]
+fn skyway_webrtc_gateway_caller(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyCaller>()?;
+    module.add_class::<PyEventIterator>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_python {
+    use pyo3::types::PyDict;
+    use serde_json::{json, Value};
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    // 受け取ったJSONメッセージを"result"に入れて返すcrateの代わりに接続したCallerを生成する
+    // eventsは与えたeventを返した後に終了する
+    fn caller(events: Vec<Value>) -> PyCaller {
+        let (message_tx, mut message_rx) = mpsc::channel::<(oneshot::Sender<String>, String)>(10);
+        get_runtime().spawn(async move {
+            while let Some((result_tx, message)) = message_rx.recv().await {
+                let message: Value = serde_json::from_str(&message).unwrap();
+                let response = json!({"is_success": true, "result": message});
+                let _ = result_tx.send(response.to_string());
+            }
+        });
+        let (event_tx, event_rx) = mpsc::channel(10);
+        for event in events {
+            event_tx.try_send(event.to_string()).unwrap();
+        }
+        PyCaller {
+            message_tx,
+            events: Arc::new(Mutex::new(event_rx)),
+        }
+    }
+
+    // Python上でcodeを実行し、変数resultの値をJSONとして取り出す
+    fn run(caller: PyCaller, code: &str) -> Value {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            // async defの中からも参照できるよう、globalsとして与える
+            let globals = PyDict::new(py);
+            globals
+                .set_item("__builtins__", py.import("builtins").unwrap())
+                .unwrap();
+            globals
+                .set_item("caller", Py::new(py, caller).unwrap())
+                .unwrap();
+            py.run(code, Some(globals), None)
+                .map_err(|e| e.print(py))
+                .unwrap();
+            let result = globals.get_item("result").unwrap();
+            serde_json::from_str(&dumps(py, result).unwrap()).unwrap()
+        })
+    }
+
+    #[test]
+    fn convert() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let command = json!({
+                "type": "PEER",
+                "command": "CREATE",
+                "params": {"key": "key", "turn": false, "id": 1}
+            });
+            // dictはJSONメッセージに変換され、JSONメッセージはdictに戻る
+            let dict = loads(py, &command.to_string()).unwrap();
+            assert!(dict.as_ref(py).is_instance_of::<PyDict>().unwrap());
+            let message = dumps(py, dict.as_ref(py)).unwrap();
+            assert_eq!(serde_json::from_str::<Value>(&message).unwrap(), command);
+        });
+    }
+
+    #[test]
+    fn send_blocking() {
+        let result = run(
+            caller(vec![]),
+            "result = caller.send_blocking({'type': 'PEER', 'command': 'STATUS'})",
+        );
+        assert_eq!(
+            result,
+            json!({"is_success": true, "result": {"type": "PEER", "command": "STATUS"}})
+        );
+    }
+
+    #[test]
+    fn send() {
+        let code = r#"
+import asyncio
+
+async def main():
+    return await caller.send({'type': 'DATA', 'command': 'DISCONNECT'})
+
+result = asyncio.run(main())
+"#;
+        let result = run(caller(vec![]), code);
+        assert_eq!(
+            result,
+            json!({"is_success": true, "result": {"type": "DATA", "command": "DISCONNECT"}})
+        );
+    }
+
+    #[test]
+    fn events() {
+        let code = r#"
+import asyncio
+
+async def main():
+    return [event async for event in caller.events()]
+
+result = asyncio.run(main())
+"#;
+        // crateが停止するとiteratorも終了する
+        let events = vec![
+            json!({"type": "PEER", "event": "OPEN"}),
+            json!({"type": "PEER", "event": "CLOSE"}),
+        ];
+        let result = run(caller(events.clone()), code);
+        assert_eq!(result, Value::Array(events));
+    }
+}
@@ -0,0 +1,298 @@
+// WebSocketなどのフロントエンドから、複数のクライアントがcrateを共有する際に利用する機能
+// クライアントごとに生成したリソースを記録し、切断時にまとめて削除する
+// eventも、そのクライアントが生成したPeerに関するもののみを配信できるようにする
+// 応答とeventを同じstreamで返すフロントエンドのために、typeとidで区別する形式も提供する
+
+use futures::future::{self, BoxFuture, FutureExt};
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::application::dto::response_message::{
+    DataResponse, EventEnvelope, MediaResponse, PeerResponse, ResponseMessage, ResponseResult,
+};
+use crate::domain::webrtc::common::value_object::{PeerInfo, SerializableSocket};
+use crate::domain::webrtc::data::value_object::DataId;
+use crate::domain::webrtc::media::value_object::{MediaId, RtcpId};
+use crate::error;
+
+/// Sender returned from `run` to give JSON messages for operation
+pub type CommandSender = mpsc::Sender<(oneshot::Sender<String>, String)>;
+
+/// Send a JSON message for operation and wait for the result.
+/// It returns an error message in the same JSON format when the crate has already stopped.
+pub async fn call(sender: &CommandSender, message: String) -> String {
+    send(sender, message).await.await
+}
+
+/// Send a JSON message for operation, and return a future resolved with the result.
+/// Messages reach the crate in the order this function is awaited, while their results can be awaited concurrently.
+pub async fn send(sender: &CommandSender, message: String) -> BoxFuture<'static, String> {
+    let (tx, rx) = oneshot::channel();
+    if sender.send((tx, message)).await.is_err() {
+        return future::ready(stopped_message()).boxed();
+    }
+    rx.map(|result| result.unwrap_or_else(|_| stopped_message()))
+        .boxed()
+}
+
+fn stopped_message() -> String {
+    let message = ResponseResult::Error(error::STOPPED_MESSAGE.into());
+    serde_json::to_string(&message).unwrap()
+}
+
+/// Remove "id" given by a client from a JSON message, and return it with the message given to the crate.
+/// A message which is not a JSON object is returned as is, so that the crate returns an error for it.
+pub fn split_id(message: &str) -> (Value, String) {
+    match serde_json::from_str::<Value>(message) {
+        Ok(Value::Object(mut map)) => {
+            let id = map.remove("id").unwrap_or(Value::Null);
+            (id, Value::Object(map).to_string())
+        }
+        _ => (Value::Null, message.to_string()),
+    }
+}
+
+/// Wrap a result as `{"type": "response", "id": <id>, "body": <ResponseResult>}`
+pub fn response_frame(id: Value, response: &str) -> Value {
+    json!({ "type": "response", "id": id, "body": parse(response) })
+}
+
+/// Wrap an event as `{"type": "event", "body": <EventEnvelope>}`
+pub fn event_frame(event: &str) -> Value {
+    json!({ "type": "event", "body": parse(event) })
+}
+
+// crateの出力は常にJSONだが、念のため解釈できない場合は文字列として出力する
+fn parse(message: &str) -> Value {
+    serde_json::from_str(message).unwrap_or_else(|_| Value::String(message.to_string()))
+}
+
+/// Resources created by a client of a front-end, to release them when the client leaves
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientResources {
+    peers: Vec<PeerInfo>,
+    data: Vec<DataId>,
+    media: Vec<MediaId>,
+    rtcp: Vec<RtcpId>,
+}
+
+impl ClientResources {
+    /// Record resources created or deleted by a command from its result
+    pub fn record(&mut self, response: &str) {
+        let message = match ResponseResult::from_str(response) {
+            Ok(ResponseResult::Success(message)) => message,
+            _ => return,
+        };
+        match message {
+            ResponseMessage::Peer(PeerResponse::Create(peer_info)) => self.peers.push(peer_info),
+            ResponseMessage::Peer(PeerResponse::Delete(peer_info)) => {
+                self.peers.retain(|peer| peer != &peer_info)
+            }
+            ResponseMessage::Data(DataResponse::Create(socket)) => {
+                self.data.extend(socket.get_id())
+            }
+            ResponseMessage::Data(DataResponse::Delete(wrapper)) => {
+                self.data.retain(|data_id| data_id != &wrapper.data_id)
+            }
+            ResponseMessage::Media(MediaResponse::ContentCreate(socket)) => {
+                self.media.extend(socket.get_id())
+            }
+            ResponseMessage::Media(MediaResponse::ContentDelete(wrapper)) => {
+                self.media.retain(|media_id| media_id != &wrapper.media_id)
+            }
+            ResponseMessage::Media(MediaResponse::RtcpCreate(socket)) => {
+                self.rtcp.extend(socket.get_id())
+            }
+            ResponseMessage::Media(MediaResponse::RtcpDelete(wrapper)) => {
+                self.rtcp.retain(|rtcp_id| rtcp_id != &wrapper.rtcp_id)
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether an event is related to a peer created by this client
+    pub fn owns_event(&self, event: &str) -> bool {
+        let peer_id = match event.parse::<EventEnvelope>() {
+            Ok(EventEnvelope {
+                peer_id: Some(peer_id),
+                ..
+            }) => peer_id,
+            _ => return false,
+        };
+        self.peers
+            .iter()
+            .any(|peer| peer.peer_id().as_str() == peer_id)
+    }
+
+    /// JSON messages to delete the remaining resources.
+    /// Sockets are deleted before peers
+    pub fn cleanup_commands(&self) -> Vec<String> {
+        let data = self.data.iter().map(
+            |data_id| json!({"type": "DATA", "command": "DELETE", "params": {"data_id": data_id}}),
+        );
+        let media = self.media.iter().map(|media_id| {
+            json!({"type": "MEDIA", "command": "CONTENT_DELETE", "params": {"media_id": media_id}})
+        });
+        let rtcp = self.rtcp.iter().map(|rtcp_id| {
+            json!({"type": "MEDIA", "command": "RTCP_DELETE", "params": {"rtcp_id": rtcp_id}})
+        });
+        // Peerを削除すると、所有するConnectionの監視も終了する
+        let peers = self
+            .peers
+            .iter()
+            .map(|peer_info| json!({"type": "PEER", "command": "DELETE", "params": peer_info}));
+        data.chain(media)
+            .chain(rtcp)
+            .chain(peers)
+            .map(|command| command.to_string())
+            .collect()
+    }
+}
+
+/// Delete the resources created by a client. Errors are ignored
+pub async fn cleanup(sender: &CommandSender, resources: &ClientResources) {
+    for command in resources.cleanup_commands() {
+        let _ = call(sender, command).await;
+    }
+}
+
+#[cfg(test)]
+mod test_frame {
+    use super::*;
+
+    #[test]
+    fn split() {
+        let (id, command) = split_id(r#"{"id": 3, "type": "PEER", "command": "CREATE"}"#);
+        assert_eq!(id, json!(3));
+        assert_eq!(
+            serde_json::from_str::<Value>(&command).unwrap(),
+            json!({ "type": "PEER", "command": "CREATE" })
+        );
+
+        // JSONでない入力はそのままcrateに与える
+        assert_eq!(split_id("hoge"), (Value::Null, "hoge".to_string()));
+    }
+
+    #[test]
+    fn tagged_frames() {
+        let response = r#"{"is_success": false, "result": "error"}"#;
+        assert_eq!(
+            response_frame(json!("request-1"), response),
+            json!({
+                "type": "response",
+                "id": "request-1",
+                "body": { "is_success": false, "result": "error" }
+            })
+        );
+        assert_eq!(event_frame("{}"), json!({ "type": "event", "body": {} }));
+    }
+}
+
+#[cfg(test)]
+mod test_client_resources {
+    use super::*;
+
+    fn peer_create() -> String {
+        let peer_info =
+            PeerInfo::try_create("peer_id", "pt-9749250e-d157-4f80-9ee2-359ce8524308").unwrap();
+        serde_json::to_string(&PeerResponse::Create(peer_info).create_response_message()).unwrap()
+    }
+
+    fn data_create() -> String {
+        use crate::domain::webrtc::common::value_object::SocketInfo;
+
+        let socket = SocketInfo::<DataId>::try_create(
+            Some("da-50a32bab-b3d9-4913-8e20-f79c90a6a211".into()),
+            "127.0.0.1",
+            10001,
+        )
+        .unwrap();
+        serde_json::to_string(&DataResponse::Create(socket).create_response_message()).unwrap()
+    }
+
+    fn event(peer_id: &str) -> String {
+        format!(
+            r#"{{
+                "seq": 1,
+                "timestamp": 0,
+                "source": {{"kind": "PEER", "resource_id": "{0}"}},
+                "peer_id": "{0}",
+                "is_success": true,
+                "result": {{
+                    "request_type": "PEER",
+                    "command": "EVENT",
+                    "event": "CLOSE",
+                    "params": {{
+                        "peer_id": "{0}",
+                        "token": "pt-9749250e-d157-4f80-9ee2-359ce8524308"
+                    }}
+                }}
+            }}"#,
+            peer_id
+        )
+    }
+
+    #[test]
+    fn cleanup_commands() {
+        let mut resources = ClientResources::default();
+        resources.record(&peer_create());
+        resources.record(&data_create());
+        // 失敗したcommandの結果は無視する
+        resources.record(r#"{"is_success": false, "result": "error"}"#);
+
+        let commands: Vec<Value> = resources
+            .cleanup_commands()
+            .iter()
+            .map(|command| serde_json::from_str(command).unwrap())
+            .collect();
+        assert_eq!(commands.len(), 2);
+        // socketを先に削除する
+        assert_eq!(
+            commands[0],
+            json!({
+                "type": "DATA",
+                "command": "DELETE",
+                "params": {"data_id": "da-50a32bab-b3d9-4913-8e20-f79c90a6a211"}
+            })
+        );
+        assert_eq!(commands[1]["type"], "PEER");
+        assert_eq!(commands[1]["params"]["peer_id"], "peer_id");
+    }
+
+    #[test]
+    fn owns_event() {
+        let mut resources = ClientResources::default();
+        assert!(!resources.owns_event(&event("peer_id")));
+
+        resources.record(&peer_create());
+        assert!(resources.owns_event(&event("peer_id")));
+        assert!(!resources.owns_event(&event("other_peer")));
+    }
+}
+
+#[cfg(test)]
+mod test_send {
+    use super::*;
+
+    #[tokio::test]
+    async fn send_in_order() {
+        let (sender, mut receiver) = mpsc::channel(10);
+        // 送信は呼び出した順に行われ、結果は後から待てる
+        let first = send(&sender, "first".into()).await;
+        let second = send(&sender, "second".into()).await;
+        let (first_tx, message) = receiver.recv().await.unwrap();
+        assert_eq!(message, "first");
+        let (second_tx, message) = receiver.recv().await.unwrap();
+        assert_eq!(message, "second");
+
+        // 結果は完了した順に受け取れる
+        second_tx.send("second result".into()).unwrap();
+        assert_eq!(second.await, "second result");
+        first_tx.send("first result".into()).unwrap();
+        assert_eq!(first.await, "first result");
+
+        // crateが停止している場合はエラーを返す
+        drop(receiver);
+        assert_eq!(send(&sender, "third".into()).await.await, stopped_message());
+    }
+}
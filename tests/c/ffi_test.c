@@ -0,0 +1,59 @@
+/*
+ * Test program of the C interface. It does not need a running WebRTC Gateway.
+ * `cargo test --features ffi --test ffi` builds and runs it. To run it by hand:
+ *
+ * cargo build --lib --features ffi
+ * cc tests/c/ffi_test.c -Iinclude -Ltarget/debug -lskyway_webrtc_gateway_caller -lpthread -ldl -lm -o target/ffi_test
+ * LD_LIBRARY_PATH=target/debug ./target/ffi_test
+ */
+
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "skyway_caller.h"
+
+struct result {
+    volatile int received;
+    char response[1024];
+};
+
+static void on_response(const char *json, void *user_data)
+{
+    struct result *result = user_data;
+
+    /* the string is valid only inside the callback, so copy it */
+    strncpy(result->response, json, sizeof(result->response) - 1);
+    result->received = 1;
+}
+
+int main(void)
+{
+    struct result result = {0};
+    char buf[16];
+    int i;
+
+    SkywayCaller *handle = skyway_caller_start("http://localhost:8000");
+    assert(handle != NULL);
+
+    /* an invalid message is answered with an error */
+    assert(skyway_caller_send(handle, "invalid", on_response, &result) == 0);
+    for (i = 0; i < 100 && !result.received; i++) {
+        usleep(10 * 1000);
+    }
+    assert(result.received);
+    assert(strstr(result.response, "\"is_success\":false") != NULL);
+
+    /* no event has occurred yet */
+    assert(skyway_caller_poll_event(handle, buf, sizeof(buf)) == 0);
+
+    /* invalid arguments */
+    assert(skyway_caller_send(handle, NULL, NULL, NULL) == -1);
+    assert(skyway_caller_poll_event(NULL, buf, sizeof(buf)) == SKYWAY_CALLER_CLOSED);
+    assert(skyway_caller_start(NULL) == NULL);
+
+    skyway_caller_stop(handle);
+    printf("ok\n");
+    return 0;
+}
@@ -5,7 +5,7 @@ use mockito::mock;
 use skyway_webrtc_gateway_caller::prelude::data::*;
 use skyway_webrtc_gateway_caller::prelude::peer::*;
 use skyway_webrtc_gateway_caller::prelude::response_parser::{
-    PeerResponse, ResponseMessage, ResponseResult,
+    EventEnvelope, PeerResponse, ResponseMessage, ResponseResult,
 };
 use skyway_webrtc_gateway_caller::*;
 
@@ -202,12 +202,27 @@ async fn test_create_peer() {
     mock_event_api.assert();
 
     // 1つめのEVENTの取得
-    let result = event_rx.recv().await.unwrap();
-    assert_eq!(result, serde_json::to_string(&expected_connect).unwrap());
+    // eventは通知順の番号と発生元の情報を含む
+    let result = event_rx
+        .recv()
+        .await
+        .unwrap()
+        .parse::<EventEnvelope>()
+        .unwrap();
+    assert_eq!(result.seq, 1);
+    assert_eq!(result.source.resource_id, "hoge");
+    assert_eq!(result.peer_id, Some("hoge".to_string()));
+    assert_eq!(result.event, expected_connect);
 
     // 2つめのEVENTの取得
-    let result = event_rx.recv().await.unwrap();
-    assert_eq!(result, serde_json::to_string(&expected_close).unwrap());
+    let result = event_rx
+        .recv()
+        .await
+        .unwrap()
+        .parse::<EventEnvelope>()
+        .unwrap();
+    assert_eq!(result.seq, 2);
+    assert_eq!(result.event, expected_close);
 
     // 3つめは来ない
 }
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use mockito::mock;
+
+use skyway_webrtc_gateway_caller::prelude::hub::{OverflowPolicy, QueueConfig};
+use skyway_webrtc_gateway_caller::prelude::response_parser::{
+    EventEnvelope, PeerResponse, ResponseMessage, ResponseResult,
+};
+use skyway_webrtc_gateway_caller::prelude::Config;
+use skyway_webrtc_gateway_caller::*;
+
+const PEER_ID: &str = "dropped";
+const TOKEN: &str = "pt-9749250e-d157-4f80-9ee2-359ce8524308";
+
+fn create_event_message(event: &str) -> String {
+    format!(
+        r#"{{
+            "event": "{}",
+            "params": {{
+                "peer_id": "{}",
+                "token": "{}"
+            }}
+        }}"#,
+        event, PEER_ID, TOKEN
+    )
+}
+
+// Receiverが受け取らない間に溢れたeventの数は、次に受け取るeventのdroppedとして通知される
+#[tokio::test]
+async fn test_event_dropped() {
+    // Receiverのqueueを1つにして、溢れたeventは古いものから破棄する
+    let mut config = Config::default();
+    config.event_channel.receiver_queue = QueueConfig {
+        capacity: 1,
+        overflow: OverflowPolicy::DropOldest,
+    };
+    let (message_tx, mut event_rx) = run_with_config(&mockito::server_url(), config).await;
+
+    // GET /peers/{peer_id}/eventsに対応するmock
+    // 最初はCreatePeerServiceのためにOPENを返し、その後はOPENを4回返した後CLOSEを返す
+    let _mock_event_api = {
+        let counter = std::sync::Mutex::new(0usize);
+        let bind_url = format!("/peers/{}/events?token={}", PEER_ID, TOKEN);
+        mock("GET", bind_url.as_str())
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_header("content-type", "application/json")
+            .with_body_from_fn(move |w| {
+                let mut count = counter.lock().unwrap();
+                *count += 1;
+                let event = if *count <= 5 { "OPEN" } else { "CLOSE" };
+                w.write_all(create_event_message(event).as_bytes())
+            })
+            .expect_at_least(6)
+            .create()
+    };
+
+    // POST /peersに対応するmock
+    let _mock_create_peer = mock("POST", "/peers")
+        .with_status(reqwest::StatusCode::CREATED.as_u16() as usize)
+        .with_header("content-type", "application/json")
+        .with_body(format!(
+            r#"{{
+                "command_type": "PEERS_CREATE",
+                "params": {{
+                    "peer_id": "{}",
+                    "token": "{}"
+                }}
+            }}"#,
+            PEER_ID, TOKEN
+        ))
+        .create();
+
+    let body = format!(
+        r#"{{
+            "type": "PEER",
+            "command": "CREATE",
+            "params": {{
+                "key": "api_key",
+                "domain": "localhost",
+                "peer_id": "{}",
+                "turn": true
+            }}
+        }}"#,
+        PEER_ID
+    );
+    let (tx, rx) = tokio::sync::oneshot::channel::<String>();
+    let _ = message_tx.send((tx, body)).await;
+    let result = ResponseResult::from_str(&rx.await.unwrap());
+    assert!(matches!(
+        result,
+        Ok(ResponseResult::Success(ResponseMessage::Peer(
+            PeerResponse::Create(_)
+        )))
+    ));
+
+    // Receiverから読み出さずに、5つのeventが配信されるのを待つ
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // 受け取ったeventの数と、droppedで通知された数の合計は、配信されたeventの数と一致する
+    let mut received = 0;
+    let mut dropped = 0;
+    loop {
+        let event = tokio::time::timeout(Duration::from_secs(1), event_rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .parse::<EventEnvelope>()
+            .unwrap();
+        received += 1;
+        dropped += event.dropped.unwrap_or(0);
+        if event.seq == 5 {
+            break;
+        }
+    }
+    assert!(dropped > 0);
+    assert_eq!(received + dropped, 5);
+}
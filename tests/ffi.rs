@@ -0,0 +1,54 @@
+// C ABIのテスト
+// 共有ライブラリを生成し、tests/c/ffi_test.cをCのプログラムとしてビルドして実行する
+// WebRTC Gatewayは必要ない
+#![cfg(feature = "ffi")]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// cargo testのbuildと並行して実行できるよう、共有ライブラリは別のtarget directoryに生成する
+fn build_cdylib(target_dir: &Path) -> PathBuf {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+    let status = Command::new(cargo)
+        .args(["build", "--lib", "--features", "ffi"])
+        .arg("--target-dir")
+        .arg(target_dir)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "failed to build the shared library");
+    target_dir.join("debug")
+}
+
+#[test]
+fn c_program() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ffi");
+    let library_dir = build_cdylib(&target_dir);
+
+    let program = target_dir.join("ffi_test");
+    let status = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".into()))
+        .arg(manifest_dir.join("tests/c/ffi_test.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-L")
+        .arg(&library_dir)
+        .arg("-lskyway_webrtc_gateway_caller")
+        .args(["-lpthread", "-ldl", "-lm"])
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "failed to compile tests/c/ffi_test.c");
+
+    let output = Command::new(&program)
+        .env("LD_LIBRARY_PATH", &library_dir)
+        .output()
+        .expect("failed to run the C program");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}